@@ -0,0 +1,369 @@
+//! Derive macros for the [`deepseek-rust`](https://docs.rs/deepseek-rust) client library
+//!
+//! Use through the `derive` feature of `deepseek-rust`:
+//!
+//! ```ignore
+//! use deepseek_rust::DeepSeekTool;
+//! use serde::Deserialize;
+//!
+//! /// Get the current weather for a city
+//! #[derive(Deserialize, DeepSeekTool)]
+//! struct GetWeather {
+//!     /// City name, e.g. "Paris"
+//!     city: String,
+//!     /// Temperature unit
+//!     unit: Option<String>,
+//! }
+//! ```
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Expr, ExprLit, Fields, Lit,
+    LitStr, Meta, Token,
+};
+
+/// Derive `ToolParameter` and `DeepSeekTool` for a struct with named fields
+///
+/// The function name defaults to the struct name in snake_case and the
+/// description to the struct's doc comment. Field doc comments become
+/// property descriptions; `Option` fields and fields marked
+/// `#[serde(default)]` are not required. Both can be overridden with
+/// `#[tool(name = "...", description = "...")]` on the struct.
+///
+/// Field `#[serde(rename = "...")]`, `#[serde(skip)]` and container
+/// `#[serde(rename_all = "...")]` attributes are honoured so the schema
+/// matches what deserialization accepts. The properties of a
+/// `#[serde(flatten)]` field are merged into the struct's own. Generic
+/// type parameters must implement `ToolParameter`.
+#[proc_macro_derive(DeepSeekTool, attributes(tool))]
+pub fn derive_deepseek_tool(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(mut input: DeriveInput) -> syn::Result<TokenStream2> {
+    let type_params: Vec<_> = input.generics.type_params().map(|param| param.ident.clone()).collect();
+    let where_clause = input.generics.make_where_clause();
+    for param in type_params {
+        where_clause
+            .predicates
+            .push(parse_quote!(#param: ::deepseek_rust::models::tool::ToolParameter));
+    }
+
+    let ident = &input.ident;
+    let named = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect(),
+            Fields::Unit => Vec::new(),
+            Fields::Unnamed(_) => {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "DeepSeekTool can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "DeepSeekTool can only be derived for structs",
+            ))
+        }
+    };
+
+    let tool = ToolAttrs::parse(&input.attrs)?;
+    let container = SerdeAttrs::parse(&input.attrs)?;
+
+    let name = tool
+        .name
+        .unwrap_or_else(|| to_snake_case(&ident.to_string()));
+    let description = tool.description.or_else(|| doc_string(&input.attrs));
+
+    let mut properties = Vec::new();
+    for field in named {
+        let serde = SerdeAttrs::parse(&field.attrs)?;
+        if serde.skip {
+            continue;
+        }
+
+        let ty = &field.ty;
+        let has_default = serde.default || container.default;
+        if serde.flatten {
+            properties.push(quote! {
+                {
+                    let inner = <#ty as ::deepseek_rust::models::tool::ToolParameter>::json_schema();
+                    if let ::std::option::Option::Some(inner_properties) =
+                        inner.get("properties").and_then(Value::as_object)
+                    {
+                        properties.extend(inner_properties.clone());
+                    }
+                    if <#ty as ::deepseek_rust::models::tool::ToolParameter>::REQUIRED && !#has_default {
+                        if let ::std::option::Option::Some(inner_required) =
+                            inner.get("required").and_then(Value::as_array)
+                        {
+                            required.extend(inner_required.iter().cloned());
+                        }
+                    }
+                }
+            });
+            continue;
+        }
+
+        let field_ident = field.ident.as_ref().expect("named field");
+        let raw = field_ident.to_string();
+        let raw = raw.trim_start_matches("r#");
+        let key = match serde.rename {
+            Some(rename) => rename,
+            None => apply_rename_all(raw, container.rename_all.as_deref())
+                .map_err(|msg| syn::Error::new_spanned(field_ident, msg))?,
+        };
+
+        let describe = doc_string(&field.attrs).map(|doc| {
+            quote! {
+                if let ::std::option::Option::Some(object) = schema.as_object_mut() {
+                    object.insert(
+                        "description".to_string(),
+                        ::deepseek_rust::__private::serde_json::Value::String(#doc.to_string()),
+                    );
+                }
+            }
+        });
+
+        properties.push(quote! {
+            {
+                let mut schema = <#ty as ::deepseek_rust::models::tool::ToolParameter>::json_schema();
+                #describe
+                properties.insert(#key.to_string(), schema);
+                if <#ty as ::deepseek_rust::models::tool::ToolParameter>::REQUIRED && !#has_default {
+                    required.push(::deepseek_rust::__private::serde_json::Value::String(#key.to_string()));
+                }
+            }
+        });
+    }
+
+    let with_description = description.map(|doc| quote! { .with_description(#doc) });
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::deepseek_rust::models::tool::ToolParameter for #ident #ty_generics #where_clause {
+            fn json_schema() -> ::deepseek_rust::__private::serde_json::Value {
+                use ::deepseek_rust::__private::serde_json::{Map, Value};
+
+                let mut properties = Map::new();
+                let mut required: ::std::vec::Vec<Value> = ::std::vec::Vec::new();
+                #(#properties)*
+
+                let mut schema = Map::new();
+                schema.insert("type".to_string(), Value::String("object".to_string()));
+                schema.insert("properties".to_string(), Value::Object(properties));
+                schema.insert("required".to_string(), Value::Array(required));
+                Value::Object(schema)
+            }
+        }
+
+        impl #impl_generics ::deepseek_rust::models::tool::DeepSeekTool for #ident #ty_generics #where_clause {
+            fn function_definition() -> ::deepseek_rust::models::tool::FunctionDefinition {
+                ::deepseek_rust::models::tool::FunctionDefinition::new(#name)
+                    #with_description
+                    .with_parameters(<Self as ::deepseek_rust::models::tool::ToolParameter>::json_schema())
+            }
+        }
+    })
+}
+
+/// `#[tool(...)]` options on the struct
+#[derive(Default)]
+struct ToolAttrs {
+    name: Option<String>,
+    description: Option<String>,
+}
+
+impl ToolAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut parsed = Self::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("tool")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    parsed.name = Some(meta.value()?.parse::<LitStr>()?.value());
+                    Ok(())
+                } else if meta.path.is_ident("description") {
+                    parsed.description = Some(meta.value()?.parse::<LitStr>()?.value());
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `name` or `description`"))
+                }
+            })?;
+        }
+        Ok(parsed)
+    }
+}
+
+/// The subset of `#[serde(...)]` options that affect the schema
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<String>,
+    skip: bool,
+    default: bool,
+    flatten: bool,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut parsed = Self::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+            let metas = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+            for meta in metas {
+                match &meta {
+                    Meta::Path(path) if path.is_ident("skip") || path.is_ident("skip_deserializing") => {
+                        parsed.skip = true;
+                    }
+                    Meta::Path(path) if path.is_ident("default") => parsed.default = true,
+                    Meta::Path(path) if path.is_ident("flatten") => parsed.flatten = true,
+                    Meta::NameValue(nv) if nv.path.is_ident("default") => parsed.default = true,
+                    Meta::NameValue(nv) if nv.path.is_ident("rename") => {
+                        parsed.rename = string_value(&nv.value);
+                    }
+                    Meta::NameValue(nv) if nv.path.is_ident("rename_all") => {
+                        parsed.rename_all = string_value(&nv.value);
+                    }
+                    Meta::List(list) if list.path.is_ident("rename") || list.path.is_ident("rename_all") => {
+                        let inner = list.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+                        for item in inner {
+                            if let Meta::NameValue(nv) = item {
+                                if nv.path.is_ident("deserialize") {
+                                    let value = string_value(&nv.value);
+                                    if list.path.is_ident("rename") {
+                                        parsed.rename = value;
+                                    } else {
+                                        parsed.rename_all = value;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+fn string_value(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) => Some(s.value()),
+        _ => None,
+    }
+}
+
+/// Join `///` doc comment lines into a single description
+fn doc_string(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|a| a.path().is_ident("doc"))
+        .filter_map(|a| match &a.meta {
+            Meta::NameValue(nv) => string_value(&nv.value),
+            _ => None,
+        })
+        .map(|line| line.trim().to_string())
+        .collect();
+
+    let doc = lines.join(" ").trim().to_string();
+    if doc.is_empty() {
+        None
+    } else {
+        Some(doc)
+    }
+}
+
+/// Split an identifier into lowercase words
+fn words(ident: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let chars: Vec<char> = ident.chars().collect();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' || c == '-' {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+
+        let boundary = c.is_uppercase()
+            && i > 0
+            && (chars[i - 1].is_lowercase()
+                || chars[i - 1].is_ascii_digit()
+                || (chars[i - 1].is_uppercase() && chars.get(i + 1).is_some_and(|n| n.is_lowercase())));
+        if boundary && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        current.extend(c.to_lowercase());
+    }
+
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn to_snake_case(ident: &str) -> String {
+    words(ident).join("_")
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Apply a serde `rename_all` rule to a snake_case field name
+fn apply_rename_all(field: &str, rule: Option<&str>) -> Result<String, String> {
+    let words = words(field);
+    Ok(match rule {
+        None => field.to_string(),
+        Some("lowercase") => field.to_lowercase(),
+        Some("UPPERCASE") => field.to_uppercase(),
+        Some("snake_case") => words.join("_"),
+        Some("SCREAMING_SNAKE_CASE") => words.join("_").to_uppercase(),
+        Some("kebab-case") => words.join("-"),
+        Some("SCREAMING-KEBAB-CASE") => words.join("-").to_uppercase(),
+        Some("PascalCase") => words.iter().map(|w| capitalize(w)).collect(),
+        Some("camelCase") => words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| if i == 0 { w.clone() } else { capitalize(w) })
+            .collect(),
+        Some(other) => return Err(format!("unsupported serde rename_all rule `{}`", other)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_snake_case() {
+        assert_eq!(to_snake_case("GetWeather"), "get_weather");
+        assert_eq!(to_snake_case("HTTPRequest"), "http_request");
+        assert_eq!(to_snake_case("Search2Web"), "search2_web");
+        assert_eq!(to_snake_case("already_snake"), "already_snake");
+    }
+
+    #[test]
+    fn test_apply_rename_all() {
+        assert_eq!(apply_rename_all("max_results", Some("camelCase")).unwrap(), "maxResults");
+        assert_eq!(apply_rename_all("max_results", Some("PascalCase")).unwrap(), "MaxResults");
+        assert_eq!(apply_rename_all("max_results", Some("kebab-case")).unwrap(), "max-results");
+        assert_eq!(apply_rename_all("max_results", Some("SCREAMING_SNAKE_CASE")).unwrap(), "MAX_RESULTS");
+        assert_eq!(apply_rename_all("max_results", None).unwrap(), "max_results");
+        assert!(apply_rename_all("max_results", Some("Train-Case")).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! Tests for the DeepSeekTool derive macro
+
+use deepseek_rust::{DeepSeekError, DeepSeekTool, FunctionCall, ToolParameter};
+use serde::Deserialize;
+use serde_json::json;
+
+/// Get the current weather
+/// for a city
+#[derive(Debug, Deserialize, DeepSeekTool, PartialEq)]
+struct GetWeather {
+    /// City name, e.g. "Paris"
+    city: String,
+    /// Temperature unit
+    unit: Option<Unit>,
+    #[serde(default)]
+    days: u32,
+    #[serde(skip)]
+    #[allow(dead_code)]
+    cache_key: String,
+}
+
+/// Temperature unit settings
+#[derive(Debug, Deserialize, DeepSeekTool, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct Unit {
+    use_celsius: bool,
+    #[serde(rename = "decimals")]
+    precision: Option<u8>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, DeepSeekTool)]
+#[tool(name = "search", description = "Search the web")]
+struct WebSearchArgs {
+    query: String,
+    tags: Vec<String>,
+}
+
+/// Paginated search
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, DeepSeekTool)]
+struct PagedSearch<F> {
+    query: String,
+    filter: F,
+    #[serde(flatten)]
+    page: Page,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, DeepSeekTool)]
+struct Page {
+    /// Page number
+    page: u32,
+    per_page: Option<u32>,
+}
+
+#[test]
+fn test_generic_struct_with_flatten() {
+    assert_eq!(
+        PagedSearch::<Vec<String>>::json_schema(),
+        json!({
+            "type": "object",
+            "properties": {
+                "query": {"type": "string"},
+                "filter": {"type": "array", "items": {"type": "string"}},
+                "page": {"type": "integer", "description": "Page number"},
+                "per_page": {"type": "integer"}
+            },
+            "required": ["query", "filter", "page"]
+        })
+    );
+    assert_eq!(PagedSearch::<bool>::function_definition().name, "paged_search");
+}
+
+#[test]
+fn test_function_definition_from_struct() {
+    let definition = GetWeather::function_definition();
+
+    assert_eq!(definition.name, "get_weather");
+    assert_eq!(definition.description.as_deref(), Some("Get the current weather for a city"));
+    assert_eq!(
+        definition.parameters.unwrap(),
+        json!({
+            "type": "object",
+            "properties": {
+                "city": {"type": "string", "description": "City name, e.g. \"Paris\""},
+                "unit": {
+                    "type": "object",
+                    "description": "Temperature unit",
+                    "properties": {
+                        "useCelsius": {"type": "boolean"},
+                        "decimals": {"type": "integer"}
+                    },
+                    "required": ["useCelsius"]
+                },
+                "days": {"type": "integer"}
+            },
+            "required": ["city"]
+        })
+    );
+    assert!(GetWeather::tool().validate().is_ok());
+}
+
+#[test]
+fn test_tool_attribute_overrides() {
+    let definition = WebSearchArgs::function_definition();
+
+    assert_eq!(definition.name, "search");
+    assert_eq!(definition.description.as_deref(), Some("Search the web"));
+    assert_eq!(
+        WebSearchArgs::json_schema()["properties"]["tags"],
+        json!({"type": "array", "items": {"type": "string"}})
+    );
+}
+
+#[test]
+fn test_parse_arguments_into_derived_struct() {
+    let call = FunctionCall {
+        name: "get_weather".to_string(),
+        arguments: r#"{"city": "Paris", "unit": {"useCelsius": true, "decimals": 1}}"#.to_string(),
+    };
+    let args: GetWeather = call.parse_arguments().unwrap();
+
+    assert_eq!(args.city, "Paris");
+    assert_eq!(args.unit, Some(Unit { use_celsius: true, precision: Some(1) }));
+
+    let bad = FunctionCall {
+        name: "get_weather".to_string(),
+        arguments: r#"{"city": 42}"#.to_string(),
+    };
+    assert!(matches!(bad.parse_arguments::<GetWeather>(), Err(DeepSeekError::ToolError(_))));
+}
+
+#[tokio::test]
+async fn test_register_typed_tool() {
+    use deepseek_rust::models::response::ToolCall;
+    use deepseek_rust::ToolRegistry;
+
+    let registry = ToolRegistry::new()
+        .register_tool(|args: GetWeather| async move { Ok(format!("Sunny in {}", args.city)) });
+
+    let call = |arguments: &str| ToolCall {
+        id: "call_1".to_string(),
+        r#type: "function".to_string(),
+        function: FunctionCall {
+            name: "get_weather".to_string(),
+            arguments: arguments.to_string(),
+        },
+    };
+
+    let result = registry.execute(&call(r#"{"city": "Paris"}"#)).await;
+    assert_eq!(result.output, "Sunny in Paris");
+
+    let result = registry.execute(&call(r#"{"city": 1}"#)).await;
+    assert!(result.is_error);
+    assert!(result.output.contains("Invalid arguments for function 'get_weather'"));
+}
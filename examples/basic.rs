@@ -0,0 +1,62 @@
+//! Basic usage examples for the DeepSeek Rust client
+//!
+//! Run with:
+//! ```bash
+//! DEEPSEEK_API_KEY=your_key cargo run --example basic
+//! ```
+
+use deepseek_rust::{DeepSeekClient, Model, Result};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    #[cfg(feature = "logging")]
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    // Create client from environment variables
+    let client = DeepSeekClient::from_env()?;
+
+    // Check that the API key works
+    client.test_connection().await?;
+    println!("✅ Connected to DeepSeek");
+
+    // Simple chat
+    let response = client
+        .chat()
+        .add_system_message("You are a helpful assistant. Answer briefly.")
+        .add_user_message("What is Rust's ownership model?")
+        .with_temperature(0.7)?
+        .with_max_tokens(200)
+        .send()
+        .await?;
+
+    if let Some(content) = response.get_content() {
+        println!("\n💬 Chat:\n{}", content);
+    }
+
+    // Reasoning model
+    let response = client
+        .chat()
+        .add_user_message("What is 15 * 47? Show your work.")
+        .with_model(Model::Reasoner)
+        .send()
+        .await?;
+
+    if let Some(reasoning) = response.get_reasoning() {
+        println!("\n🧠 Reasoning:\n{}", reasoning);
+    }
+    if let Some(content) = response.get_content() {
+        println!("\n✅ Answer:\n{}", content);
+    }
+
+    // Token usage
+    if let Some(usage) = &response.usage {
+        println!(
+            "\n📊 Tokens: {} prompt + {} completion = {} total",
+            usage.prompt_tokens, usage.completion_tokens, usage.total_tokens
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,238 @@
+//! Account balance monitoring
+//!
+//! A [`BalanceWatcher`] polls [`DeepSeekClient::balance`] in the background
+//! and reports when the balance drops below a threshold, either through a
+//! callback or a `tracing` warning (with the `logging` feature).
+//!
+//! # Example
+//! ```no_run
+//! use deepseek_rust::{DeepSeekClient, DeepSeekConfig, Result};
+//! use std::time::Duration;
+//!
+//! # async fn run() -> Result<()> {
+//! let client = DeepSeekClient::new(
+//!     DeepSeekConfig::new("your-api-key").with_low_balance_threshold(5.0),
+//! )?;
+//!
+//! let watcher = client
+//!     .watch_balance()
+//!     .with_interval(Duration::from_secs(60))
+//!     .on_low_balance(|balance| eprintln!("Low balance: {:?}", balance.balance_infos))
+//!     .spawn();
+//!
+//! // ... run the batch job ...
+//! watcher.stop();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`DeepSeekClient::balance`]: crate::DeepSeekClient::balance
+
+use crate::client::DeepSeekClient;
+use crate::error::Result;
+use crate::models::balance::BalanceInfo;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Default interval between balance checks
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Shortest interval between balance checks
+pub const MIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+type Callback = Arc<dyn Fn(&BalanceInfo) + Send + Sync>;
+
+/// Polls the account balance and reports low balances
+///
+/// Created with [`DeepSeekClient::watch_balance`]. The threshold defaults to
+/// [`DeepSeekConfig::low_balance_threshold`]; without one, only an
+/// unavailable balance is reported. A low balance is reported once when it
+/// is first seen and again only after the balance has recovered.
+///
+/// [`DeepSeekConfig::low_balance_threshold`]: crate::DeepSeekConfig::low_balance_threshold
+#[derive(Clone)]
+pub struct BalanceWatcher {
+    client: DeepSeekClient,
+    interval: Duration,
+    threshold: Option<f64>,
+    on_low: Option<Callback>,
+}
+
+impl BalanceWatcher {
+    /// Create a watcher using the client's configured threshold
+    pub fn new(client: DeepSeekClient) -> Self {
+        let threshold = client.config().low_balance_threshold;
+        Self {
+            client,
+            interval: DEFAULT_POLL_INTERVAL,
+            threshold,
+            on_low: None,
+        }
+    }
+
+    /// Set the interval between balance checks
+    ///
+    /// Intervals shorter than [`MIN_POLL_INTERVAL`] are raised to it.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval.max(MIN_POLL_INTERVAL);
+        self
+    }
+
+    /// Set the threshold, overriding the client configuration
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = Some(threshold);
+        self
+    }
+
+    /// Call `callback` when a low balance is detected
+    pub fn on_low_balance<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&BalanceInfo) + Send + Sync + 'static,
+    {
+        self.on_low = Some(Arc::new(callback));
+        self
+    }
+
+    /// Check if a balance counts as low for this watcher
+    pub fn is_low(&self, balance: &BalanceInfo) -> bool {
+        match self.threshold {
+            Some(threshold) => balance.is_below(threshold),
+            None => !balance.is_available,
+        }
+    }
+
+    /// Fetch the balance once without reporting it
+    pub async fn check(&self) -> Result<BalanceInfo> {
+        self.client.balance().await
+    }
+
+    /// Start polling in a background task
+    ///
+    /// Failed checks are logged and retried at the next interval. Must be
+    /// called within a Tokio runtime.
+    pub fn spawn(self) -> BalanceWatcherHandle {
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            let mut was_low = false;
+
+            loop {
+                ticker.tick().await;
+
+                let balance = match self.check().await {
+                    Ok(balance) => balance,
+                    Err(_err) => {
+                        #[cfg(feature = "logging")]
+                        tracing::warn!(error = %_err, "failed to check DeepSeek balance");
+                        continue;
+                    }
+                };
+
+                let is_low = self.is_low(&balance);
+                if is_low && !was_low {
+                    #[cfg(feature = "logging")]
+                    tracing::warn!(
+                        is_available = balance.is_available,
+                        threshold = self.threshold,
+                        balances = ?balance.balance_infos,
+                        "DeepSeek balance is low"
+                    );
+                    if let Some(callback) = &self.on_low {
+                        callback(&balance);
+                    }
+                }
+                was_low = is_low;
+            }
+        });
+
+        BalanceWatcherHandle { task }
+    }
+}
+
+impl fmt::Debug for BalanceWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BalanceWatcher")
+            .field("interval", &self.interval)
+            .field("threshold", &self.threshold)
+            .field("has_callback", &self.on_low.is_some())
+            .finish()
+    }
+}
+
+/// Handle to a running [`BalanceWatcher`]
+///
+/// Polling stops when the handle is stopped or dropped.
+#[derive(Debug)]
+pub struct BalanceWatcherHandle {
+    task: JoinHandle<()>,
+}
+
+impl BalanceWatcherHandle {
+    /// Stop polling
+    pub fn stop(self) {
+        self.task.abort();
+    }
+
+    /// Check if the watcher is still polling
+    pub fn is_running(&self) -> bool {
+        !self.task.is_finished()
+    }
+}
+
+impl Drop for BalanceWatcherHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DeepSeekConfig;
+    use crate::models::balance::CurrencyBalance;
+
+    fn balance(is_available: bool, total: f64) -> BalanceInfo {
+        BalanceInfo {
+            is_available,
+            balance_infos: vec![CurrencyBalance {
+                currency: "USD".to_string(),
+                total_balance: total,
+                granted_balance: 0.0,
+                topped_up_balance: total,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_is_low() {
+        let config = DeepSeekConfig::new("test-key").with_low_balance_threshold(5.0);
+        let watcher = BalanceWatcher::new(DeepSeekClient::new(config).unwrap());
+        assert!(watcher.is_low(&balance(true, 4.99)));
+        assert!(!watcher.is_low(&balance(true, 5.0)));
+        assert!(watcher.is_low(&balance(false, 100.0)));
+
+        let watcher = watcher.with_threshold(50.0);
+        assert!(watcher.is_low(&balance(true, 10.0)));
+
+        let client = DeepSeekClient::new(DeepSeekConfig::new("test-key")).unwrap();
+        let without_threshold = BalanceWatcher::new(client);
+        assert!(!without_threshold.is_low(&balance(true, 0.0)));
+        assert!(without_threshold.is_low(&balance(false, 0.0)));
+    }
+
+    #[tokio::test]
+    async fn test_zero_interval_is_clamped() {
+        let config = DeepSeekConfig::new("test-key").with_base_url("http://127.0.0.1:9");
+        let client = DeepSeekClient::new(config).unwrap();
+        let watcher = BalanceWatcher::new(client).with_interval(Duration::ZERO);
+        assert_eq!(watcher.interval, MIN_POLL_INTERVAL);
+
+        // A zero interval would panic inside the polling task
+        let handle = watcher.spawn();
+        tokio::task::yield_now().await;
+        assert!(handle.is_running());
+        handle.stop();
+    }
+}
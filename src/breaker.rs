@@ -0,0 +1,397 @@
+//! Circuit breaker around the API transport
+//!
+//! A [`CircuitBreaker`] set with [`DeepSeekConfig::with_circuit_breaker`]
+//! watches the outcome of every attempt sent to the API. When the share of
+//! failed attempts in a rolling window reaches a threshold, the circuit
+//! opens and requests fail immediately with [`DeepSeekError::CircuitOpen`]
+//! instead of waiting for timeouts. After a cool-down the circuit is
+//! half-open: a few probe requests go through, and the circuit closes if
+//! they succeed or opens again if one fails.
+//!
+//! Connection failures, timeouts and 408/500/502/503/504 responses count as
+//! failures. Rate limits and other client errors show the API is reachable
+//! and count as successes.
+//!
+//! # Example
+//! ```no_run
+//! use deepseek_rust::breaker::CircuitBreaker;
+//! use deepseek_rust::{DeepSeekClient, DeepSeekConfig, DeepSeekError};
+//! use std::time::Duration;
+//!
+//! # async fn run() -> deepseek_rust::Result<()> {
+//! let breaker = CircuitBreaker::new()
+//!     .with_failure_ratio(0.5)
+//!     .with_window(Duration::from_secs(60))
+//!     .with_open_duration(Duration::from_secs(15));
+//! let client = DeepSeekClient::new(DeepSeekConfig::new("your-api-key").with_circuit_breaker(breaker))?;
+//!
+//! match client.chat().add_user_message("Hello!").send().await {
+//!     Err(DeepSeekError::CircuitOpen(retry_in)) => println!("DeepSeek is down, retry in {:?}", retry_in),
+//!     other => println!("{:?}", other.map(|response| response.get_content().map(str::to_string))),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`DeepSeekConfig::with_circuit_breaker`]: crate::DeepSeekConfig::with_circuit_breaker
+
+use crate::error::{DeepSeekError, Result};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// State of a circuit breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally
+    Closed,
+
+    /// Requests fail fast until the cool-down ends
+    Open,
+
+    /// A limited number of probe requests are let through
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Settings {
+    failure_ratio: f64,
+    window: Duration,
+    min_requests: usize,
+    open_duration: Duration,
+    probes: usize,
+}
+
+#[derive(Debug)]
+enum Phase {
+    Closed,
+    /// `until` is `None` when the open duration is too long to represent;
+    /// the circuit then stays open until reset
+    Open { until: Option<Instant> },
+    HalfOpen { in_flight: usize, succeeded: usize },
+}
+
+#[derive(Debug)]
+struct Inner {
+    settings: Settings,
+    phase: Phase,
+    /// Outcomes in the window, `true` for failures
+    outcomes: VecDeque<(Instant, bool)>,
+}
+
+impl Inner {
+    /// Move an open circuit to half-open once the cool-down is over
+    fn refresh(&mut self, now: Instant) {
+        if let Phase::Open { until: Some(until) } = self.phase {
+            if now >= until {
+                self.phase = Phase::HalfOpen { in_flight: 0, succeeded: 0 };
+            }
+        }
+    }
+
+    fn open(&mut self, now: Instant) {
+        #[cfg(feature = "logging")]
+        tracing::warn!(
+            open_secs = self.settings.open_duration.as_secs_f64(),
+            "DeepSeek circuit breaker opened"
+        );
+        self.phase = Phase::Open { until: now.checked_add(self.settings.open_duration) };
+        self.outcomes.clear();
+    }
+
+    fn close(&mut self) {
+        #[cfg(feature = "logging")]
+        tracing::info!("DeepSeek circuit breaker closed");
+        self.phase = Phase::Closed;
+        self.outcomes.clear();
+    }
+
+    fn record(&mut self, probe: bool, failed: bool, now: Instant) {
+        match &mut self.phase {
+            Phase::HalfOpen { in_flight, succeeded } if probe => {
+                *in_flight = in_flight.saturating_sub(1);
+                if failed {
+                    self.open(now);
+                } else {
+                    *succeeded += 1;
+                    if *succeeded >= self.settings.probes {
+                        self.close();
+                    }
+                }
+            }
+            Phase::Closed => {
+                self.outcomes.push_back((now, failed));
+                while self
+                    .outcomes
+                    .front()
+                    .is_some_and(|(at, _)| now.saturating_duration_since(*at) > self.settings.window)
+                {
+                    self.outcomes.pop_front();
+                }
+
+                let failures = self.outcomes.iter().filter(|(_, failed)| *failed).count();
+                let total = self.outcomes.len();
+                if failed
+                    && total >= self.settings.min_requests
+                    && failures as f64 >= self.settings.failure_ratio * total as f64
+                {
+                    self.open(now);
+                }
+            }
+            // Requests admitted before the circuit opened, or before the
+            // last probe failed, no longer matter
+            _ => {}
+        }
+    }
+}
+
+/// Circuit breaker shared by all clones
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                settings: Settings {
+                    failure_ratio: 0.5,
+                    window: Duration::from_secs(30),
+                    min_requests: 10,
+                    open_duration: Duration::from_secs(30),
+                    probes: 1,
+                },
+                phase: Phase::Closed,
+                outcomes: VecDeque::new(),
+            })),
+        }
+    }
+}
+
+impl CircuitBreaker {
+    /// Create a breaker opening when half of at least 10 attempts in 30
+    /// seconds failed, for 30 seconds, with one probe
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Share of failed attempts in the window that opens the circuit
+    pub fn with_failure_ratio(self, ratio: f64) -> Self {
+        self.lock().settings.failure_ratio = ratio;
+        self
+    }
+
+    /// Length of the rolling window of attempts
+    pub fn with_window(self, window: Duration) -> Self {
+        self.lock().settings.window = window;
+        self
+    }
+
+    /// Attempts needed in the window before the circuit can open
+    pub fn with_min_requests(self, count: usize) -> Self {
+        self.lock().settings.min_requests = count;
+        self
+    }
+
+    /// Time requests fail fast before probing again
+    ///
+    /// `Duration::MAX` keeps the circuit open until [`reset`](Self::reset).
+    pub fn with_open_duration(self, duration: Duration) -> Self {
+        self.lock().settings.open_duration = duration;
+        self
+    }
+
+    /// Successful probes needed to close a half-open circuit; as many
+    /// probes may be in flight at once
+    pub fn with_half_open_probes(self, probes: usize) -> Self {
+        self.lock().settings.probes = probes;
+        self
+    }
+
+    /// Validate the settings
+    pub fn validate(&self) -> Result<()> {
+        let inner = self.lock();
+        let settings = &inner.settings;
+        if !(settings.failure_ratio > 0.0 && settings.failure_ratio <= 1.0) {
+            return Err(DeepSeekError::ConfigError(format!(
+                "Circuit breaker failure ratio must be in (0, 1], got {}",
+                settings.failure_ratio
+            )));
+        }
+        if settings.min_requests == 0 || settings.probes == 0 {
+            return Err(DeepSeekError::ConfigError(
+                "Circuit breaker minimum requests and probes must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Current state
+    pub fn state(&self) -> CircuitState {
+        let mut inner = self.lock();
+        inner.refresh(Instant::now());
+        match inner.phase {
+            Phase::Closed => CircuitState::Closed,
+            Phase::Open { .. } => CircuitState::Open,
+            Phase::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Close the circuit and forget recorded outcomes
+    pub fn reset(&self) {
+        let mut inner = self.lock();
+        inner.phase = Phase::Closed;
+        inner.outcomes.clear();
+    }
+
+    /// Ask to send an attempt
+    ///
+    /// # Errors
+    /// Returns `CircuitOpen` with the time left before probing if the
+    /// circuit is open, or half-open with all probes in flight
+    pub fn call(&self) -> Result<BreakerCall> {
+        let mut inner = self.lock();
+        let now = Instant::now();
+        inner.refresh(now);
+        let probes = inner.settings.probes;
+        let probe = match &mut inner.phase {
+            Phase::Closed => false,
+            Phase::Open { until } => {
+                let retry_in = until.map_or(Duration::MAX, |until| until.saturating_duration_since(now));
+                return Err(DeepSeekError::CircuitOpen(retry_in));
+            }
+            Phase::HalfOpen { in_flight, succeeded } => {
+                if *in_flight + *succeeded >= probes {
+                    return Err(DeepSeekError::CircuitOpen(Duration::ZERO));
+                }
+                *in_flight += 1;
+                true
+            }
+        };
+        Ok(BreakerCall {
+            breaker: self.clone(),
+            probe,
+            recorded: false,
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Whether an error shows the API is unavailable
+fn is_failure(err: &DeepSeekError) -> bool {
+    err.is_retryable() && !err.is_rate_limit()
+}
+
+/// An attempt let through by the breaker, whose outcome must be recorded
+///
+/// Dropping it unrecorded, e.g. when the request is cancelled, frees its
+/// probe slot without counting an outcome.
+#[derive(Debug)]
+pub struct BreakerCall {
+    breaker: CircuitBreaker,
+    probe: bool,
+    recorded: bool,
+}
+
+impl BreakerCall {
+    /// Record the outcome of the attempt: `None` on success
+    pub fn record(mut self, error: Option<&DeepSeekError>) {
+        self.recorded = true;
+        let failed = error.is_some_and(is_failure);
+        self.breaker.lock().record(self.probe, failed, Instant::now());
+    }
+}
+
+impl Drop for BreakerCall {
+    fn drop(&mut self) {
+        if self.probe && !self.recorded {
+            if let Phase::HalfOpen { in_flight, .. } = &mut self.breaker.lock().phase {
+                *in_flight = in_flight.saturating_sub(1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server_error() -> DeepSeekError {
+        DeepSeekError::ApiError { status: 503, message: String::new() }
+    }
+
+    #[test]
+    fn test_opens_on_failure_ratio() {
+        let breaker = CircuitBreaker::new().with_min_requests(4).with_failure_ratio(0.5);
+        assert!(breaker.validate().is_ok());
+
+        breaker.call().unwrap().record(None);
+        breaker.call().unwrap().record(Some(&server_error()));
+        // Client errors and rate limits do not count as failures
+        let rate_limit = DeepSeekError::ApiError { status: 429, message: String::new() };
+        breaker.call().unwrap().record(Some(&rate_limit));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        breaker.call().unwrap().record(Some(&DeepSeekError::TimeoutError(30)));
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(matches!(breaker.call(), Err(DeepSeekError::CircuitOpen(retry_in)) if retry_in > Duration::ZERO));
+
+        breaker.reset();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_open_until_reset() {
+        let breaker = CircuitBreaker::new()
+            .with_min_requests(1)
+            .with_open_duration(Duration::MAX);
+        breaker.call().unwrap().record(Some(&server_error()));
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(matches!(breaker.call(), Err(DeepSeekError::CircuitOpen(Duration::MAX))));
+
+        breaker.reset();
+        assert!(breaker.call().is_ok());
+    }
+
+    #[test]
+    fn test_half_open_probes() {
+        let breaker = CircuitBreaker::new()
+            .with_min_requests(1)
+            .with_open_duration(Duration::ZERO)
+            .with_half_open_probes(2);
+        breaker.call().unwrap().record(Some(&server_error()));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+
+        let first = breaker.call().unwrap();
+        let second = breaker.call().unwrap();
+        assert!(matches!(breaker.call(), Err(DeepSeekError::CircuitOpen(_))));
+
+        // A cancelled probe frees its slot
+        drop(second);
+        first.record(None);
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        breaker.call().unwrap().record(None);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        // A failed probe opens the circuit again
+        let breaker = CircuitBreaker::new()
+            .with_min_requests(1)
+            .with_open_duration(Duration::from_secs(60));
+        breaker.call().unwrap().record(Some(&server_error()));
+        assert_eq!(breaker.state(), CircuitState::Open);
+        breaker.lock().phase = Phase::HalfOpen { in_flight: 0, succeeded: 0 };
+        breaker.call().unwrap().record(Some(&server_error()));
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(CircuitBreaker::new().with_failure_ratio(0.0).validate().is_err());
+        assert!(CircuitBreaker::new().with_failure_ratio(1.5).validate().is_err());
+        assert!(CircuitBreaker::new().with_half_open_probes(0).validate().is_err());
+    }
+}
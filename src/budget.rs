@@ -0,0 +1,202 @@
+//! Spend caps enforced by the client
+//!
+//! A [`Budget`] set with [`DeepSeekConfig::with_budget`] records the cost of
+//! every response, priced with [`DeepSeekConfig::pricing`], and refuses new
+//! requests with `BudgetExceeded` once a cap is reached. Caps apply to the
+//! whole client and, optionally, to each end user named in a request's
+//! `user` field.
+//!
+//! Requests already in flight when a cap is reached still complete, so
+//! spend can exceed a cap by the cost of those requests. Responses for
+//! models without a price are not recorded.
+//!
+//! # Example
+//! ```no_run
+//! use deepseek_rust::budget::Budget;
+//! use deepseek_rust::{DeepSeekClient, DeepSeekConfig, Result};
+//!
+//! # async fn run() -> Result<()> {
+//! let budget = Budget::new().with_limit(10.0).with_user_limit(0.50);
+//! let client = DeepSeekClient::new(DeepSeekConfig::new("your-api-key").with_budget(budget.clone()))?;
+//!
+//! client.chat().add_user_message("Hello!").with_user("user-42").send().await?;
+//! println!("Spent ${:.4}", budget.spent());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`DeepSeekConfig::with_budget`]: crate::DeepSeekConfig::with_budget
+//! [`DeepSeekConfig::pricing`]: crate::DeepSeekConfig::pricing
+
+use crate::error::{DeepSeekError, Result};
+use crate::models::pricing::ModelPricing;
+use crate::models::response::Usage;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+#[derive(Debug, Default)]
+struct Spend {
+    total: f64,
+    by_user: HashMap<String, f64>,
+}
+
+/// Spend caps in USD, shared by all clones
+///
+/// Clones share the recorded spend, so keep a clone to inspect it after
+/// passing the budget to the configuration.
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    limit: Option<f64>,
+    user_limit: Option<f64>,
+    user_limits: HashMap<String, f64>,
+    spend: Arc<Mutex<Spend>>,
+}
+
+impl Budget {
+    /// Create a budget without caps
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cap the total spend of the client
+    pub fn with_limit(mut self, limit: f64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Cap the spend of every user without a specific cap
+    pub fn with_user_limit(mut self, limit: f64) -> Self {
+        self.user_limit = Some(limit);
+        self
+    }
+
+    /// Cap the spend of one user
+    pub fn with_limit_for_user(mut self, user: impl Into<String>, limit: f64) -> Self {
+        self.user_limits.insert(user.into(), limit);
+        self
+    }
+
+    /// Total spend recorded
+    pub fn spent(&self) -> f64 {
+        self.lock().total
+    }
+
+    /// Spend recorded for a user
+    pub fn spent_by(&self, user: &str) -> f64 {
+        self.lock().by_user.get(user).copied().unwrap_or(0.0)
+    }
+
+    /// Spend left before the client cap, if there is one
+    pub fn remaining(&self) -> Option<f64> {
+        self.limit.map(|limit| (limit - self.spent()).max(0.0))
+    }
+
+    /// Cap that applies to a user, if any
+    pub fn limit_for(&self, user: &str) -> Option<f64> {
+        self.user_limits.get(user).copied().or(self.user_limit)
+    }
+
+    /// Check that neither the client cap nor the user's cap is reached
+    ///
+    /// # Errors
+    /// Returns `BudgetExceeded` if a cap is reached
+    pub fn check(&self, user: Option<&str>) -> Result<()> {
+        let spend = self.lock();
+        if let Some(limit) = self.limit {
+            if spend.total >= limit {
+                return Err(DeepSeekError::BudgetExceeded(format!(
+                    "spent ${:.4} of the ${:.4} limit",
+                    spend.total, limit
+                )));
+            }
+        }
+
+        if let Some(user) = user {
+            if let Some(limit) = self.limit_for(user) {
+                let spent = spend.by_user.get(user).copied().unwrap_or(0.0);
+                if spent >= limit {
+                    return Err(DeepSeekError::BudgetExceeded(format!(
+                        "user '{}' spent ${:.4} of the ${:.4} limit",
+                        user, spent, limit
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the cost of a request
+    pub fn record(&self, user: Option<&str>, cost: f64) {
+        let mut spend = self.lock();
+        spend.total += cost;
+        if let Some(user) = user {
+            *spend.by_user.entry(user.to_string()).or_default() += cost;
+        }
+    }
+
+    /// Forget all recorded spend
+    pub fn reset(&self) {
+        *self.lock() = Spend::default();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Spend> {
+        // The spend stays consistent even if a holder panicked
+        self.spend.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Records the cost of one request's usage in a budget
+#[derive(Debug, Clone)]
+pub(crate) struct SpendTracker {
+    pub(crate) budget: Budget,
+    pub(crate) pricing: ModelPricing,
+    pub(crate) user: Option<String>,
+}
+
+impl SpendTracker {
+    /// Price the usage now and add it to the budget
+    pub(crate) fn record(&self, usage: &Usage) {
+        let cost = self.pricing.cost(usage, SystemTime::now());
+        self.budget.record(self.user.as_deref(), cost);
+        #[cfg(feature = "logging")]
+        tracing::debug!(cost, total = self.budget.spent(), "recorded request cost");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_limit() {
+        let budget = Budget::new().with_limit(1.0);
+        assert!(budget.check(None).is_ok());
+        assert_eq!(budget.remaining(), Some(1.0));
+
+        budget.clone().record(None, 0.6);
+        assert!(budget.check(Some("alice")).is_ok());
+        budget.record(Some("alice"), 0.4);
+        assert!(matches!(budget.check(None), Err(DeepSeekError::BudgetExceeded(_))));
+        assert_eq!(budget.remaining(), Some(0.0));
+
+        budget.reset();
+        assert_eq!(budget.spent(), 0.0);
+        assert!(budget.check(None).is_ok());
+    }
+
+    #[test]
+    fn test_user_limits() {
+        let budget = Budget::new().with_user_limit(0.1).with_limit_for_user("vip", 5.0);
+        budget.record(Some("alice"), 0.1);
+        budget.record(Some("vip"), 1.0);
+
+        assert!(budget.check(Some("alice")).is_err());
+        assert!(budget.check(Some("bob")).is_ok());
+        assert!(budget.check(Some("vip")).is_ok());
+        assert!(budget.check(None).is_ok());
+        assert_eq!(budget.spent_by("vip"), 1.0);
+        assert!((budget.spent() - 1.1).abs() < 1e-9);
+        assert_eq!(budget.remaining(), None);
+    }
+}
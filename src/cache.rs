@@ -0,0 +1,396 @@
+//! Response caching for deterministic chat requests
+//!
+//! A [`ResponseCache`] set with [`DeepSeekConfig::with_response_cache`]
+//! stores chat completion responses keyed by [`cache_key`], a SHA-256 hash
+//! of the canonical JSON form of the request. By default only deterministic
+//! requests, those with a temperature of 0, are cached; [`CacheMode`]
+//! changes that per request. Responses served from the cache have
+//! [`ChatCompletionResponse::cached`] set, and do not count against budgets
+//! or rate limits.
+//!
+//! Streaming requests are never cached.
+//!
+//! # Example
+//! ```no_run
+//! use deepseek_rust::cache::InMemoryCache;
+//! use deepseek_rust::{DeepSeekClient, DeepSeekConfig, Result};
+//! use std::time::Duration;
+//!
+//! # async fn run() -> Result<()> {
+//! let cache = InMemoryCache::new(1_000).with_ttl(Duration::from_secs(3600));
+//! let config = DeepSeekConfig::new("your-api-key").with_response_cache(cache);
+//! let client = DeepSeekClient::new(config)?;
+//!
+//! let ask = || client.chat().add_user_message("Capital of France?").with_temperature(0.0);
+//! assert!(!ask()?.send().await?.cached);
+//! assert!(ask()?.send().await?.cached);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`DeepSeekConfig::with_response_cache`]: crate::DeepSeekConfig::with_response_cache
+
+use crate::error::{DeepSeekError, Result};
+use crate::models::request::ChatCompletionRequest;
+use crate::models::response::ChatCompletionResponse;
+use crate::persistence;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// How a request uses the configured response cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheMode {
+    /// Serve and store deterministic requests (temperature 0)
+    #[default]
+    Default,
+
+    /// Neither read nor write the cache
+    Bypass,
+
+    /// Always send the request and store the response
+    Refresh,
+
+    /// Only serve from the cache, failing with `CacheMiss` otherwise
+    OnlyIfCached,
+}
+
+/// Storage for chat completion responses
+///
+/// Keys are the hex strings returned by [`cache_key`]. Implementations
+/// decide on eviction and expiry; an expired entry should read as `None`.
+pub trait ResponseCache: fmt::Debug + Send + Sync {
+    /// Look up a stored response
+    fn get(&self, key: &str) -> Result<Option<ChatCompletionResponse>>;
+
+    /// Store a response, replacing any earlier one
+    fn put(&self, key: &str, response: &ChatCompletionResponse) -> Result<()>;
+}
+
+/// Whether a request is cached in [`CacheMode::Default`]
+pub fn is_deterministic(request: &ChatCompletionRequest) -> bool {
+    request.temperature.is_some_and(|temperature| temperature.value() == 0.0)
+        && request.n.is_none_or(|n| n == 1)
+}
+
+/// Canonical hash of a request, used as its cache key
+///
+/// The request is serialized with object keys sorted; streaming options,
+/// which do not change the response, are left out.
+pub fn cache_key(request: &ChatCompletionRequest) -> Result<String> {
+    let mut value = serde_json::to_value(request)?;
+    if let Some(object) = value.as_object_mut() {
+        object.remove("stream");
+        object.remove("stream_options");
+    }
+    // serde_json maps keep keys sorted, so this form is canonical
+    let canonical = serde_json::to_vec(&value)?;
+    Ok(Sha256::digest(&canonical).iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+#[derive(Debug)]
+struct MemoryEntry {
+    response: ChatCompletionResponse,
+    stored_at: Instant,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    entries: HashMap<String, MemoryEntry>,
+    /// Keys by last use, oldest first
+    recency: BTreeMap<u64, String>,
+    clock: u64,
+}
+
+impl MemoryState {
+    fn touch(&mut self, key: &str) {
+        self.clock += 1;
+        let clock = self.clock;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.recency.remove(&entry.last_used);
+            entry.last_used = clock;
+            self.recency.insert(clock, key.to_string());
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recency.remove(&entry.last_used);
+        }
+    }
+}
+
+/// In-memory cache evicting the least recently used entries
+#[derive(Debug)]
+pub struct InMemoryCache {
+    capacity: usize,
+    ttl: Option<Duration>,
+    state: Mutex<MemoryState>,
+}
+
+impl InMemoryCache {
+    /// Create a cache holding up to `capacity` responses (at least 1)
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            ttl: None,
+            state: Mutex::new(MemoryState::default()),
+        }
+    }
+
+    /// Expire entries this long after they were stored
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Number of stored responses, including expired ones not yet evicted
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all entries
+    pub fn clear(&self) {
+        *self.lock() = MemoryState::default();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl ResponseCache for InMemoryCache {
+    fn get(&self, key: &str) -> Result<Option<ChatCompletionResponse>> {
+        let mut state = self.lock();
+        let expired = match state.entries.get(key) {
+            None => return Ok(None),
+            Some(entry) => self.ttl.is_some_and(|ttl| entry.stored_at.elapsed() >= ttl),
+        };
+        if expired {
+            state.remove(key);
+            return Ok(None);
+        }
+
+        state.touch(key);
+        Ok(state.entries.get(key).map(|entry| entry.response.clone()))
+    }
+
+    fn put(&self, key: &str, response: &ChatCompletionResponse) -> Result<()> {
+        let mut state = self.lock();
+        state.remove(key);
+        while state.entries.len() >= self.capacity {
+            let Some((_, oldest)) = state.recency.pop_first() else {
+                break;
+            };
+            state.entries.remove(&oldest);
+        }
+
+        state.entries.insert(
+            key.to_string(),
+            MemoryEntry {
+                response: response.clone(),
+                stored_at: Instant::now(),
+                last_used: 0,
+            },
+        );
+        state.touch(key);
+        Ok(())
+    }
+}
+
+/// A response file of a [`DiskCache`]
+#[derive(Debug, Serialize, Deserialize)]
+struct DiskEntry {
+    /// Unix time in seconds when the response was stored
+    stored_at: u64,
+    response: ChatCompletionResponse,
+}
+
+/// Cache storing each response as a JSON file in a directory
+///
+/// Entries survive restarts and can be shared by processes using the same
+/// directory. Expired files are removed when read.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+    ttl: Option<Duration>,
+}
+
+impl DiskCache {
+    /// Use `dir` for storage, creating it if needed
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir, ttl: None })
+    }
+
+    /// Expire entries this long after they were stored
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// The storage directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Remove all entries, returning how many were removed
+    pub fn clear(&self) -> Result<usize> {
+        let mut removed = 0;
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "json") {
+                fs::remove_file(path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(DeepSeekError::InvalidParameter(format!(
+                "Invalid cache key '{}': use ASCII letters and digits",
+                key
+            )));
+        }
+        Ok(self.dir.join(format!("{}.json", key)))
+    }
+}
+
+impl ResponseCache for DiskCache {
+    fn get(&self, key: &str) -> Result<Option<ChatCompletionResponse>> {
+        let path = self.path(key)?;
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let entry: DiskEntry = serde_json::from_str(&contents)?;
+        let age = persistence::unix_now().saturating_sub(entry.stored_at);
+        if self.ttl.is_some_and(|ttl| age >= ttl.as_secs()) {
+            match fs::remove_file(&path) {
+                Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+                _ => return Ok(None),
+            }
+        }
+        Ok(Some(entry.response))
+    }
+
+    fn put(&self, key: &str, response: &ChatCompletionResponse) -> Result<()> {
+        let path = self.path(key)?;
+        let entry = DiskEntry {
+            stored_at: persistence::unix_now(),
+            response: response.clone(),
+        };
+
+        // Write then rename so readers never see a partial file
+        let tmp = self.dir.join(format!("{}.{}.tmp", key, persistence::new_id()));
+        fs::write(&tmp, serde_json::to_vec(&entry)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::request::{Message, Temperature};
+    use crate::models::response::{Choice, ResponseMessage};
+
+    fn response(content: &str) -> ChatCompletionResponse {
+        ChatCompletionResponse {
+            id: "chatcmpl-1".to_string(),
+            object: "chat.completion".to_string(),
+            created: 0,
+            model: "deepseek-chat".to_string(),
+            choices: vec![Choice {
+                index: 0,
+                message: ResponseMessage {
+                    role: "assistant".to_string(),
+                    content: Some(content.to_string()),
+                    reasoning_content: None,
+                    function_call: None,
+                    tool_calls: None,
+                },
+                finish_reason: Some("stop".to_string()),
+                logprobs: None,
+            }],
+            usage: None,
+            system_fingerprint: None,
+            cached: false,
+        }
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let mut request = ChatCompletionRequest::new(vec![Message::user("Hello")]);
+        let key = cache_key(&request).unwrap();
+        assert_eq!(key.len(), 64);
+        assert!(!is_deterministic(&request));
+
+        request.stream = Some(true);
+        assert_eq!(cache_key(&request).unwrap(), key);
+
+        request.temperature = Some(Temperature::new(0.0).unwrap());
+        assert!(is_deterministic(&request));
+        assert_ne!(cache_key(&request).unwrap(), key);
+    }
+
+    #[test]
+    fn test_in_memory_lru() {
+        let cache = InMemoryCache::new(2);
+        cache.put("a", &response("A")).unwrap();
+        cache.put("b", &response("B")).unwrap();
+        assert!(cache.get("a").unwrap().is_some());
+
+        // "b" is the least recently used
+        cache.put("c", &response("C")).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("b").unwrap().is_none());
+        assert_eq!(cache.get("a").unwrap().unwrap().get_content(), Some("A"));
+
+        cache.put("a", &response("A2")).unwrap();
+        assert_eq!(cache.get("a").unwrap().unwrap().get_content(), Some("A2"));
+        assert_eq!(cache.len(), 2);
+
+        let expiring = InMemoryCache::new(2).with_ttl(Duration::ZERO);
+        expiring.put("a", &response("A")).unwrap();
+        assert!(expiring.get("a").unwrap().is_none());
+        assert!(expiring.is_empty());
+    }
+
+    #[test]
+    fn test_disk_cache() {
+        let dir = std::env::temp_dir().join(format!("deepseek-cache-{}", persistence::new_id()));
+        let cache = DiskCache::new(&dir).unwrap();
+        assert!(cache.get("abc123").unwrap().is_none());
+
+        cache.put("abc123", &response("Cached")).unwrap();
+        assert_eq!(cache.get("abc123").unwrap().unwrap().get_content(), Some("Cached"));
+        assert!(cache.get("../escape").is_err());
+
+        let expiring = DiskCache::new(&dir).unwrap().with_ttl(Duration::ZERO);
+        assert!(expiring.get("abc123").unwrap().is_none());
+        assert!(cache.get("abc123").unwrap().is_none());
+
+        cache.put("abc123", &response("Cached")).unwrap();
+        assert_eq!(cache.clear().unwrap(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
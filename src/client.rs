@@ -1,8 +1,1856 @@
-// This file defines the client module.
+//! HTTP client for the DeepSeek API
 
-/// Builder for chat requests (واجهة أولية)
-pub struct ChatBuilder;
+use crate::balance::BalanceWatcher;
+use crate::breaker::{BreakerCall, CircuitBreaker};
+use crate::budget::SpendTracker;
+use crate::cache::{self, CacheMode};
+use crate::config::DeepSeekConfig;
+use crate::context::{self, ContextStrategy};
+use crate::conversation::Conversation;
+use crate::error::{DeepSeekError, Result};
+use crate::limiter::{Permit, Priority};
+use crate::models::balance::BalanceInfo;
+use crate::models::capabilities::ValidationMode;
+use crate::models::fim::{FimCompletionRequest, FimCompletionResponse};
+use crate::models::request::{
+    ChatCompletionRequest, Message, Model, ResponseFormat, Temperature,
+};
+use crate::models::response::{
+    ApiErrorResponse, ChatCompletionResponse, ModelInfo, ModelList, Usage,
+};
+use crate::models::tool::{Tool, ToolChoice};
+use crate::retry::{parse_retry_after, Failure};
+use crate::tools::{ToolRegistry, ToolRun, ToolStep};
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER,
+};
+use reqwest::Method;
+use secrecy::ExposeSecret;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
-/// DeepSeek API client (واجهة أولية)
-pub struct DeepSeekClient;
+#[cfg(feature = "streaming")]
+use crate::streaming::{self, ChatStream, FimStream};
+#[cfg(feature = "streaming")]
+use futures::StreamExt;
 
+/// Path of the chat completions endpoint
+pub const CHAT_COMPLETIONS_PATH: &str = "/chat/completions";
+
+/// Path of the list models endpoint
+pub const MODELS_PATH: &str = "/models";
+
+/// Path of the account balance endpoint
+pub const BALANCE_PATH: &str = "/user/balance";
+
+/// Path of the FIM completions endpoint (relative to the beta base URL)
+pub const FIM_COMPLETIONS_PATH: &str = "/completions";
+
+/// Per-request transport overrides
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
+    /// Timeout for this request, overriding the configured timeout
+    pub timeout: Option<Duration>,
+
+    /// Extra headers sent with this request
+    pub headers: HeaderMap,
+
+    /// Position in the rate limiter's queue, if one is configured
+    pub priority: Priority,
+
+    /// How the response cache is used, if one is configured
+    pub cache: CacheMode,
+}
+
+impl RequestOptions {
+    /// Timeout that applies to a request, falling back to the configured one
+    fn effective_timeout(&self, config: &DeepSeekConfig) -> Duration {
+        self.timeout.unwrap_or(config.timeout)
+    }
+
+    /// Parse and add an extra header
+    fn insert_header(&mut self, name: &str, value: &str) -> Result<()> {
+        let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+            DeepSeekError::InvalidParameter(format!("Invalid header name: {}", name))
+        })?;
+        let value = HeaderValue::from_str(value).map_err(|_| {
+            DeepSeekError::InvalidParameter(format!("Invalid value for header {}", name))
+        })?;
+        self.headers.insert(name, value);
+        Ok(())
+    }
+}
+
+/// DeepSeek API client
+///
+/// The client is cheap to clone; clones share the same connection pool
+/// and configuration.
+///
+/// # Example
+/// ```no_run
+/// use deepseek_rust::{DeepSeekClient, DeepSeekConfig};
+///
+/// let client = DeepSeekClient::new(DeepSeekConfig::new("your-api-key"))
+///     .expect("Failed to create client");
+/// ```
+#[derive(Debug, Clone)]
+pub struct DeepSeekClient {
+    http: reqwest::Client,
+    config: Arc<DeepSeekConfig>,
+}
+
+impl DeepSeekClient {
+    /// Create a new client from a configuration
+    ///
+    /// # Errors
+    /// Returns an error if the configuration is invalid or the underlying
+    /// HTTP client cannot be built (e.g. a malformed proxy URL).
+    pub fn new(config: DeepSeekConfig) -> Result<Self> {
+        config.validate()?;
+
+        let mut headers = HeaderMap::new();
+        let mut auth = HeaderValue::from_str(&format!("Bearer {}", config.api_key.expose_secret()))
+            .map_err(|_| DeepSeekError::ConfigError(
+                "API key contains characters that are not valid in an HTTP header".to_string()
+            ))?;
+        auth.set_sensitive(true);
+        headers.insert(AUTHORIZATION, auth);
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        let mut builder = reqwest::Client::builder()
+            .default_headers(headers)
+            .connect_timeout(config.timeout)
+            .user_agent(config.user_agent.clone())
+            .danger_accept_invalid_certs(!config.validate_certs);
+
+        if let Some(proxy) = &config.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| DeepSeekError::ConfigError(format!("Invalid proxy URL: {}", e)))?;
+            builder = builder.proxy(proxy);
+        }
+
+        let http = builder
+            .build()
+            .map_err(|e| DeepSeekError::ConfigError(format!("Failed to build HTTP client: {}", e)))?;
+
+        Ok(Self {
+            http,
+            config: Arc::new(config),
+        })
+    }
+
+    /// Create a new client from environment variables
+    ///
+    /// See [`DeepSeekConfig::from_env`] for the variables that are read.
+    pub fn from_env() -> Result<Self> {
+        Self::new(DeepSeekConfig::from_env()?)
+    }
+
+    /// Get the client configuration
+    pub fn config(&self) -> &DeepSeekConfig {
+        &self.config
+    }
+
+    /// Start a multi-turn conversation
+    ///
+    /// See [`Conversation`] for managing the history between turns.
+    pub fn conversation(&self) -> Conversation {
+        Conversation::new(self.clone())
+    }
+
+    /// Start building a chat completion request
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use deepseek_rust::{DeepSeekClient, Result};
+    /// # async fn run(client: DeepSeekClient) -> Result<()> {
+    /// let response = client
+    ///     .chat()
+    ///     .add_user_message("Hello!")
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn chat(&self) -> ChatBuilder<'_> {
+        ChatBuilder::new(self)
+    }
+
+    /// Send a chat completion request
+    pub async fn chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        self.chat_completion_with_options(request, &RequestOptions::default())
+            .await
+    }
+
+    /// Send a chat completion request with per-request overrides
+    pub async fn chat_completion_with_options(
+        &self,
+        mut request: ChatCompletionRequest,
+        options: &RequestOptions,
+    ) -> Result<ChatCompletionResponse> {
+        self.prepare_chat(&mut request)?;
+        let cache_key = self.cache_key(&request, options.cache);
+        if options.cache != CacheMode::Refresh {
+            let hit = cache_key.as_deref().and_then(|key| self.cached_response(key));
+            if let Some(mut response) = hit {
+                response.cached = true;
+                return Ok(response);
+            }
+        }
+        if options.cache == CacheMode::OnlyIfCached {
+            return Err(DeepSeekError::CacheMiss);
+        }
+
+        self.check_budget(request.user.as_deref())?;
+        let mut recorder = self.chat_recorder(&request, options).await;
+
+        let response: ChatCompletionResponse =
+            self.post(&self.chat_url(&request), &request, options, recorder.permit())
+                .await?;
+        if let Some(usage) = &response.usage {
+            recorder.record(usage);
+        }
+        if response.choices.is_empty() {
+            return Err(DeepSeekError::EmptyResponse);
+        }
+
+        if let Some(key) = &cache_key {
+            self.store_response(key, &response);
+        }
+        Ok(response)
+    }
+
+    /// Send a chat completion request and stream the response
+    ///
+    /// `stream` is forced on. Transient failures are retried until the
+    /// response headers arrive; errors after that are yielded by the stream.
+    #[cfg(feature = "streaming")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "streaming")))]
+    pub async fn chat_completion_stream(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatStream> {
+        self.chat_completion_stream_with_options(request, &RequestOptions::default())
+            .await
+    }
+
+    /// Stream a chat completion with per-request overrides
+    ///
+    /// Unlike buffered requests, the configured timeout only bounds
+    /// connecting; an explicit [`RequestOptions::timeout`] bounds the whole
+    /// stream.
+    #[cfg(feature = "streaming")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "streaming")))]
+    pub async fn chat_completion_stream_with_options(
+        &self,
+        mut request: ChatCompletionRequest,
+        options: &RequestOptions,
+    ) -> Result<ChatStream> {
+        request.stream = Some(true);
+        self.prepare_chat(&mut request)?;
+        self.check_budget(request.user.as_deref())?;
+        let mut recorder = self.chat_recorder(&request, options).await;
+
+        let body = self
+            .open_stream(&self.chat_url(&request), &request, options, recorder.permit())
+            .await?;
+        let stream = streaming::chunk_stream(body);
+        if recorder.is_idle() {
+            return Ok(stream);
+        }
+        // The recorder keeps the rate limiter permit until the stream is dropped
+        Ok(Box::pin(stream.inspect(move |item| {
+            if let Ok(Some(usage)) = item.as_ref().map(|chunk| chunk.usage.as_ref()) {
+                recorder.record(usage);
+            }
+        })))
+    }
+
+    /// List the models available to the API key
+    ///
+    /// Ids without a dedicated [`Model`] variant map to [`Model::Custom`]
+    /// through [`ModelInfo::model`].
+    pub async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let list: ModelList = self
+            .get(&self.endpoint(MODELS_PATH), &RequestOptions::default())
+            .await?;
+        Ok(list.data)
+    }
+
+    /// Get the account balance
+    pub async fn balance(&self) -> Result<BalanceInfo> {
+        self.get(&self.endpoint(BALANCE_PATH), &RequestOptions::default())
+            .await
+    }
+
+    /// Create a [`BalanceWatcher`] that polls the balance in the background
+    ///
+    /// The watcher uses [`DeepSeekConfig::low_balance_threshold`] unless
+    /// overridden; call [`BalanceWatcher::spawn`] to start it.
+    pub fn watch_balance(&self) -> BalanceWatcher {
+        BalanceWatcher::new(self.clone())
+    }
+
+    /// Start building a fill-in-the-middle completion request
+    ///
+    /// FIM completion is a beta endpoint served from
+    /// [`DeepSeekConfig::beta_url`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use deepseek_rust::{DeepSeekClient, Result};
+    /// # async fn run(client: DeepSeekClient) -> Result<()> {
+    /// let response = client
+    ///     .fim()
+    ///     .with_prompt("fn add(a: i32, b: i32) -> i32 {\n")
+    ///     .with_suffix("\n}")
+    ///     .with_max_tokens(64)
+    ///     .send()
+    ///     .await?;
+    /// println!("{}", response.get_text().unwrap_or_default());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fim(&self) -> FimBuilder<'_> {
+        FimBuilder::new(self)
+    }
+
+    /// Send a FIM completion request
+    pub async fn fim_completion(
+        &self,
+        request: FimCompletionRequest,
+    ) -> Result<FimCompletionResponse> {
+        self.fim_completion_with_options(request, &RequestOptions::default())
+            .await
+    }
+
+    /// Send a FIM completion request with per-request overrides
+    pub async fn fim_completion_with_options(
+        &self,
+        mut request: FimCompletionRequest,
+        options: &RequestOptions,
+    ) -> Result<FimCompletionResponse> {
+        self.prepare_fim(&mut request)?;
+        self.check_budget(None)?;
+        let mut recorder = self.fim_recorder(&request, options).await;
+
+        let response: FimCompletionResponse = self
+            .post(
+                &self.beta_endpoint(FIM_COMPLETIONS_PATH),
+                &request,
+                options,
+                recorder.permit(),
+            )
+            .await?;
+        if let Some(usage) = &response.usage {
+            recorder.record(usage);
+        }
+        if response.choices.is_empty() {
+            return Err(DeepSeekError::EmptyResponse);
+        }
+
+        Ok(response)
+    }
+
+    /// Send a FIM completion request and stream the response
+    #[cfg(feature = "streaming")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "streaming")))]
+    pub async fn fim_completion_stream(
+        &self,
+        request: FimCompletionRequest,
+    ) -> Result<FimStream> {
+        self.fim_completion_stream_with_options(request, &RequestOptions::default())
+            .await
+    }
+
+    /// Stream a FIM completion with per-request overrides
+    ///
+    /// Timeouts behave as for [`DeepSeekClient::chat_completion_stream_with_options`].
+    #[cfg(feature = "streaming")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "streaming")))]
+    pub async fn fim_completion_stream_with_options(
+        &self,
+        mut request: FimCompletionRequest,
+        options: &RequestOptions,
+    ) -> Result<FimStream> {
+        request.stream = Some(true);
+        self.prepare_fim(&mut request)?;
+        self.check_budget(None)?;
+        let mut recorder = self.fim_recorder(&request, options).await;
+
+        let body = self
+            .open_stream(
+                &self.beta_endpoint(FIM_COMPLETIONS_PATH),
+                &request,
+                options,
+                recorder.permit(),
+            )
+            .await?;
+        let stream = streaming::fim_chunk_stream(body);
+        if recorder.is_idle() {
+            return Ok(stream);
+        }
+        Ok(Box::pin(stream.inspect(move |item| {
+            if let Ok(Some(usage)) = item.as_ref().map(|chunk| chunk.usage.as_ref()) {
+                recorder.record(usage);
+            }
+        })))
+    }
+
+    /// Run a request, executing tool calls with the registry until the
+    /// model answers without calling a tool
+    ///
+    /// The registry's tools are added to the request when it declares none.
+    /// All tool calls from one response run concurrently. A forced
+    /// `tool_choice` only applies to the first round trip so the loop can
+    /// terminate.
+    ///
+    /// # Errors
+    /// Returns `ToolError` if the model is still calling tools after the
+    /// registry's maximum number of iterations.
+    pub async fn run_with_tools(
+        &self,
+        mut request: ChatCompletionRequest,
+        registry: &ToolRegistry,
+    ) -> Result<ToolRun> {
+        if request.tools.is_none() && !registry.is_empty() {
+            request.tools = Some(registry.tools());
+        }
+
+        let mut steps = Vec::new();
+        for _ in 0..registry.max_iterations() {
+            let response = self.chat_completion(request.clone()).await?;
+            let message = &response.choices[0].message;
+            let calls = message.tool_calls.clone().unwrap_or_default();
+
+            if calls.is_empty() {
+                if message.content.is_some() {
+                    request.messages.push(message.to_message());
+                }
+                steps.push(ToolStep {
+                    response: response.clone(),
+                    invocations: Vec::new(),
+                });
+                return Ok(ToolRun {
+                    response,
+                    messages: request.messages,
+                    steps,
+                });
+            }
+
+            #[cfg(feature = "logging")]
+            tracing::debug!(count = calls.len(), "executing tool calls");
+
+            request.messages.push(message.to_message());
+
+            let invocations = registry.execute_all(&calls).await;
+            for invocation in &invocations {
+                request
+                    .messages
+                    .push(Message::tool(invocation.call.id.clone(), invocation.output.clone()));
+            }
+
+            if matches!(request.tool_choice, Some(ToolChoice::Required | ToolChoice::Function(_))) {
+                request.tool_choice = None;
+            }
+
+            steps.push(ToolStep {
+                response,
+                invocations,
+            });
+        }
+
+        Err(DeepSeekError::ToolError(format!(
+            "model was still calling tools after {} iterations",
+            registry.max_iterations()
+        )))
+    }
+
+    /// Check that the API is reachable and the API key is accepted
+    ///
+    /// Sends a minimal one-token chat completion.
+    pub async fn test_connection(&self) -> Result<()> {
+        self.chat()
+            .add_user_message("ping")
+            .with_max_tokens(1)
+            .send()
+            .await
+            .map(|_| ())
+    }
+
+    /// Validate a chat request against the configured model capabilities
+    ///
+    /// Reasoning kept in the history is always dropped. In lenient mode,
+    /// unsupported parameters are stripped first. With a configured
+    /// tokenizer, prompts that exceed the context window are rejected.
+    pub(crate) fn prepare_chat(&self, request: &mut ChatCompletionRequest) -> Result<()> {
+        let _stripped = request.strip_history_reasoning();
+        #[cfg(feature = "logging")]
+        if _stripped > 0 {
+            tracing::debug!(messages = _stripped, "dropping reasoning content from history");
+        }
+
+        let capabilities = self.config.capabilities(&request.model);
+        if self.config.validation_mode == ValidationMode::Lenient {
+            let stripped = request.strip_unsupported(&capabilities);
+            log_stripped(&request.model, &stripped);
+        }
+        request.validate_with(&capabilities)?;
+        if let Some(tokenizer) = &self.config.tokenizer {
+            tokenizer.check_request(request, &capabilities)?;
+        }
+        Ok(())
+    }
+
+    /// Validate a FIM request against the configured model capabilities
+    ///
+    /// In lenient mode, unsupported parameters are stripped first.
+    pub(crate) fn prepare_fim(&self, request: &mut FimCompletionRequest) -> Result<()> {
+        let capabilities = self.config.capabilities(&request.model);
+        if self.config.validation_mode == ValidationMode::Lenient {
+            let stripped = request.strip_unsupported(&capabilities);
+            log_stripped(&request.model, &stripped);
+        }
+        request.validate_with(&capabilities)
+    }
+
+    /// Refuse a request if a spend cap of the configured budget is reached
+    fn check_budget(&self, user: Option<&str>) -> Result<()> {
+        match &self.config.budget {
+            Some(budget) => budget.check(user),
+            None => Ok(()),
+        }
+    }
+
+    /// Cache key of a chat request, if the response cache applies to it
+    fn cache_key(&self, request: &ChatCompletionRequest, mode: CacheMode) -> Option<String> {
+        self.config.response_cache.as_ref()?;
+        let applies = match mode {
+            CacheMode::Default => cache::is_deterministic(request),
+            CacheMode::Bypass => false,
+            CacheMode::Refresh | CacheMode::OnlyIfCached => true,
+        };
+        if !applies {
+            return None;
+        }
+
+        cache::cache_key(request)
+            .inspect_err(|_err| {
+                #[cfg(feature = "logging")]
+                tracing::warn!(error = %_err, "could not compute response cache key");
+            })
+            .ok()
+    }
+
+    /// Look up a cached response; cache errors count as misses
+    fn cached_response(&self, key: &str) -> Option<ChatCompletionResponse> {
+        let cache = self.config.response_cache.as_ref()?;
+        cache
+            .get(key)
+            .inspect_err(|_err| {
+                #[cfg(feature = "logging")]
+                tracing::warn!(error = %_err, "response cache lookup failed");
+            })
+            .ok()
+            .flatten()
+    }
+
+    /// Store a response in the cache, ignoring cache errors
+    fn store_response(&self, key: &str, response: &ChatCompletionResponse) {
+        if let Some(cache) = &self.config.response_cache {
+            if let Err(_err) = cache.put(key, response) {
+                #[cfg(feature = "logging")]
+                tracing::warn!(error = %_err, "could not store response in cache");
+            }
+        }
+    }
+
+    /// Tracker recording a request's cost, if a budget is configured and
+    /// the model has a price
+    fn spend_tracker(&self, model: &Model, user: Option<&str>) -> Option<SpendTracker> {
+        let budget = self.config.budget.clone()?;
+        let pricing = self.config.pricing(model)?;
+        Some(SpendTracker {
+            budget,
+            pricing,
+            user: user.map(str::to_string),
+        })
+    }
+
+    /// Wait for a turn from the configured rate limiter, if any
+    ///
+    /// `estimate` is only called when the limiter limits tokens.
+    async fn acquire_permit(
+        &self,
+        priority: Priority,
+        estimate: impl FnOnce() -> usize,
+    ) -> Option<Permit> {
+        let limiter = self.config.rate_limiter.as_ref()?;
+        let tokens = if limiter.limits_tokens() {
+            u32::try_from(estimate()).unwrap_or(u32::MAX)
+        } else {
+            0
+        };
+        Some(limiter.acquire(priority, tokens).await)
+    }
+
+    /// Wait for a turn to send a chat request and prepare to record its usage
+    async fn chat_recorder(
+        &self,
+        request: &ChatCompletionRequest,
+        options: &RequestOptions,
+    ) -> UsageRecorder {
+        let permit = self
+            .acquire_permit(options.priority, || {
+                self.config.tokenizer().count_request(request)
+                    + request.max_tokens.unwrap_or(0) as usize
+            })
+            .await;
+        UsageRecorder {
+            tracker: self.spend_tracker(&request.model, request.user.as_deref()),
+            permit,
+        }
+    }
+
+    /// Wait for a turn to send a FIM request and prepare to record its usage
+    async fn fim_recorder(
+        &self,
+        request: &FimCompletionRequest,
+        options: &RequestOptions,
+    ) -> UsageRecorder {
+        let permit = self
+            .acquire_permit(options.priority, || {
+                let tokenizer = self.config.tokenizer();
+                tokenizer.count(&request.prompt)
+                    + request.suffix.as_deref().map_or(0, |suffix| tokenizer.count(suffix))
+                    + request.max_tokens.unwrap_or(0) as usize
+            })
+            .await;
+        UsageRecorder {
+            tracker: self.spend_tracker(&request.model, None),
+            permit,
+        }
+    }
+
+    /// Build the full URL for an API path
+    pub(crate) fn endpoint(&self, path: &str) -> String {
+        format!("{}{}", self.config.base_url.trim_end_matches('/'), path)
+    }
+
+    /// Build the full URL for a beta API path
+    pub(crate) fn beta_endpoint(&self, path: &str) -> String {
+        format!("{}{}", self.config.beta_url(), path)
+    }
+
+    /// Chat completions URL for a request
+    ///
+    /// Requests ending with an assistant prefix need the beta endpoint.
+    fn chat_url(&self, request: &ChatCompletionRequest) -> String {
+        if request.has_prefix() {
+            self.beta_endpoint(CHAT_COMPLETIONS_PATH)
+        } else {
+            self.endpoint(CHAT_COMPLETIONS_PATH)
+        }
+    }
+
+    /// POST a JSON body and decode the response
+    ///
+    /// The whole exchange is bounded by the effective request timeout.
+    /// Responses are reported to the rate limiter through `permit`.
+    pub(crate) async fn post<B, R>(
+        &self,
+        url: &str,
+        body: &B,
+        options: &RequestOptions,
+        permit: Option<&Permit>,
+    ) -> Result<R>
+    where
+        B: Serialize + ?Sized,
+        R: DeserializeOwned,
+    {
+        self.fetch(Method::POST, url, Some(body), options, permit).await
+    }
+
+    /// GET a URL and decode the response
+    ///
+    /// The whole exchange is bounded by the effective request timeout.
+    pub(crate) async fn get<R>(&self, url: &str, options: &RequestOptions) -> Result<R>
+    where
+        R: DeserializeOwned,
+    {
+        self.fetch::<(), R>(Method::GET, url, None, options, None).await
+    }
+
+    /// Send a request with an optional JSON body and decode the response
+    async fn fetch<B, R>(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<&B>,
+        options: &RequestOptions,
+        permit: Option<&Permit>,
+    ) -> Result<R>
+    where
+        B: Serialize + ?Sized,
+        R: DeserializeOwned,
+    {
+        let timeout = options.effective_timeout(&self.config);
+        let bytes = self
+            .with_retry(&method, permit, || async {
+                let response = self
+                    .send_once(method.clone(), url, body, options, Some(timeout))
+                    .await?;
+                read_body(response, timeout).await
+            })
+            .await?;
+
+        if bytes.is_empty() {
+            return Err(DeepSeekError::EmptyResponse);
+        }
+
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    /// POST a JSON body and return the response body as a byte stream
+    ///
+    /// Only an explicit per-request timeout bounds the stream.
+    #[cfg(feature = "streaming")]
+    async fn open_stream<B>(
+        &self,
+        url: &str,
+        body: &B,
+        options: &RequestOptions,
+        permit: Option<&Permit>,
+    ) -> Result<streaming::ByteStream>
+    where
+        B: Serialize + ?Sized,
+    {
+        let response = self
+            .with_retry(&Method::POST, permit, || {
+                self.send_once(Method::POST, url, Some(body), options, options.timeout)
+            })
+            .await?;
+
+        let timeout = options.effective_timeout(&self.config);
+        let body = response
+            .bytes_stream()
+            .map(move |bytes| bytes.map_err(|e| transport_error(e, timeout)));
+
+        Ok(Box::pin(body))
+    }
+
+    /// Run attempts of a request until one succeeds, following the
+    /// configured [`RetryPolicy`](crate::retry::RetryPolicy)
+    ///
+    /// Non-idempotent requests are not retried once part of a successful
+    /// response body was received. After any retry, the final error is
+    /// wrapped in `RetriesExhausted` with the number of attempts. Every
+    /// success and rate limit is reported to the rate limiter through
+    /// `permit`, and every outcome to the circuit breaker. An attempt the
+    /// breaker rejects is not sent; the request fails with `CircuitOpen`.
+    async fn with_retry<T, F, Fut>(
+        &self,
+        method: &Method,
+        permit: Option<&Permit>,
+        mut attempt: F,
+    ) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = std::result::Result<T, Failure>>,
+    {
+        let policy = &self.config.retry_policy;
+        let started = Instant::now();
+        let mut retries = 0;
+        let mut delay = Duration::ZERO;
+        loop {
+            let sent_at = Instant::now();
+            let call = self.breaker_call()?;
+            let result = attempt().await;
+            if let Some(call) = call {
+                call.record(result.as_ref().err().map(|failure| &failure.error));
+            }
+            let failure = match result {
+                Ok(value) => {
+                    if let Some(permit) = permit {
+                        permit.record_response(sent_at);
+                    }
+                    return Ok(value);
+                }
+                Err(failure) => failure,
+            };
+            if let Some(permit) = permit.filter(|_| failure.error.is_rate_limit()) {
+                permit.record_rate_limit(sent_at);
+            }
+
+            let retryable = retries < self.config.max_retries
+                && policy.should_retry(&failure.error)
+                && (method.is_idempotent() || !failure.body_received);
+            let next = retryable
+                .then(|| policy.next_delay(retries, delay, failure.retry_after))
+                .flatten()
+                .filter(|next| {
+                    policy
+                        .deadline
+                        .is_none_or(|deadline| started.elapsed() + *next < deadline)
+                });
+
+            let Some(next) = next else {
+                return Err(if retries == 0 {
+                    failure.error
+                } else {
+                    DeepSeekError::RetriesExhausted {
+                        attempts: retries + 1,
+                        source: Box::new(failure.error),
+                    }
+                });
+            };
+
+            #[cfg(feature = "logging")]
+            tracing::warn!(
+                error = %failure.error,
+                attempt = retries + 1,
+                delay_ms = next.as_millis() as u64,
+                "retrying DeepSeek request"
+            );
+            tokio::time::sleep(next).await;
+            delay = next;
+            retries += 1;
+        }
+    }
+
+    /// Ask the configured circuit breaker, if any, to send an attempt
+    fn breaker_call(&self) -> Result<Option<BreakerCall>> {
+        self.config
+            .circuit_breaker
+            .as_ref()
+            .map(CircuitBreaker::call)
+            .transpose()
+    }
+
+    /// Send a request once, turning non-success statuses into errors
+    async fn send_once<B>(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<&B>,
+        options: &RequestOptions,
+        timeout: Option<Duration>,
+    ) -> std::result::Result<reqwest::Response, Failure>
+    where
+        B: Serialize + ?Sized,
+    {
+        let reported_timeout = timeout.unwrap_or(self.config.timeout);
+        let mut request = self.request(method, url, options, timeout);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| transport_error(e, reported_timeout))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let retry_after = match status.as_u16() {
+                429 | 503 => response
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| parse_retry_after(value, SystemTime::now())),
+                _ => None,
+            };
+            let bytes = response
+                .bytes()
+                .await
+                .map_err(|e| transport_error(e, reported_timeout))?;
+            return Err(Failure {
+                error: api_error(status.as_u16(), &bytes),
+                retry_after,
+                body_received: false,
+            });
+        }
+
+        Ok(response)
+    }
+
+    /// Start a request to a URL with per-request overrides applied
+    pub(crate) fn request(
+        &self,
+        method: Method,
+        url: &str,
+        options: &RequestOptions,
+        timeout: Option<Duration>,
+    ) -> reqwest::RequestBuilder {
+        #[cfg(feature = "logging")]
+        tracing::debug!(%method, %url, "sending DeepSeek request");
+
+        let mut builder = self
+            .http
+            .request(method, url)
+            .headers(options.headers.clone());
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
+        builder
+    }
+}
+
+/// Records the usage of a request in the budget and the rate limiter
+///
+/// Holds the request's rate limiter permit until dropped.
+struct UsageRecorder {
+    tracker: Option<SpendTracker>,
+    permit: Option<Permit>,
+}
+
+impl UsageRecorder {
+    fn permit(&self) -> Option<&Permit> {
+        self.permit.as_ref()
+    }
+
+    /// Whether there is nothing to record
+    #[cfg(feature = "streaming")]
+    fn is_idle(&self) -> bool {
+        self.tracker.is_none() && self.permit.is_none()
+    }
+
+    fn record(&mut self, usage: &Usage) {
+        if let Some(tracker) = &self.tracker {
+            tracker.record(usage);
+        }
+        if let Some(permit) = &mut self.permit {
+            permit.record_tokens(usage.total_tokens);
+        }
+    }
+}
+
+/// Warn about parameters stripped in lenient validation mode
+fn log_stripped(_model: &Model, _stripped: &[String]) {
+    #[cfg(feature = "logging")]
+    for change in _stripped {
+        tracing::warn!(model = %_model, "{}; removed from request", change);
+    }
+}
+
+/// Convert a transport-level error, turning timeouts into `TimeoutError`
+pub(crate) fn transport_error(err: reqwest::Error, timeout: Duration) -> DeepSeekError {
+    if err.is_timeout() {
+        // Rounded up so sub-second timeouts are not reported as 0 seconds
+        DeepSeekError::TimeoutError(timeout.as_secs_f64().ceil() as u64)
+    } else {
+        DeepSeekError::HttpError(err)
+    }
+}
+
+/// Build an `ApiError` from a non-success response body
+///
+/// Uses the message from the API's error payload when it can be decoded,
+/// falling back to the raw body text.
+pub(crate) fn api_error(status: u16, body: &[u8]) -> DeepSeekError {
+    let message = match serde_json::from_slice::<ApiErrorResponse>(body) {
+        Ok(error) => error.error.message,
+        Err(_) => {
+            let text = String::from_utf8_lossy(body).trim().to_string();
+            if text.is_empty() {
+                format!("HTTP {}", status)
+            } else {
+                text
+            }
+        }
+    };
+
+    DeepSeekError::ApiError { status, message }
+}
+
+/// Read a successful response body, noting whether any bytes arrived
+/// before a failure
+async fn read_body(
+    mut response: reqwest::Response,
+    timeout: Duration,
+) -> std::result::Result<Vec<u8>, Failure> {
+    let mut body = Vec::new();
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+            Ok(None) => return Ok(body),
+            Err(err) => {
+                return Err(Failure {
+                    error: transport_error(err, timeout),
+                    retry_after: None,
+                    body_received: !body.is_empty(),
+                })
+            }
+        }
+    }
+}
+
+/// Builder for chat completion requests
+///
+/// Created with [`DeepSeekClient::chat`]. Every field of
+/// [`ChatCompletionRequest`] can be set, along with per-request transport
+/// overrides. The builder state is public so it can be inspected before
+/// sending.
+#[derive(Debug, Clone)]
+pub struct ChatBuilder<'a> {
+    client: &'a DeepSeekClient,
+
+    /// Messages in the conversation
+    pub messages: Vec<Message>,
+
+    /// The model to use
+    pub model: Model,
+
+    /// Temperature for randomness
+    pub temperature: Option<Temperature>,
+
+    /// Maximum tokens to generate
+    pub max_tokens: Option<u32>,
+
+    /// Top-p sampling parameter
+    pub top_p: Option<f32>,
+
+    /// Frequency penalty (-2.0 to 2.0)
+    pub frequency_penalty: Option<f32>,
+
+    /// Presence penalty (-2.0 to 2.0)
+    pub presence_penalty: Option<f32>,
+
+    /// Stop sequences
+    pub stop: Option<Vec<String>>,
+
+    /// Number of completions to generate
+    pub n: Option<u32>,
+
+    /// User identifier for tracking
+    pub user: Option<String>,
+
+    /// Tools the model may call
+    pub tools: Option<Vec<Tool>>,
+
+    /// Controls which tool the model calls
+    pub tool_choice: Option<ToolChoice>,
+
+    /// Output format
+    pub response_format: Option<ResponseFormat>,
+
+    /// Whether to return log probabilities of the output tokens
+    pub logprobs: Option<bool>,
+
+    /// Number of most likely alternatives to return per token
+    pub top_logprobs: Option<u32>,
+
+    /// How many times [`ChatBuilder::send_json`] re-asks the model after
+    /// a reply that fails to parse
+    pub json_repair_attempts: u32,
+
+    /// How the messages are trimmed before sending
+    pub context_strategy: Option<Arc<dyn ContextStrategy>>,
+
+    /// Per-request transport overrides
+    pub options: RequestOptions,
+}
+
+impl<'a> ChatBuilder<'a> {
+    /// Create an empty builder bound to a client
+    pub fn new(client: &'a DeepSeekClient) -> Self {
+        Self {
+            client,
+            messages: Vec::new(),
+            model: Model::default(),
+            temperature: None,
+            max_tokens: None,
+            top_p: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            stop: None,
+            n: None,
+            user: None,
+            tools: None,
+            tool_choice: None,
+            response_format: None,
+            logprobs: None,
+            top_logprobs: None,
+            json_repair_attempts: 0,
+            context_strategy: None,
+            options: RequestOptions::default(),
+        }
+    }
+
+    /// Add a message
+    pub fn add_message(mut self, message: Message) -> Self {
+        self.messages.push(message);
+        self
+    }
+
+    /// Add a system message
+    pub fn add_system_message(self, content: impl Into<String>) -> Self {
+        self.add_message(Message::system(content))
+    }
+
+    /// Add a user message
+    pub fn add_user_message(self, content: impl Into<String>) -> Self {
+        self.add_message(Message::user(content))
+    }
+
+    /// Add an assistant message
+    pub fn add_assistant_message(self, content: impl Into<String>) -> Self {
+        self.add_message(Message::assistant(content))
+    }
+
+    /// Add a tool result message answering a tool call
+    pub fn add_tool_message(
+        self,
+        tool_call_id: impl Into<String>,
+        content: impl Into<String>,
+    ) -> Self {
+        self.add_message(Message::tool(tool_call_id, content))
+    }
+
+    /// Append several messages
+    pub fn add_messages(mut self, messages: impl IntoIterator<Item = Message>) -> Self {
+        self.messages.extend(messages);
+        self
+    }
+
+    /// Force the reply to start with `prefix` (beta)
+    ///
+    /// Appends an assistant prefix message, so this must be the last message
+    /// added. The request is sent to the beta endpoint and the response
+    /// content only holds the text that follows the prefix.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use deepseek_rust::{DeepSeekClient, Result};
+    /// # async fn run(client: DeepSeekClient) -> Result<()> {
+    /// let response = client
+    ///     .chat()
+    ///     .add_user_message("Write a quicksort in Rust")
+    ///     .continue_from("```rust\n")
+    ///     .add_stop("```")
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn continue_from(self, prefix: impl Into<String>) -> Self {
+        self.add_message(Message::assistant_prefix(prefix))
+    }
+
+    /// Set the model
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Set the temperature
+    ///
+    /// # Errors
+    /// Returns an error if the value is outside 0.0 - 2.0
+    pub fn with_temperature(mut self, temperature: f32) -> Result<Self> {
+        self.temperature = Some(Temperature::new(temperature)?);
+        Ok(self)
+    }
+
+    /// Set the maximum number of tokens to generate
+    pub fn with_max_tokens(mut self, tokens: u32) -> Self {
+        self.max_tokens = Some(tokens);
+        self
+    }
+
+    /// Set top-p sampling
+    pub fn with_top_p(mut self, top_p: f32) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    /// Set frequency penalty
+    pub fn with_frequency_penalty(mut self, penalty: f32) -> Self {
+        self.frequency_penalty = Some(penalty);
+        self
+    }
+
+    /// Set presence penalty
+    pub fn with_presence_penalty(mut self, penalty: f32) -> Self {
+        self.presence_penalty = Some(penalty);
+        self
+    }
+
+    /// Set stop sequences
+    pub fn with_stop<I, S>(mut self, stop: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.stop = Some(stop.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Add a single stop sequence
+    pub fn add_stop(mut self, stop: impl Into<String>) -> Self {
+        self.stop.get_or_insert_with(Vec::new).push(stop.into());
+        self
+    }
+
+    /// Set number of completions
+    pub fn with_n(mut self, n: u32) -> Self {
+        self.n = Some(n);
+        self
+    }
+
+    /// Set user identifier
+    pub fn with_user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    /// Set the tools the model may call
+    pub fn with_tools(mut self, tools: Vec<Tool>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    /// Add a tool the model may call
+    pub fn add_tool(mut self, tool: Tool) -> Self {
+        self.tools.get_or_insert_with(Vec::new).push(tool);
+        self
+    }
+
+    /// Set the tool choice
+    pub fn with_tool_choice(mut self, choice: ToolChoice) -> Self {
+        self.tool_choice = Some(choice);
+        self
+    }
+
+    /// Set the response format
+    pub fn with_response_format(mut self, format: ResponseFormat) -> Self {
+        self.response_format = Some(format);
+        self
+    }
+
+    /// Ask the model to reply with a single JSON object
+    ///
+    /// A system or user message must mention JSON, as the API requires.
+    pub fn json_mode(self) -> Self {
+        self.with_response_format(ResponseFormat::JsonObject)
+    }
+
+    /// Request log probabilities of the output tokens
+    pub fn with_logprobs(mut self, logprobs: bool) -> Self {
+        self.logprobs = Some(logprobs);
+        self
+    }
+
+    /// Request the most likely alternatives for each token (0-20)
+    ///
+    /// Also enables `logprobs`.
+    pub fn with_top_logprobs(mut self, top_logprobs: u32) -> Self {
+        self.logprobs = Some(true);
+        self.top_logprobs = Some(top_logprobs);
+        self
+    }
+
+    /// Set how many times [`ChatBuilder::send_json`] re-asks the model,
+    /// passing along the parse error, when its reply does not deserialize
+    pub fn with_json_repair_attempts(mut self, attempts: u32) -> Self {
+        self.json_repair_attempts = attempts;
+        self
+    }
+
+    /// Set a timeout for this request, overriding the client configuration
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the position of this request in the rate limiter's queue
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.options.priority = priority;
+        self
+    }
+
+    /// Set how this request uses the response cache
+    pub fn with_cache_mode(mut self, mode: CacheMode) -> Self {
+        self.options.cache = mode;
+        self
+    }
+
+    /// Add an extra HTTP header to this request
+    ///
+    /// # Errors
+    /// Returns an error if the name or value is not a valid HTTP header
+    pub fn with_header(mut self, name: &str, value: &str) -> Result<Self> {
+        self.options.insert_header(name, value)?;
+        Ok(self)
+    }
+
+    /// Set how the messages are trimmed before sending
+    ///
+    /// See the [`context`](crate::context) module for the built-in strategies.
+    pub fn with_context_strategy(mut self, strategy: impl ContextStrategy + 'static) -> Self {
+        self.context_strategy = Some(Arc::new(strategy));
+        self
+    }
+
+    /// Build the request without sending it
+    ///
+    /// The request is validated against the client's model capabilities;
+    /// in lenient mode unsupported parameters are stripped instead. The
+    /// context strategy is only applied when sending.
+    pub fn build(&self) -> Result<ChatCompletionRequest> {
+        let mut request = self.request();
+        self.client.prepare_chat(&mut request)?;
+        Ok(request)
+    }
+
+    /// Count the prompt tokens of the request as built
+    ///
+    /// Uses the client's tokenizer, or the approximate estimator if none is
+    /// configured. The context strategy is not applied.
+    pub fn count_tokens(&self) -> usize {
+        self.client.config().tokenizer().count_request(&self.request())
+    }
+
+    /// Estimate the cost of the request in USD before sending it
+    ///
+    /// Prices the counted prompt tokens as cache misses and assumes the
+    /// reply uses all of `max_tokens`, or the model's output limit if unset,
+    /// so the estimate is an upper bound. Returns `None` for models without
+    /// a price.
+    pub fn estimate_cost(&self) -> Option<f64> {
+        let config = self.client.config();
+        let pricing = config.pricing(&self.model)?;
+        let output_tokens = self
+            .max_tokens
+            .or(config.capabilities(&self.model).max_output_tokens)
+            .unwrap_or(0);
+        Some(pricing.estimate(self.count_tokens(), output_tokens as usize, SystemTime::now()))
+    }
+
+    /// Build the request with the context strategy applied
+    async fn build_trimmed(&self) -> Result<ChatCompletionRequest> {
+        let mut request = self.request();
+        context::apply_strategy(self.context_strategy.as_deref(), self.client, &mut request).await?;
+        self.client.prepare_chat(&mut request)?;
+        Ok(request)
+    }
+
+    /// The request as set on the builder, before validation
+    fn request(&self) -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            model: self.model.clone(),
+            messages: self.messages.clone(),
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            top_p: self.top_p,
+            frequency_penalty: self.frequency_penalty,
+            presence_penalty: self.presence_penalty,
+            stop: self.stop.clone(),
+            stream: None,
+            n: self.n,
+            user: self.user.clone(),
+            tools: self.tools.clone(),
+            tool_choice: self.tool_choice.clone(),
+            response_format: self.response_format,
+            logprobs: self.logprobs,
+            top_logprobs: self.top_logprobs,
+        }
+    }
+
+    /// Send the request
+    pub async fn send(self) -> Result<ChatCompletionResponse> {
+        let request = self.build_trimmed().await?;
+        self.client
+            .chat_completion_with_options(request, &self.options)
+            .await
+    }
+
+    /// Send the request in JSON mode and deserialize the answer
+    ///
+    /// Code fences around the JSON are stripped. When the reply does not
+    /// deserialize into `T`, the model is re-asked with the error up to
+    /// [`ChatBuilder::json_repair_attempts`] times.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use deepseek_rust::{DeepSeekClient, Result};
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Colors {
+    ///     colors: Vec<String>,
+    /// }
+    ///
+    /// # async fn run(client: DeepSeekClient) -> Result<()> {
+    /// let colors: Colors = client
+    ///     .chat()
+    ///     .add_system_message("Reply in JSON with a `colors` array")
+    ///     .add_user_message("List three colors")
+    ///     .with_json_repair_attempts(1)
+    ///     .send_json()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send_json<T: DeserializeOwned>(self) -> Result<T> {
+        let builder = self.json_mode();
+        let mut request = builder.build_trimmed().await?;
+        let mut attempts_left = builder.json_repair_attempts;
+
+        loop {
+            let response = builder
+                .client
+                .chat_completion_with_options(request.clone(), &builder.options)
+                .await?;
+
+            match response.parse_json::<T>() {
+                Ok(value) => return Ok(value),
+                Err(err) if attempts_left > 0 => {
+                    #[cfg(feature = "logging")]
+                    tracing::debug!(error = %err, attempts_left, "re-asking for valid JSON");
+
+                    attempts_left -= 1;
+                    // JSON mode may return no content; an empty assistant
+                    // turn would fail validation, so only re-ask
+                    let reply = response.get_content().unwrap_or_default();
+                    if !reply.trim().is_empty() {
+                        request.messages.push(Message::assistant(reply));
+                    }
+                    request.messages.push(Message::user(format!(
+                        "Your previous reply could not be parsed as the expected JSON ({}). \
+                         Reply again with only the corrected JSON object.",
+                        err
+                    )));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Send the request and stream the response as it is generated
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use deepseek_rust::{DeepSeekClient, Result};
+    /// use futures::StreamExt;
+    ///
+    /// # async fn run(client: DeepSeekClient) -> Result<()> {
+    /// let mut stream = client
+    ///     .chat()
+    ///     .add_user_message("Tell me a story")
+    ///     .stream()
+    ///     .await?;
+    ///
+    /// while let Some(chunk) = stream.next().await {
+    ///     if let Some(content) = &chunk?.choices[0].delta.content {
+    ///         print!("{}", content);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "streaming")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "streaming")))]
+    pub async fn stream(self) -> Result<ChatStream> {
+        let request = self.build_trimmed().await?;
+        self.client
+            .chat_completion_stream_with_options(request, &self.options)
+            .await
+    }
+}
+
+/// Builder for fill-in-the-middle completion requests
+///
+/// Created with [`DeepSeekClient::fim`]. Mirrors [`ChatBuilder`] for the
+/// fields of [`FimCompletionRequest`].
+#[derive(Debug, Clone)]
+pub struct FimBuilder<'a> {
+    client: &'a DeepSeekClient,
+
+    /// Text before the insertion point
+    pub prompt: String,
+
+    /// Text after the insertion point
+    pub suffix: Option<String>,
+
+    /// The model to use
+    pub model: Model,
+
+    /// Maximum tokens to generate
+    pub max_tokens: Option<u32>,
+
+    /// Whether to echo the prompt back before the completion
+    pub echo: Option<bool>,
+
+    /// Temperature for randomness
+    pub temperature: Option<Temperature>,
+
+    /// Top-p sampling parameter
+    pub top_p: Option<f32>,
+
+    /// Frequency penalty (-2.0 to 2.0)
+    pub frequency_penalty: Option<f32>,
+
+    /// Presence penalty (-2.0 to 2.0)
+    pub presence_penalty: Option<f32>,
+
+    /// Stop sequences
+    pub stop: Option<Vec<String>>,
+
+    /// Number of most likely alternatives to return per token
+    pub logprobs: Option<u32>,
+
+    /// Per-request transport overrides
+    pub options: RequestOptions,
+}
+
+impl<'a> FimBuilder<'a> {
+    /// Create an empty builder bound to a client
+    pub fn new(client: &'a DeepSeekClient) -> Self {
+        Self {
+            client,
+            prompt: String::new(),
+            suffix: None,
+            model: Model::Coder,
+            max_tokens: None,
+            echo: None,
+            temperature: None,
+            top_p: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            stop: None,
+            logprobs: None,
+            options: RequestOptions::default(),
+        }
+    }
+
+    /// Set the text before the insertion point
+    pub fn with_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = prompt.into();
+        self
+    }
+
+    /// Set the text after the insertion point
+    pub fn with_suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.suffix = Some(suffix.into());
+        self
+    }
+
+    /// Set the model
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Set the maximum number of tokens to generate (at most 4096)
+    pub fn with_max_tokens(mut self, tokens: u32) -> Self {
+        self.max_tokens = Some(tokens);
+        self
+    }
+
+    /// Echo the prompt back before the completion
+    pub fn with_echo(mut self, echo: bool) -> Self {
+        self.echo = Some(echo);
+        self
+    }
+
+    /// Set the temperature
+    ///
+    /// # Errors
+    /// Returns an error if the temperature is outside 0.0-2.0
+    pub fn with_temperature(mut self, temperature: f32) -> Result<Self> {
+        self.temperature = Some(Temperature::new(temperature)?);
+        Ok(self)
+    }
+
+    /// Set top-p sampling
+    pub fn with_top_p(mut self, top_p: f32) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    /// Set frequency penalty
+    pub fn with_frequency_penalty(mut self, penalty: f32) -> Self {
+        self.frequency_penalty = Some(penalty);
+        self
+    }
+
+    /// Set presence penalty
+    pub fn with_presence_penalty(mut self, penalty: f32) -> Self {
+        self.presence_penalty = Some(penalty);
+        self
+    }
+
+    /// Set stop sequences
+    pub fn with_stop<I, S>(mut self, stop: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.stop = Some(stop.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Add a single stop sequence
+    pub fn add_stop(mut self, stop: impl Into<String>) -> Self {
+        self.stop.get_or_insert_with(Vec::new).push(stop.into());
+        self
+    }
+
+    /// Request log probabilities with up to this many alternatives per token
+    pub fn with_logprobs(mut self, logprobs: u32) -> Self {
+        self.logprobs = Some(logprobs);
+        self
+    }
+
+    /// Set a timeout for this request, overriding the client configuration
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the position of this request in the rate limiter's queue
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.options.priority = priority;
+        self
+    }
+
+    /// Add an extra HTTP header to this request
+    ///
+    /// # Errors
+    /// Returns an error if the name or value is not a valid HTTP header
+    pub fn with_header(mut self, name: &str, value: &str) -> Result<Self> {
+        self.options.insert_header(name, value)?;
+        Ok(self)
+    }
+
+    /// Build the request without sending it
+    ///
+    /// The request is validated against the client's model capabilities;
+    /// in lenient mode unsupported parameters are stripped instead.
+    pub fn build(&self) -> Result<FimCompletionRequest> {
+        let mut request = FimCompletionRequest {
+            model: self.model.clone(),
+            prompt: self.prompt.clone(),
+            suffix: self.suffix.clone(),
+            max_tokens: self.max_tokens,
+            echo: self.echo,
+            temperature: self.temperature,
+            top_p: self.top_p,
+            frequency_penalty: self.frequency_penalty,
+            presence_penalty: self.presence_penalty,
+            stop: self.stop.clone(),
+            logprobs: self.logprobs,
+            stream: None,
+        };
+        self.client.prepare_fim(&mut request)?;
+        Ok(request)
+    }
+
+    /// Send the request
+    pub async fn send(self) -> Result<FimCompletionResponse> {
+        let request = self.build()?;
+        self.client
+            .fim_completion_with_options(request, &self.options)
+            .await
+    }
+
+    /// Send the request and stream the completion
+    #[cfg(feature = "streaming")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "streaming")))]
+    pub async fn stream(self) -> Result<FimStream> {
+        let request = self.build()?;
+        self.client
+            .fim_completion_stream_with_options(request, &self.options)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_creation() {
+        let client = DeepSeekClient::new(DeepSeekConfig::new("test-key"));
+        assert!(client.is_ok());
+
+        let client = DeepSeekClient::new(DeepSeekConfig::new(""));
+        assert!(client.is_err());
+
+        let client = DeepSeekClient::new(DeepSeekConfig::new("test-key").with_proxy("::not a url::"));
+        assert!(matches!(client, Err(DeepSeekError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_endpoint() {
+        let config = DeepSeekConfig::new("test-key").with_base_url("https://example.com/v1/");
+        let client = DeepSeekClient::new(config).unwrap();
+        assert_eq!(client.endpoint(CHAT_COMPLETIONS_PATH), "https://example.com/v1/chat/completions");
+    }
+
+    #[test]
+    fn test_api_error_parsing() {
+        let body = br#"{"error":{"message":"Invalid API key","type":"authentication_error"}}"#;
+        match api_error(401, body) {
+            DeepSeekError::ApiError { status, message } => {
+                assert_eq!(status, 401);
+                assert_eq!(message, "Invalid API key");
+            }
+            other => panic!("Expected ApiError, got {:?}", other),
+        }
+
+        match api_error(500, b"Internal Server Error") {
+            DeepSeekError::ApiError { message, .. } => assert_eq!(message, "Internal Server Error"),
+            other => panic!("Expected ApiError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_chat_builder_build() {
+        let client = DeepSeekClient::new(DeepSeekConfig::new("test-key")).unwrap();
+        let request = client
+            .chat()
+            .add_system_message("System")
+            .add_user_message("Hello")
+            .with_model(Model::Coder)
+            .with_temperature(0.2)
+            .unwrap()
+            .with_top_p(0.9)
+            .with_frequency_penalty(0.5)
+            .with_presence_penalty(-0.5)
+            .with_stop(["END"])
+            .add_stop("STOP")
+            .with_n(2)
+            .with_user("user-1")
+            .build()
+            .unwrap();
+
+        assert_eq!(request.messages.len(), 2);
+        assert_eq!(request.model, Model::Coder);
+        assert_eq!(request.temperature, Some(Temperature::new(0.2).unwrap()));
+        assert_eq!(request.top_p, Some(0.9));
+        assert_eq!(request.stop, Some(vec!["END".to_string(), "STOP".to_string()]));
+        assert_eq!(request.n, Some(2));
+        assert_eq!(request.user.as_deref(), Some("user-1"));
+    }
+
+    #[test]
+    fn test_chat_builder_validation() {
+        let client = DeepSeekClient::new(DeepSeekConfig::new("test-key")).unwrap();
+        assert!(client.chat().build().is_err());
+        assert!(client.chat().add_user_message("Hi").with_top_p(1.5).build().is_err());
+        assert!(client.chat().add_user_message("Hi").with_n(0).build().is_err());
+    }
+
+    #[test]
+    fn test_chat_builder_options() {
+        let client = DeepSeekClient::new(DeepSeekConfig::new("test-key")).unwrap();
+        let builder = client
+            .chat()
+            .with_timeout(Duration::from_secs(5))
+            .with_header("x-request-id", "abc")
+            .unwrap();
+
+        assert_eq!(builder.options.timeout, Some(Duration::from_secs(5)));
+        assert_eq!(builder.options.headers.get("x-request-id").unwrap(), "abc");
+        assert!(client.chat().with_header("bad header", "x").is_err());
+    }
+
+    #[test]
+    fn test_chat_builder_json_mode() {
+        let client = DeepSeekClient::new(DeepSeekConfig::new("test-key")).unwrap();
+        let request = client
+            .chat()
+            .add_user_message("Answer in JSON")
+            .json_mode()
+            .build()
+            .unwrap();
+
+        assert_eq!(request.response_format, Some(ResponseFormat::JsonObject));
+        assert!(client.chat().add_user_message("Hi").json_mode().build().is_err());
+    }
+
+    #[test]
+    fn test_chat_builder_continue_from() {
+        let client = DeepSeekClient::new(DeepSeekConfig::new("test-key")).unwrap();
+        let request = client
+            .chat()
+            .add_user_message("Write a hello world program")
+            .continue_from("```rust\n")
+            .build()
+            .unwrap();
+
+        assert!(request.has_prefix());
+        assert_eq!(
+            client.chat_url(&request),
+            "https://api.deepseek.com/beta/chat/completions"
+        );
+        assert!(client
+            .chat()
+            .add_user_message("Hi")
+            .continue_from("Hello")
+            .add_user_message("Again")
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_chat_builder_validation_mode() {
+        let strict = DeepSeekClient::new(DeepSeekConfig::new("test-key")).unwrap();
+        let lenient = DeepSeekClient::new(
+            DeepSeekConfig::new("test-key").with_validation_mode(ValidationMode::Lenient),
+        )
+        .unwrap();
+
+        let build = |client: &DeepSeekClient| {
+            client
+                .chat()
+                .add_user_message("Hi")
+                .with_model(Model::Reasoner)
+                .with_temperature(0.7)
+                .unwrap()
+                .with_top_logprobs(3)
+                .build()
+        };
+
+        assert!(matches!(build(&strict), Err(DeepSeekError::InvalidParameter(_))));
+        let request = build(&lenient).unwrap();
+        assert_eq!(request.temperature, None);
+        assert_eq!(request.logprobs, None);
+        assert_eq!(request.top_logprobs, None);
+
+        // FIM support cannot be stripped
+        assert!(lenient.fim().with_prompt("x").with_model(Model::Reasoner).build().is_err());
+    }
+
+    #[test]
+    fn test_fim_builder_build() {
+        let client = DeepSeekClient::new(DeepSeekConfig::new("test-key")).unwrap();
+        let request = client
+            .fim()
+            .with_prompt("def add(a, b):")
+            .with_suffix("    return result")
+            .with_max_tokens(32)
+            .with_echo(true)
+            .add_stop("\n\n")
+            .with_logprobs(3)
+            .build()
+            .unwrap();
+
+        assert_eq!(request.model, Model::Coder);
+        assert_eq!(request.suffix.as_deref(), Some("    return result"));
+        assert_eq!(request.stop, Some(vec!["\n\n".to_string()]));
+        assert_eq!(request.logprobs, Some(3));
+        assert!(client.fim().build().is_err());
+        assert_eq!(
+            client.beta_endpoint(FIM_COMPLETIONS_PATH),
+            "https://api.deepseek.com/beta/completions"
+        );
+    }
+}
@@ -1,12 +1,26 @@
 //! Configuration module for DeepSeek API client
 
+use crate::breaker::CircuitBreaker;
+use crate::budget::Budget;
+use crate::cache::ResponseCache;
 use crate::error::{DeepSeekError, Result};
+use crate::limiter::RateLimiter;
+use crate::models::capabilities::{ModelCapabilities, ValidationMode};
+use crate::models::pricing::ModelPricing;
+use crate::models::request::Model;
+use crate::retry::RetryPolicy;
+use crate::tokenizer::Tokenizer;
 use secrecy::{ExposeSecret, Secret};
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Default API base URL
 pub const DEFAULT_BASE_URL: &str = "https://api.deepseek.com";
 
+/// Path appended to the base URL to reach beta endpoints
+pub const BETA_PATH: &str = "/beta";
+
 /// Default timeout in seconds
 pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
 
@@ -22,12 +36,20 @@ pub struct DeepSeekConfig {
     /// Base URL for the API
     pub base_url: String,
     
+    /// Base URL for beta endpoints such as FIM completion
+    /// 
+    /// When unset, `/beta` is appended to `base_url`.
+    pub beta_base_url: Option<String>,
+    
     /// Request timeout duration
     pub timeout: Duration,
     
     /// Maximum number of retries for failed requests
     pub max_retries: u32,
     
+    /// How failed requests are retried
+    pub retry_policy: RetryPolicy,
+    
     /// Whether to validate SSL certificates (should be true in production)
     pub validate_certs: bool,
     
@@ -36,6 +58,36 @@ pub struct DeepSeekConfig {
     
     /// User agent string
     pub user_agent: String,
+    
+    /// Balance below which a [`BalanceWatcher`] reports a low balance
+    /// 
+    /// [`BalanceWatcher`]: crate::balance::BalanceWatcher
+    pub low_balance_threshold: Option<f64>,
+    
+    /// How requests using unsupported model features are handled
+    pub validation_mode: ValidationMode,
+    
+    /// Capabilities overriding the built-in ones, per model
+    pub model_capabilities: HashMap<Model, ModelCapabilities>,
+    
+    /// Tokenizer used to reject prompts that exceed the context window
+    /// before sending
+    pub tokenizer: Option<Tokenizer>,
+    
+    /// Prices overriding the built-in ones, per model
+    pub model_pricing: HashMap<Model, ModelPricing>,
+    
+    /// Spend caps checked before every request
+    pub budget: Option<Budget>,
+    
+    /// Rate limits and queue applied to completion requests
+    pub rate_limiter: Option<RateLimiter>,
+    
+    /// Circuit breaker failing requests fast during outages
+    pub circuit_breaker: Option<CircuitBreaker>,
+    
+    /// Cache serving repeated chat completions
+    pub response_cache: Option<Arc<dyn ResponseCache>>,
 }
 
 impl DeepSeekConfig {
@@ -51,11 +103,22 @@ impl DeepSeekConfig {
         Self {
             api_key: Secret::new(api_key.into()),
             base_url: DEFAULT_BASE_URL.to_string(),
+            beta_base_url: None,
             timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
             max_retries: DEFAULT_MAX_RETRIES,
+            retry_policy: RetryPolicy::default(),
             validate_certs: true,
             proxy: None,
             user_agent: format!("deepseek-rust/{}", env!("CARGO_PKG_VERSION")),
+            low_balance_threshold: None,
+            validation_mode: ValidationMode::default(),
+            model_capabilities: HashMap::new(),
+            tokenizer: None,
+            model_pricing: HashMap::new(),
+            budget: None,
+            rate_limiter: None,
+            circuit_breaker: None,
+            response_cache: None,
         }
     }
     
@@ -64,9 +127,11 @@ impl DeepSeekConfig {
     /// Looks for:
     /// - `DEEPSEEK_API_KEY` (required)
     /// - `DEEPSEEK_API_BASE_URL` (optional)
+    /// - `DEEPSEEK_API_BETA_BASE_URL` (optional)
     /// - `DEEPSEEK_TIMEOUT_SECONDS` (optional)
     /// - `DEEPSEEK_MAX_RETRIES` (optional)
     /// - `DEEPSEEK_PROXY` (optional)
+    /// - `DEEPSEEK_LOW_BALANCE_THRESHOLD` (optional)
     /// 
     /// # Example
     /// ```no_run
@@ -96,6 +161,9 @@ impl DeepSeekConfig {
         let base_url = std::env::var("DEEPSEEK_API_BASE_URL")
             .unwrap_or_else(|_| DEFAULT_BASE_URL.to_string());
         
+        // Get optional beta base URL
+        let beta_base_url = std::env::var("DEEPSEEK_API_BETA_BASE_URL").ok();
+        
         // Get optional timeout
         let timeout_secs = std::env::var("DEEPSEEK_TIMEOUT_SECONDS")
             .ok()
@@ -111,6 +179,11 @@ impl DeepSeekConfig {
         // Get optional proxy
         let proxy = std::env::var("DEEPSEEK_PROXY").ok();
         
+        // Get optional low balance threshold
+        let low_balance_threshold = std::env::var("DEEPSEEK_LOW_BALANCE_THRESHOLD")
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok());
+        
         // Get optional cert validation setting
         let validate_certs = std::env::var("DEEPSEEK_VALIDATE_CERTS")
             .ok()
@@ -120,11 +193,22 @@ impl DeepSeekConfig {
         Ok(Self {
             api_key: Secret::new(api_key),
             base_url,
+            beta_base_url,
             timeout: Duration::from_secs(timeout_secs),
             max_retries,
+            retry_policy: RetryPolicy::default(),
             validate_certs,
             proxy,
             user_agent: format!("deepseek-rust/{}", env!("CARGO_PKG_VERSION")),
+            low_balance_threshold,
+            validation_mode: ValidationMode::default(),
+            model_capabilities: HashMap::new(),
+            tokenizer: None,
+            model_pricing: HashMap::new(),
+            budget: None,
+            rate_limiter: None,
+            circuit_breaker: None,
+            response_cache: None,
         })
     }
     
@@ -134,6 +218,20 @@ impl DeepSeekConfig {
         self
     }
     
+    /// Set the base URL for beta endpoints
+    pub fn with_beta_base_url(mut self, url: impl Into<String>) -> Self {
+        self.beta_base_url = Some(url.into());
+        self
+    }
+    
+    /// Base URL used for beta endpoints
+    pub fn beta_url(&self) -> String {
+        match &self.beta_base_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => format!("{}{}", self.base_url.trim_end_matches('/'), BETA_PATH),
+        }
+    }
+    
     /// Set the timeout duration
     pub fn with_timeout(mut self, timeout: Duration) -> Self {
         self.timeout = timeout;
@@ -146,6 +244,12 @@ impl DeepSeekConfig {
         self
     }
     
+    /// Set how failed requests are retried
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+    
     /// Set proxy URL
     pub fn with_proxy(mut self, proxy: impl Into<String>) -> Self {
         self.proxy = Some(proxy.into());
@@ -164,6 +268,87 @@ impl DeepSeekConfig {
         self
     }
     
+    /// Set the balance below which a low balance is reported
+    pub fn with_low_balance_threshold(mut self, threshold: f64) -> Self {
+        self.low_balance_threshold = Some(threshold);
+        self
+    }
+    
+    /// Set how requests using unsupported model features are handled
+    pub fn with_validation_mode(mut self, mode: ValidationMode) -> Self {
+        self.validation_mode = mode;
+        self
+    }
+    
+    /// Override the capabilities of a model
+    /// 
+    /// Useful to describe a [`Model::Custom`] model, or a model whose limits
+    /// changed since this version of the crate.
+    pub fn with_model_capabilities(mut self, model: Model, capabilities: ModelCapabilities) -> Self {
+        self.model_capabilities.insert(model, capabilities);
+        self
+    }
+    
+    /// Capabilities of a model, preferring configured overrides
+    pub fn capabilities(&self, model: &Model) -> ModelCapabilities {
+        self.model_capabilities
+            .get(model)
+            .cloned()
+            .unwrap_or_else(|| model.capabilities())
+    }
+    
+    /// Check every chat request against the model's context window with
+    /// this tokenizer before sending it
+    pub fn with_tokenizer(mut self, tokenizer: Tokenizer) -> Self {
+        self.tokenizer = Some(tokenizer);
+        self
+    }
+    
+    /// The configured tokenizer, or the approximate estimator
+    pub fn tokenizer(&self) -> Tokenizer {
+        self.tokenizer.clone().unwrap_or_default()
+    }
+    
+    /// Override the prices of a model
+    pub fn with_model_pricing(mut self, model: Model, pricing: ModelPricing) -> Self {
+        self.model_pricing.insert(model, pricing);
+        self
+    }
+    
+    /// Prices of a model, preferring configured overrides
+    pub fn pricing(&self, model: &Model) -> Option<ModelPricing> {
+        self.model_pricing
+            .get(model)
+            .cloned()
+            .or_else(|| model.pricing())
+    }
+    
+    /// Refuse requests once a spend cap of the budget is reached
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+    
+    /// Queue completion requests to stay within the limiter's rate limits
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+    
+    /// Fail requests fast while the breaker's circuit is open
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(breaker);
+        self
+    }
+    
+    /// Serve repeated chat completions from a cache
+    /// 
+    /// See [`crate::cache`] for which requests are cached.
+    pub fn with_response_cache(mut self, cache: impl ResponseCache + 'static) -> Self {
+        self.response_cache = Some(Arc::new(cache));
+        self
+    }
+    
     /// Validate the configuration
     pub fn validate(&self) -> Result<()> {
         // Check API key
@@ -187,6 +372,15 @@ impl DeepSeekConfig {
             ));
         }
         
+        // Validate beta URL format
+        if let Some(beta_url) = &self.beta_base_url {
+            if !beta_url.starts_with("http://") && !beta_url.starts_with("https://") {
+                return Err(DeepSeekError::ConfigError(
+                    "Beta base URL must start with http:// or https://".to_string()
+                ));
+            }
+        }
+        
         // Check timeout
         if self.timeout.as_secs() == 0 {
             return Err(DeepSeekError::ConfigError(
@@ -194,6 +388,28 @@ impl DeepSeekConfig {
             ));
         }
         
+        // Check low balance threshold
+        if let Some(threshold) = self.low_balance_threshold {
+            if !threshold.is_finite() || threshold < 0.0 {
+                return Err(DeepSeekError::ConfigError(
+                    format!("Low balance threshold must be a non-negative number, got {}", threshold)
+                ));
+            }
+        }
+        
+        // Check retry policy
+        self.retry_policy.validate()?;
+        
+        // Check adaptive concurrency settings
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.validate()?;
+        }
+        
+        // Check circuit breaker settings
+        if let Some(breaker) = &self.circuit_breaker {
+            breaker.validate()?;
+        }
+        
         Ok(())
     }
 }
@@ -234,6 +450,38 @@ mod tests {
         assert!(!config.validate_certs);
     }
     
+    #[test]
+    fn test_config_beta_url() {
+        let config = DeepSeekConfig::new("test-key");
+        assert_eq!(config.beta_url(), "https://api.deepseek.com/beta");
+        
+        let config = config.with_base_url("http://localhost:8080/");
+        assert_eq!(config.beta_url(), "http://localhost:8080/beta");
+        
+        let config = config.with_beta_base_url("https://beta.example.com/v1/");
+        assert_eq!(config.beta_url(), "https://beta.example.com/v1");
+        
+        assert!(DeepSeekConfig::new("test-key")
+            .with_beta_base_url("beta.example.com")
+            .validate()
+            .is_err());
+    }
+    
+    #[test]
+    fn test_config_capabilities() {
+        let custom = Model::from_id("deepseek-v4");
+        let mut limited = ModelCapabilities::unknown();
+        limited.supports_logprobs = false;
+        
+        let config = DeepSeekConfig::new("test-key")
+            .with_validation_mode(ValidationMode::Lenient)
+            .with_model_capabilities(custom.clone(), limited.clone());
+        
+        assert_eq!(config.validation_mode, ValidationMode::Lenient);
+        assert_eq!(config.capabilities(&custom), limited);
+        assert_eq!(config.capabilities(&Model::Reasoner), Model::Reasoner.capabilities());
+    }
+    
     #[test]
     fn test_config_validation() {
         // Valid config
@@ -253,5 +501,10 @@ mod tests {
         let zero_timeout_config = DeepSeekConfig::new("test-key")
             .with_timeout(Duration::from_secs(0));
         assert!(zero_timeout_config.validate().is_err());
+        
+        // Negative balance threshold
+        let negative_threshold_config = DeepSeekConfig::new("test-key")
+            .with_low_balance_threshold(-1.0);
+        assert!(negative_threshold_config.validate().is_err());
     }
 }
\ No newline at end of file
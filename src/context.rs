@@ -0,0 +1,399 @@
+//! Context window management for long conversations
+//!
+//! A [`ContextStrategy`] rewrites the messages of a request right before it
+//! is sent so the history stays within the model's context window. The
+//! stored history is never changed. Strategies are set with
+//! [`Conversation::with_context_strategy`] or
+//! [`ChatBuilder::with_context_strategy`].
+//!
+//! System messages are always kept, as is the newest message. Tool results
+//! whose assistant tool call was trimmed away are dropped with it.
+//!
+//! # Example
+//! ```no_run
+//! use deepseek_rust::context::TokenBudget;
+//! use deepseek_rust::{DeepSeekClient, Result};
+//!
+//! # async fn run(client: DeepSeekClient) -> Result<()> {
+//! let mut conversation = client
+//!     .conversation()
+//!     .with_context_strategy(TokenBudget::new(32_000));
+//!
+//! conversation.ask("Hello!").await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Conversation::with_context_strategy`]: crate::Conversation::with_context_strategy
+//! [`ChatBuilder::with_context_strategy`]: crate::ChatBuilder::with_context_strategy
+
+use crate::client::DeepSeekClient;
+use crate::error::{DeepSeekError, Result};
+use crate::models::request::{ChatCompletionRequest, Message, Model, Role};
+use crate::tokenizer::Tokenizer;
+use std::fmt;
+use std::future::{self, Future};
+use std::pin::Pin;
+
+/// Future returned by [`ContextStrategy::apply`]
+pub type ContextFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<Message>>> + Send + 'a>>;
+
+/// Content that replaces tool results cleared by [`DropOldToolResults`]
+pub const CLEARED_TOOL_RESULT: &str = "[tool result removed to save context]";
+
+/// Instruction sent with the history [`SummarizeOlderTurns`] compresses
+const SUMMARY_PROMPT: &str = "Summarize the following conversation for your own future reference. \
+    Keep every fact, decision, name and open question that later turns may rely on. \
+    Reply with the summary only.";
+
+/// Rewrites the messages of a request before it is sent
+pub trait ContextStrategy: fmt::Debug + Send + Sync {
+    /// Return the messages to send in place of `messages`
+    ///
+    /// `client` can be used to call the model, e.g. to summarize.
+    fn apply<'a>(&'a self, client: &'a DeepSeekClient, messages: Vec<Message>) -> ContextFuture<'a>;
+}
+
+/// Apply an optional strategy to the messages of a request
+pub(crate) async fn apply_strategy(
+    strategy: Option<&dyn ContextStrategy>,
+    client: &DeepSeekClient,
+    request: &mut ChatCompletionRequest,
+) -> Result<()> {
+    if let Some(strategy) = strategy {
+        let messages = std::mem::take(&mut request.messages);
+        request.messages = strategy.apply(client, messages).await?;
+    }
+    Ok(())
+}
+
+/// Keep the system messages and the last `max_messages` other messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeepLastMessages {
+    max_messages: usize,
+}
+
+impl KeepLastMessages {
+    /// Create the strategy; at least one message is always kept
+    pub fn new(max_messages: usize) -> Self {
+        Self {
+            max_messages: max_messages.max(1),
+        }
+    }
+}
+
+impl ContextStrategy for KeepLastMessages {
+    fn apply<'a>(&'a self, _client: &'a DeepSeekClient, messages: Vec<Message>) -> ContextFuture<'a> {
+        let (system, mut rest) = split_system(messages);
+        let cut = rest.len().saturating_sub(self.max_messages);
+        rest.drain(..cut);
+        Box::pin(future::ready(Ok(join(system, rest))))
+    }
+}
+
+/// Keep the system messages and as many of the newest messages as fit in
+/// a token budget
+///
+/// Tokens are estimated unless a tokenizer is set.
+#[derive(Debug, Clone)]
+pub struct TokenBudget {
+    max_tokens: usize,
+    tokenizer: Tokenizer,
+}
+
+impl TokenBudget {
+    /// Create the strategy for a budget of `max_tokens` prompt tokens
+    pub fn new(max_tokens: usize) -> Self {
+        Self {
+            max_tokens,
+            tokenizer: Tokenizer::approximate(),
+        }
+    }
+
+    /// Count tokens with a tokenizer instead of estimating them
+    pub fn with_tokenizer(mut self, tokenizer: Tokenizer) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+}
+
+impl ContextStrategy for TokenBudget {
+    fn apply<'a>(&'a self, _client: &'a DeepSeekClient, messages: Vec<Message>) -> ContextFuture<'a> {
+        let (system, mut rest) = split_system(messages);
+        let mut used: usize = system.iter().map(|m| self.tokenizer.count_message(m)).sum();
+
+        let mut keep = 0;
+        for message in rest.iter().rev() {
+            used += self.tokenizer.count_message(message);
+            if used > self.max_tokens && keep > 0 {
+                break;
+            }
+            keep += 1;
+        }
+        rest.drain(..rest.len() - keep);
+        Box::pin(future::ready(Ok(join(system, rest))))
+    }
+}
+
+/// Clear the content of the oldest tool results until the history fits in
+/// a token budget
+///
+/// Tool results are often the largest messages. Their tool calls stay in
+/// place, so the history remains valid; the results of the latest round of
+/// tool calls are never cleared. Tokens are estimated unless a tokenizer
+/// is set.
+#[derive(Debug, Clone)]
+pub struct DropOldToolResults {
+    max_tokens: usize,
+    placeholder: String,
+    tokenizer: Tokenizer,
+}
+
+impl DropOldToolResults {
+    /// Create the strategy for a budget of `max_tokens` prompt tokens
+    pub fn new(max_tokens: usize) -> Self {
+        Self {
+            max_tokens,
+            placeholder: CLEARED_TOOL_RESULT.to_string(),
+            tokenizer: Tokenizer::approximate(),
+        }
+    }
+
+    /// Count tokens with a tokenizer instead of estimating them
+    pub fn with_tokenizer(mut self, tokenizer: Tokenizer) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// Set the content that replaces cleared tool results
+    pub fn with_placeholder(mut self, placeholder: impl Into<String>) -> Self {
+        self.placeholder = placeholder.into();
+        self
+    }
+}
+
+impl ContextStrategy for DropOldToolResults {
+    fn apply<'a>(&'a self, _client: &'a DeepSeekClient, mut messages: Vec<Message>) -> ContextFuture<'a> {
+        let mut used: usize = messages.iter().map(|m| self.tokenizer.count_message(m)).sum();
+        let latest_round = messages.len() - messages.iter().rev().take_while(|m| m.role == Role::Tool).count();
+
+        for message in &mut messages[..latest_round] {
+            if used <= self.max_tokens {
+                break;
+            }
+            if message.role == Role::Tool && message.content != self.placeholder {
+                let before = self.tokenizer.count_message(message);
+                message.content = self.placeholder.clone();
+                used = used - before + self.tokenizer.count_message(message);
+            }
+        }
+        Box::pin(future::ready(Ok(messages)))
+    }
+}
+
+/// Replace all but the last few turns with a summary written by the model
+///
+/// The summary is sent as a system message after the original system
+/// messages. Summarizing costs an extra request each time it runs, so set
+/// a trigger with [`SummarizeOlderTurns::with_trigger_tokens`] to only
+/// summarize long histories. Its usage is not included in
+/// [`Conversation::usage`].
+///
+/// [`Conversation::usage`]: crate::Conversation::usage
+#[derive(Debug, Clone)]
+pub struct SummarizeOlderTurns {
+    keep_turns: usize,
+    model: Model,
+    max_summary_tokens: Option<u32>,
+    trigger_tokens: Option<usize>,
+    tokenizer: Tokenizer,
+}
+
+impl SummarizeOlderTurns {
+    /// Create the strategy keeping the last `keep_turns` turns verbatim
+    ///
+    /// A turn starts at a user message. At least one turn is always kept.
+    pub fn new(keep_turns: usize) -> Self {
+        Self {
+            keep_turns: keep_turns.max(1),
+            model: Model::Chat,
+            max_summary_tokens: None,
+            trigger_tokens: None,
+            tokenizer: Tokenizer::approximate(),
+        }
+    }
+
+    /// Set the model that writes the summary
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Limit the length of the summary
+    pub fn with_max_summary_tokens(mut self, tokens: u32) -> Self {
+        self.max_summary_tokens = Some(tokens);
+        self
+    }
+
+    /// Only summarize when the history exceeds a token count
+    pub fn with_trigger_tokens(mut self, tokens: usize) -> Self {
+        self.trigger_tokens = Some(tokens);
+        self
+    }
+
+    /// Count tokens for the trigger with a tokenizer instead of estimating
+    pub fn with_tokenizer(mut self, tokenizer: Tokenizer) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// Ask the model to summarize `messages`
+    async fn summarize(&self, client: &DeepSeekClient, messages: &[Message]) -> Result<String> {
+        let transcript = messages
+            .iter()
+            .filter(|m| !m.content.is_empty())
+            .map(|m| format!("{}: {}", m.role, m.content))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let mut request = ChatCompletionRequest::new(vec![
+            Message::system(SUMMARY_PROMPT),
+            Message::user(transcript),
+        ])
+        .with_model(self.model.clone());
+        request.max_tokens = self.max_summary_tokens;
+
+        let response = client.chat_completion(request).await?;
+        response
+            .get_content()
+            .map(str::to_string)
+            .ok_or(DeepSeekError::EmptyResponse)
+    }
+}
+
+impl ContextStrategy for SummarizeOlderTurns {
+    fn apply<'a>(&'a self, client: &'a DeepSeekClient, messages: Vec<Message>) -> ContextFuture<'a> {
+        Box::pin(async move {
+            let total: usize = messages.iter().map(|m| self.tokenizer.count_message(m)).sum();
+            if self.trigger_tokens.is_some_and(|trigger| total <= trigger) {
+                return Ok(messages);
+            }
+
+            let (system, mut rest) = split_system(messages);
+            let user_turns: Vec<usize> = rest
+                .iter()
+                .enumerate()
+                .filter(|(_, m)| m.role == Role::User)
+                .map(|(i, _)| i)
+                .collect();
+            let cut = match user_turns.len().checked_sub(self.keep_turns) {
+                Some(0) | None => return Ok(join(system, rest)),
+                Some(older) => user_turns.get(older).copied().unwrap_or(rest.len()),
+            };
+
+            let older: Vec<Message> = rest.drain(..cut).collect();
+            let summary = self.summarize(client, &older).await?;
+
+            #[cfg(feature = "logging")]
+            tracing::debug!(messages = older.len(), "summarized older conversation turns");
+
+            let mut system = system;
+            system.push(Message::system(format!("Summary of the earlier conversation:\n{}", summary)));
+            Ok(join(system, rest))
+        })
+    }
+}
+
+/// Separate the system messages from the rest of the history
+fn split_system(messages: Vec<Message>) -> (Vec<Message>, Vec<Message>) {
+    messages.into_iter().partition(|m| m.role == Role::System)
+}
+
+/// Put the system messages back in front, dropping tool results that lost
+/// their tool call
+fn join(mut system: Vec<Message>, rest: Vec<Message>) -> Vec<Message> {
+    system.extend(rest.into_iter().skip_while(|m| m.role == Role::Tool));
+    system
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DeepSeekConfig;
+    use crate::models::response::{FunctionCall, ToolCall};
+
+    fn client() -> DeepSeekClient {
+        DeepSeekClient::new(DeepSeekConfig::new("test-key")).unwrap()
+    }
+
+    fn history() -> Vec<Message> {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCall {
+                name: "search".to_string(),
+                arguments: "{}".to_string(),
+            },
+        };
+        vec![
+            Message::system("Be brief"),
+            Message::user("Search for Rust"),
+            Message::assistant_with_tool_calls("", vec![call]),
+            Message::tool("call_1", "x".repeat(400)),
+            Message::assistant("Found it"),
+            Message::user("Thanks"),
+            Message::assistant("You're welcome"),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_keep_last_messages() {
+        let client = client();
+        let messages = KeepLastMessages::new(4).apply(&client, history()).await.unwrap();
+        // The orphaned tool result is dropped along with its tool call
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0], Message::system("Be brief"));
+        assert_eq!(messages[1], Message::assistant("Found it"));
+
+        let messages = KeepLastMessages::new(0).apply(&client, history()).await.unwrap();
+        assert_eq!(messages.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_token_budget() {
+        let client = client();
+        let messages = TokenBudget::new(40).apply(&client, history()).await.unwrap();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages.last(), Some(&Message::assistant("You're welcome")));
+
+        let messages = TokenBudget::new(10_000).apply(&client, history()).await.unwrap();
+        assert_eq!(messages, history());
+
+        let messages = TokenBudget::new(0).apply(&client, history()).await.unwrap();
+        assert_eq!(messages.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_drop_old_tool_results() {
+        let client = client();
+        let messages = DropOldToolResults::new(50).apply(&client, history()).await.unwrap();
+        assert_eq!(messages.len(), history().len());
+        assert_eq!(messages[3].content, CLEARED_TOOL_RESULT);
+        assert_eq!(messages[3].tool_call_id.as_deref(), Some("call_1"));
+
+        let mut latest = history();
+        latest.truncate(4);
+        let messages = DropOldToolResults::new(0).apply(&client, latest.clone()).await.unwrap();
+        assert_eq!(messages, latest);
+    }
+
+    #[tokio::test]
+    async fn test_summarize_skips_short_history() {
+        let client = client();
+        let strategy = SummarizeOlderTurns::new(2);
+        assert_eq!(strategy.apply(&client, history()).await.unwrap(), history());
+
+        let strategy = SummarizeOlderTurns::new(0).with_trigger_tokens(10_000);
+        assert_eq!(strategy.apply(&client, history()).await.unwrap(), history());
+    }
+}
@@ -0,0 +1,497 @@
+//! Multi-turn conversations
+//!
+//! A [`Conversation`] keeps the system prompt and message history between
+//! turns, appends every assistant reply without its reasoning, and sums the
+//! token usage of all requests it sends.
+//!
+//! # Example
+//! ```no_run
+//! use deepseek_rust::{DeepSeekClient, Model, Result};
+//!
+//! # async fn run(client: DeepSeekClient) -> Result<()> {
+//! let mut conversation = client
+//!     .conversation()
+//!     .with_model(Model::Reasoner)
+//!     .with_system_prompt("You are a concise math tutor.");
+//!
+//! let answer = conversation.ask("Which is larger, 9.11 or 9.8?").await?;
+//! println!("{}", answer);
+//!
+//! let follow_up = conversation.ask("Explain why in one sentence.").await?;
+//! println!("{}", follow_up);
+//!
+//! // Try a different question from the first turn on
+//! let mut branch = conversation.fork(1)?;
+//! branch.ask("And 9.11 or 9.2?").await?;
+//!
+//! println!("Tokens used: {}", conversation.usage().total_tokens);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::cache::CacheMode;
+use crate::client::{DeepSeekClient, RequestOptions};
+use crate::context::{self, ContextStrategy};
+use crate::error::{DeepSeekError, Result};
+use crate::limiter::Priority;
+use crate::models::request::{ChatCompletionRequest, Message, Model, Role, Temperature};
+use crate::models::response::{ChatCompletionResponse, ReasoningPolicy, Usage};
+use crate::persistence::{self, ConversationSnapshot, SCHEMA_VERSION};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A chat session with a system prompt and message history
+///
+/// Created with [`DeepSeekClient::conversation`]. A turn is a user message
+/// together with the replies that follow it. Failed requests leave the
+/// history unchanged.
+#[derive(Debug, Clone)]
+pub struct Conversation {
+    client: DeepSeekClient,
+    id: String,
+    model: Model,
+    system_prompt: Option<String>,
+    messages: Vec<Message>,
+    temperature: Option<Temperature>,
+    max_tokens: Option<u32>,
+    reasoning_policy: ReasoningPolicy,
+    context_strategy: Option<Arc<dyn ContextStrategy>>,
+    options: RequestOptions,
+    usage: Usage,
+    metadata: BTreeMap<String, serde_json::Value>,
+    created_at: u64,
+    updated_at: u64,
+    last_response: Option<ChatCompletionResponse>,
+}
+
+impl Conversation {
+    /// Create an empty conversation
+    pub fn new(client: DeepSeekClient) -> Self {
+        let now = persistence::unix_now();
+        Self {
+            client,
+            id: persistence::new_id(),
+            model: Model::default(),
+            system_prompt: None,
+            messages: Vec::new(),
+            temperature: None,
+            max_tokens: None,
+            reasoning_policy: ReasoningPolicy::default(),
+            context_strategy: None,
+            options: RequestOptions::default(),
+            usage: Usage::default(),
+            metadata: BTreeMap::new(),
+            created_at: now,
+            updated_at: now,
+            last_response: None,
+        }
+    }
+
+    /// Restore a conversation saved with [`Conversation::snapshot`]
+    pub fn restore(client: DeepSeekClient, snapshot: ConversationSnapshot) -> Self {
+        Self {
+            id: snapshot.id,
+            model: snapshot.model,
+            system_prompt: snapshot.system_prompt,
+            messages: snapshot.messages,
+            temperature: snapshot.temperature,
+            max_tokens: snapshot.max_tokens,
+            reasoning_policy: snapshot.reasoning_policy,
+            usage: snapshot.usage,
+            metadata: snapshot.metadata,
+            created_at: snapshot.created_at,
+            updated_at: snapshot.updated_at,
+            ..Self::new(client)
+        }
+    }
+
+    /// Capture the conversation's state for saving
+    ///
+    /// The context strategy and timeout are not included.
+    pub fn snapshot(&self) -> ConversationSnapshot {
+        ConversationSnapshot {
+            version: SCHEMA_VERSION,
+            id: self.id.clone(),
+            model: self.model.clone(),
+            system_prompt: self.system_prompt.clone(),
+            messages: self.messages.clone(),
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            reasoning_policy: self.reasoning_policy,
+            usage: self.usage.clone(),
+            metadata: self.metadata.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+
+    /// Set the id used when saving
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    /// Attach application-defined metadata
+    pub fn with_metadata(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.set_metadata(key, value);
+        self
+    }
+
+    /// Set the model
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Set the system prompt sent before the history
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Set the temperature
+    ///
+    /// # Errors
+    /// Returns `InvalidParameter` if the temperature is outside 0.0 to 2.0
+    pub fn with_temperature(mut self, temperature: f32) -> Result<Self> {
+        self.temperature = Some(Temperature::new(temperature)?);
+        Ok(self)
+    }
+
+    /// Set the maximum number of tokens per reply
+    pub fn with_max_tokens(mut self, tokens: u32) -> Self {
+        self.max_tokens = Some(tokens);
+        self
+    }
+
+    /// Set whether replies keep their reasoning in the local history
+    ///
+    /// Kept reasoning is never sent back to the API.
+    pub fn with_reasoning_policy(mut self, policy: ReasoningPolicy) -> Self {
+        self.reasoning_policy = policy;
+        self
+    }
+
+    /// Set how the history is trimmed before each request
+    ///
+    /// The stored history itself is kept in full.
+    pub fn with_context_strategy(mut self, strategy: impl ContextStrategy + 'static) -> Self {
+        self.context_strategy = Some(Arc::new(strategy));
+        self
+    }
+
+    /// Set the timeout for each request
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the position of each request in the rate limiter's queue
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.options.priority = priority;
+        self
+    }
+
+    /// Set how each request uses the response cache
+    pub fn with_cache_mode(mut self, mode: CacheMode) -> Self {
+        self.options.cache = mode;
+        self
+    }
+
+    /// The conversation id
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Application-defined metadata
+    pub fn metadata(&self) -> &BTreeMap<String, serde_json::Value> {
+        &self.metadata
+    }
+
+    /// Set a metadata entry, replacing any earlier value
+    pub fn set_metadata(&mut self, key: impl Into<String>, value: serde_json::Value) {
+        self.metadata.insert(key.into(), value);
+        self.touch();
+    }
+
+    /// Unix timestamp of when the conversation was created
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    /// Unix timestamp of the last change to the conversation
+    pub fn updated_at(&self) -> u64 {
+        self.updated_at
+    }
+
+    /// The model used for replies
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// The system prompt, if set
+    pub fn system_prompt(&self) -> Option<&str> {
+        self.system_prompt.as_deref()
+    }
+
+    /// Replace the system prompt for the following turns
+    pub fn set_system_prompt(&mut self, prompt: Option<String>) {
+        self.system_prompt = prompt;
+        self.touch();
+    }
+
+    /// The history, without the system prompt
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    /// Number of turns in the history
+    pub fn turns(&self) -> usize {
+        self.messages.iter().filter(|m| m.role == Role::User).count()
+    }
+
+    /// Token usage summed over every request sent, including regenerations
+    pub fn usage(&self) -> &Usage {
+        &self.usage
+    }
+
+    /// The full response to the latest request, including its reasoning
+    pub fn last_response(&self) -> Option<&ChatCompletionResponse> {
+        self.last_response.as_ref()
+    }
+
+    /// Build the request for the current history
+    ///
+    /// The context strategy is not applied.
+    pub fn to_request(&self) -> ChatCompletionRequest {
+        let system = self.system_prompt.iter().map(Message::system);
+        let mut request = ChatCompletionRequest::new(system.chain(self.messages.iter().cloned()).collect())
+            .with_model(self.model.clone());
+        request.temperature = self.temperature;
+        request.max_tokens = self.max_tokens;
+        request
+    }
+
+    /// Send a user message and return the assistant's reply
+    ///
+    /// The message and the reply are appended to the history.
+    pub async fn ask(&mut self, text: impl Into<String>) -> Result<String> {
+        let start = self.messages.len();
+        self.messages.push(Message::user(text));
+        self.complete(start, Vec::new()).await
+    }
+
+    /// Replace the reply to the last user message with a new one
+    ///
+    /// # Errors
+    /// Returns `InvalidParameter` if there is no user message yet
+    pub async fn regenerate(&mut self) -> Result<String> {
+        let start = self.last_user_index()? + 1;
+        let removed = self.messages.split_off(start);
+        self.complete(start, removed).await
+    }
+
+    /// Change the last user message and get a new reply to it
+    ///
+    /// # Errors
+    /// Returns `InvalidParameter` if there is no user message yet
+    pub async fn edit_last_user_message(&mut self, text: impl Into<String>) -> Result<String> {
+        let start = self.last_user_index()?;
+        let removed = self.messages.split_off(start);
+        self.messages.push(Message::user(text));
+        self.complete(start, removed).await
+    }
+
+    /// Remove the last turn and return its messages
+    ///
+    /// Returns an empty vector if there is nothing to undo.
+    pub fn undo(&mut self) -> Vec<Message> {
+        match self.last_user_index() {
+            Ok(start) => {
+                self.last_response = None;
+                self.touch();
+                self.messages.split_off(start)
+            }
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Copy the conversation with only its first `turns` turns
+    ///
+    /// The fork shares the settings and metadata but gets a new id and
+    /// starts with zero usage.
+    ///
+    /// # Errors
+    /// Returns `InvalidParameter` if the conversation has fewer turns
+    pub fn fork(&self, turns: usize) -> Result<Conversation> {
+        let end = match self.turn_index(turns) {
+            Some(index) => index,
+            None if turns == self.turns() => self.messages.len(),
+            None => {
+                return Err(DeepSeekError::InvalidParameter(format!(
+                    "Cannot fork at turn {}, the conversation has {} turns",
+                    turns,
+                    self.turns()
+                )))
+            }
+        };
+
+        let now = persistence::unix_now();
+        Ok(Conversation {
+            id: persistence::new_id(),
+            messages: self.messages[..end].to_vec(),
+            usage: Usage::default(),
+            created_at: now,
+            updated_at: now,
+            last_response: None,
+            ..self.clone()
+        })
+    }
+
+    /// Remove the whole history, keeping the system prompt and usage
+    pub fn clear(&mut self) {
+        self.messages.clear();
+        self.last_response = None;
+        self.touch();
+    }
+
+    /// Request a reply to the history and append it
+    ///
+    /// On failure the history is truncated to `start` and `removed` is put
+    /// back, undoing whatever the caller changed.
+    async fn complete(&mut self, start: usize, removed: Vec<Message>) -> Result<String> {
+        let result = self.send().await.and_then(|response| {
+            let reply = response.clone().into_message_with(self.reasoning_policy)?;
+            Ok((response, reply))
+        });
+
+        match result {
+            Ok((response, reply)) => {
+                if let Some(usage) = &response.usage {
+                    self.usage += usage;
+                }
+                let content = reply.content.clone();
+                self.messages.push(reply);
+                self.last_response = Some(response);
+                self.touch();
+                Ok(content)
+            }
+            Err(err) => {
+                self.messages.truncate(start);
+                self.messages.extend(removed);
+                Err(err)
+            }
+        }
+    }
+
+    /// Send the history, trimmed by the context strategy
+    async fn send(&self) -> Result<ChatCompletionResponse> {
+        let mut request = self.to_request();
+        context::apply_strategy(self.context_strategy.as_deref(), &self.client, &mut request).await?;
+        self.client
+            .chat_completion_with_options(request, &self.options)
+            .await
+    }
+
+    /// Record a change to the conversation
+    fn touch(&mut self) {
+        self.updated_at = persistence::unix_now();
+    }
+
+    /// Index of the user message that starts turn `turn` (zero-based)
+    fn turn_index(&self, turn: usize) -> Option<usize> {
+        self.messages
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.role == Role::User)
+            .nth(turn)
+            .map(|(i, _)| i)
+    }
+
+    /// Index of the last user message
+    fn last_user_index(&self) -> Result<usize> {
+        self.messages
+            .iter()
+            .rposition(|m| m.role == Role::User)
+            .ok_or_else(|| {
+                DeepSeekError::InvalidParameter("The conversation has no user message".to_string())
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DeepSeekConfig;
+
+    fn conversation() -> Conversation {
+        let client = DeepSeekClient::new(DeepSeekConfig::new("test-key")).unwrap();
+        let mut conversation = client.conversation().with_system_prompt("Be brief");
+        conversation.messages = vec![
+            Message::user("One"),
+            Message::assistant("1"),
+            Message::user("Two"),
+            Message::assistant("2"),
+        ];
+        conversation
+    }
+
+    #[test]
+    fn test_to_request() {
+        let conversation = conversation().with_model(Model::Reasoner).with_max_tokens(10);
+        let request = conversation.to_request();
+        assert_eq!(request.messages.len(), 5);
+        assert_eq!(request.messages[0], Message::system("Be brief"));
+        assert_eq!(request.model, Model::Reasoner);
+        assert_eq!(request.max_tokens, Some(10));
+        assert!(request.validate().is_ok());
+    }
+
+    #[test]
+    fn test_undo() {
+        let mut conversation = conversation();
+        assert_eq!(conversation.turns(), 2);
+        assert_eq!(conversation.undo(), vec![Message::user("Two"), Message::assistant("2")]);
+        assert_eq!(conversation.turns(), 1);
+        conversation.undo();
+        assert!(conversation.undo().is_empty());
+        assert!(conversation.messages().is_empty());
+        assert_eq!(conversation.system_prompt(), Some("Be brief"));
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let conversation = conversation()
+            .with_id("session-1")
+            .with_model(Model::Reasoner)
+            .with_metadata("topic", serde_json::json!("numbers"));
+        let snapshot = conversation.snapshot();
+        assert_eq!(snapshot.id, "session-1");
+
+        let json = snapshot.to_json().unwrap();
+        let restored = Conversation::restore(
+            conversation.client.clone(),
+            ConversationSnapshot::from_json(&json).unwrap(),
+        );
+        assert_eq!(restored.id(), "session-1");
+        assert_eq!(restored.model(), &Model::Reasoner);
+        assert_eq!(restored.messages(), conversation.messages());
+        assert_eq!(restored.metadata()["topic"], "numbers");
+        assert_eq!(restored.created_at(), conversation.created_at());
+        assert_eq!(restored.snapshot(), snapshot);
+    }
+
+    #[test]
+    fn test_fork() {
+        let conversation = conversation();
+        let branch = conversation.fork(1).unwrap();
+        assert_eq!(branch.messages(), &conversation.messages()[..2]);
+        assert_eq!(branch.system_prompt(), Some("Be brief"));
+        assert_ne!(branch.id(), conversation.id());
+
+        assert!(conversation.fork(0).unwrap().messages().is_empty());
+        assert_eq!(conversation.fork(2).unwrap().messages().len(), 4);
+        assert!(conversation.fork(3).is_err());
+    }
+}
@@ -54,28 +54,74 @@ pub enum DeepSeekError {
     #[error("Received empty response from API")]
     EmptyResponse,
     
+    /// Streaming response was malformed or reported an error mid-stream
+    #[error("Stream error: {0}")]
+    StreamError(String),
+    
+    /// Tool execution failed
+    #[error("Tool error: {0}")]
+    ToolError(String),
+    
     /// Unsupported feature
     #[error("Feature not yet supported: {0}")]
     UnsupportedFeature(String),
+    
+    /// A spend cap of the configured budget was reached
+    #[error("Budget exceeded: {0}")]
+    BudgetExceeded(String),
+    
+    /// A request still failed after being retried
+    /// 
+    /// The last error is the `source`, so it is not repeated in the message.
+    #[error("Request failed; gave up after {attempts} attempts")]
+    RetriesExhausted {
+        /// Number of attempts made, including the first
+        attempts: u32,
+        /// Error of the last attempt
+        source: Box<DeepSeekError>,
+    },
+    
+    /// The circuit breaker is open; the API is not called until it probes
+    /// again after the given time
+    #[error("Circuit breaker is open; requests are rejected for another {0:?}")]
+    CircuitOpen(std::time::Duration),
+    
+    /// A request limited to the response cache had no cached response
+    #[error("No cached response for this request")]
+    CacheMiss,
 }
 
 /// Type alias for Results with DeepSeekError
 pub type Result<T> = std::result::Result<T, DeepSeekError>;
 
+/// Statuses worth retrying: timeouts, rate limits and server failures
+fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 429 | 500 | 502 | 503 | 504)
+}
+
 impl DeepSeekError {
     /// Check if the error is retryable
+    /// 
+    /// Connection failures, timeouts and 408, 429, 500, 502, 503 and 504
+    /// responses are transient; other errors are not.
     pub fn is_retryable(&self) -> bool {
-        matches!(
-            self,
-            DeepSeekError::HttpError(_) 
-            | DeepSeekError::RateLimitExceeded
-            | DeepSeekError::TimeoutError(_)
-        )
+        match self.last_error() {
+            DeepSeekError::HttpError(err) => {
+                err.is_connect()
+                    || err.is_timeout()
+                    || err.is_request()
+                    || err.is_body()
+                    || err.status().is_some_and(|status| is_retryable_status(status.as_u16()))
+            }
+            DeepSeekError::ApiError { status, .. } => is_retryable_status(*status),
+            DeepSeekError::RateLimitExceeded | DeepSeekError::TimeoutError(_) => true,
+            _ => false,
+        }
     }
     
     /// Get the HTTP status code if available
     pub fn status_code(&self) -> Option<u16> {
-        match self {
+        match self.last_error() {
             DeepSeekError::ApiError { status, .. } => Some(*status),
             _ => None,
         }
@@ -83,14 +129,37 @@ impl DeepSeekError {
     
     /// Check if this is an authentication error
     pub fn is_auth_error(&self) -> bool {
-        matches!(self, DeepSeekError::AuthenticationError(_))
-            || matches!(self, DeepSeekError::ApiError { status: 401 | 403, .. })
+        let err = self.last_error();
+        matches!(err, DeepSeekError::AuthenticationError(_))
+            || matches!(err, DeepSeekError::ApiError { status: 401 | 403, .. })
     }
     
     /// Check if this is a rate limit error
     pub fn is_rate_limit(&self) -> bool {
-        matches!(self, DeepSeekError::RateLimitExceeded)
-            || matches!(self, DeepSeekError::ApiError { status: 429, .. })
+        let err = self.last_error();
+        matches!(err, DeepSeekError::RateLimitExceeded)
+            || matches!(err, DeepSeekError::ApiError { status: 429, .. })
+    }
+    
+    /// Check if the request was rejected by an open circuit breaker
+    pub fn is_circuit_open(&self) -> bool {
+        matches!(self.last_error(), DeepSeekError::CircuitOpen(_))
+    }
+    
+    /// Number of attempts made before the request failed
+    pub fn attempts(&self) -> u32 {
+        match self {
+            DeepSeekError::RetriesExhausted { attempts, .. } => *attempts,
+            _ => 1,
+        }
+    }
+    
+    /// The error of the last attempt, unwrapping `RetriesExhausted`
+    pub fn last_error(&self) -> &DeepSeekError {
+        match self {
+            DeepSeekError::RetriesExhausted { source, .. } => source.last_error(),
+            other => other,
+        }
     }
 }
 
@@ -108,6 +177,37 @@ mod tests {
         
         let rate_limit_err = DeepSeekError::RateLimitExceeded;
         assert!(rate_limit_err.is_retryable());
+        
+        for status in [429, 500, 502, 503] {
+            let api_err = DeepSeekError::ApiError { status, message: String::new() };
+            assert!(api_err.is_retryable());
+        }
+        let bad_request = DeepSeekError::ApiError { status: 400, message: String::new() };
+        assert!(!bad_request.is_retryable());
+    }
+    
+    #[test]
+    fn test_retries_exhausted() {
+        let err = DeepSeekError::RetriesExhausted {
+            attempts: 3,
+            source: Box::new(DeepSeekError::ApiError {
+                status: 429,
+                message: "Too many requests".to_string(),
+            }),
+        };
+        assert_eq!(err.attempts(), 3);
+        assert_eq!(err.status_code(), Some(429));
+        assert!(err.is_rate_limit());
+        assert_eq!(err.to_string(), "Request failed; gave up after 3 attempts");
+        let source = std::error::Error::source(&err).map(ToString::to_string);
+        assert_eq!(source.as_deref(), Some(err.last_error().to_string().as_str()));
+        assert_eq!(DeepSeekError::EmptyResponse.attempts(), 1);
+        assert!(!err.is_circuit_open());
+        
+        let open = DeepSeekError::CircuitOpen(std::time::Duration::from_secs(5));
+        assert!(open.is_circuit_open());
+        let wrapped = DeepSeekError::RetriesExhausted { attempts: 2, source: Box::new(open) };
+        assert!(wrapped.is_circuit_open());
     }
     
     #[test]
@@ -33,7 +33,18 @@
 //! - **Type Safety** - Leverage Rust's type system for compile-time safety
 //! - **Multiple Models** - Support for Chat, Reasoner, and Coder models
 //! - **Builder Pattern** - Intuitive API with method chaining
-//! - **Automatic Retries** - Built-in exponential backoff for transient failures
+//! - **Automatic Retries** - Configurable backoff with jitter, deadlines and `Retry-After` support
+//! - **Circuit Breaker** - Fail fast during API outages instead of piling up timeouts
+//! - **Streaming** - Server-sent events streaming with the `streaming` feature
+//! - **Function Calling** - Typed tool definitions and an automatic tool loop
+//! - **Conversations** - Multi-turn sessions with undo, regenerate and forks
+//! - **Context Management** - Pluggable strategies to keep long histories in the context window
+//! - **Persistence** - Save and restore conversations as JSON or JSONL files
+//! - **Token Counting** - Count prompt tokens locally before sending
+//! - **Cost Control** - Pre-flight cost estimates and per-client or per-user spend caps
+//! - **Rate Limiting** - Request and token rate limits, a priority queue and adaptive concurrency
+//! - **Response Caching** - In-memory and on-disk caches for deterministic requests
+//! - **FIM Completion** - Fill-in-the-middle code completion on the beta endpoint
 //! - **Secure** - API keys handled securely with the `secrecy` crate
 
 #![doc(html_logo_url = "https://raw.githubusercontent.com/abdulwahed-sweden/deepseek-rust/main/logo.png")]
@@ -42,24 +53,69 @@
 #![warn(rustdoc::missing_crate_level_docs)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+pub mod balance;
+pub mod breaker;
+pub mod budget;
+pub mod cache;
 pub mod client;
 pub mod config;
+pub mod context;
+pub mod conversation;
 pub mod error;
+pub mod limiter;
 pub mod models;
+pub mod persistence;
+pub mod retry;
+pub mod tokenizer;
+pub mod tools;
+
+#[cfg(feature = "streaming")]
+#[cfg_attr(docsrs, doc(cfg(feature = "streaming")))]
+pub mod streaming;
 
 // Re-export main types for convenience
-pub use client::{ChatBuilder, DeepSeekClient};
+pub use balance::BalanceWatcher;
+pub use breaker::{CircuitBreaker, CircuitState};
+pub use budget::Budget;
+pub use cache::{CacheMode, DiskCache, InMemoryCache, ResponseCache};
+pub use client::{ChatBuilder, DeepSeekClient, FimBuilder, RequestOptions};
 pub use config::DeepSeekConfig;
+pub use conversation::Conversation;
+pub use persistence::{ConversationSnapshot, FileConversationStore};
+pub use retry::{Jitter, RetryPolicy};
 pub use error::{DeepSeekError, Result};
+pub use limiter::{AdaptiveConcurrency, Priority, RateLimiter};
 
 // Re-export model types
+pub use models::balance::{BalanceInfo, CurrencyBalance};
+pub use models::capabilities::{ModelCapabilities, ValidationMode};
+pub use models::fim::{FimCompletionRequest, FimCompletionResponse};
+pub use models::pricing::ModelPricing;
 pub use models::request::{
-    ChatCompletionRequest, Message, Model, Role, Temperature,
+    ChatCompletionRequest, Message, Model, ResponseFormat, Role, Temperature,
 };
 pub use models::response::{
-    ChatCompletionResponse, Choice, ResponseMessage, Usage,
+    ChatCompletionResponse, Choice, FunctionCall, LogProbs, ModelInfo, ReasoningPolicy,
+    ResponseMessage, StreamAccumulator, StreamChunk, TokenLogProb, ToolCall, Usage,
 };
 
+pub use models::tool::{DeepSeekTool, FunctionDefinition, Tool, ToolChoice, ToolParameter};
+
+/// Derive [`DeepSeekTool`] and [`ToolParameter`] for a struct
+#[cfg(feature = "derive")]
+#[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+pub use deepseek_rust_derive::DeepSeekTool;
+
+pub use tools::{ToolRegistry, ToolRun};
+
+#[cfg(feature = "streaming")]
+pub use streaming::{ChatStream, FimStream};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use serde_json;
+}
+
 /// Library version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
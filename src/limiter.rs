@@ -0,0 +1,789 @@
+//! Client-side rate limiting and request queueing
+//!
+//! A [`RateLimiter`] set with [`DeepSeekConfig::with_rate_limiter`] holds
+//! completion requests back before they are sent:
+//!
+//! - a token bucket for requests per minute;
+//! - a token bucket for tokens per minute, charged with an estimate before
+//!   sending (prompt tokens plus `max_tokens`) and corrected with the
+//!   reported [`Usage`](crate::Usage) afterwards;
+//! - a cap on the number of requests in flight, either fixed or adapted to
+//!   the API's behaviour with [`AdaptiveConcurrency`].
+//!
+//! Waiting requests form a single queue ordered by [`Priority`], then by
+//! arrival, so interactive calls jump ahead of batch work and requests of
+//! the same priority are served first come, first served. Waiting requests
+//! age: once a request has waited for the aging step (30 seconds by
+//! default, see [`RateLimiter::with_priority_aging`]) it is served ahead of
+//! newer requests one priority higher, so a steady stream of `High`
+//! requests cannot starve `Low` ones. Clones share the limits and the
+//! queue, so one limiter can be shared by every client using the same API
+//! key.
+//!
+//! # Example
+//! ```no_run
+//! use deepseek_rust::limiter::{Priority, RateLimiter};
+//! use deepseek_rust::{DeepSeekClient, DeepSeekConfig, Result};
+//!
+//! # async fn run() -> Result<()> {
+//! let limiter = RateLimiter::new()
+//!     .with_requests_per_minute(60)
+//!     .with_tokens_per_minute(200_000)
+//!     .with_max_concurrency(8);
+//! let client = DeepSeekClient::new(DeepSeekConfig::new("your-api-key").with_rate_limiter(limiter))?;
+//!
+//! client.chat().add_user_message("Hello!").with_priority(Priority::High).send().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`DeepSeekConfig::with_rate_limiter`]: crate::DeepSeekConfig::with_rate_limiter
+
+use crate::error::{DeepSeekError, Result};
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Scheduling priority of a request waiting in the queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    /// Background or batch work
+    Low,
+
+    /// The default priority
+    #[default]
+    Normal,
+
+    /// Interactive calls served before everything else
+    High,
+}
+
+impl Priority {
+    /// Number of priority levels below `High`
+    fn levels_below_high(self) -> u32 {
+        match self {
+            Priority::Low => 2,
+            Priority::Normal => 1,
+            Priority::High => 0,
+        }
+    }
+}
+
+/// Default wait after which a request is served ahead of newer requests one
+/// priority higher
+pub const DEFAULT_PRIORITY_AGING: Duration = Duration::from_secs(30);
+
+/// Weight of a new latency sample in the moving baseline
+const LATENCY_SMOOTHING: f64 = 0.1;
+
+/// Settings of an AIMD (additive increase, multiplicative decrease)
+/// concurrency limit
+///
+/// The limit grows by `increase` for every `limit` healthy responses and is
+/// multiplied by `decrease_factor` when a request is rate limited (429) or,
+/// if `latency_tolerance` is set, its latency exceeds that multiple of the
+/// moving average. Only one decrease happens per round trip: signals from
+/// requests sent before the last decrease are ignored.
+///
+/// Latency detection is off by default because latency grows with output
+/// length and differs between models; only enable it for traffic of similar
+/// requests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptiveConcurrency {
+    /// Limit to start from
+    pub initial_limit: usize,
+
+    /// Lowest limit
+    pub min_limit: usize,
+
+    /// Highest limit
+    pub max_limit: usize,
+
+    /// Growth of the limit per `limit` healthy responses
+    pub increase: f64,
+
+    /// Factor applied to the limit on a rate limit or latency spike
+    pub decrease_factor: f64,
+
+    /// Latency, as a multiple of the moving average, counted as a spike;
+    /// `None`, the default, ignores latency
+    pub latency_tolerance: Option<f64>,
+}
+
+impl Default for AdaptiveConcurrency {
+    fn default() -> Self {
+        Self {
+            initial_limit: 4,
+            min_limit: 1,
+            max_limit: 64,
+            increase: 1.0,
+            decrease_factor: 0.5,
+            latency_tolerance: None,
+        }
+    }
+}
+
+impl AdaptiveConcurrency {
+    /// Create the default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the limit to start from
+    pub fn with_initial_limit(mut self, limit: usize) -> Self {
+        self.initial_limit = limit;
+        self
+    }
+
+    /// Set the lowest and highest limits
+    pub fn with_bounds(mut self, min: usize, max: usize) -> Self {
+        self.min_limit = min;
+        self.max_limit = max;
+        self
+    }
+
+    /// Set the growth of the limit per `limit` healthy responses
+    pub fn with_increase(mut self, increase: f64) -> Self {
+        self.increase = increase;
+        self
+    }
+
+    /// Set the factor applied to the limit on overload
+    pub fn with_decrease_factor(mut self, factor: f64) -> Self {
+        self.decrease_factor = factor;
+        self
+    }
+
+    /// Set the latency multiple counted as a spike, or `None` to ignore
+    /// latency
+    pub fn with_latency_tolerance(mut self, tolerance: Option<f64>) -> Self {
+        self.latency_tolerance = tolerance;
+        self
+    }
+
+    /// Validate the settings
+    pub fn validate(&self) -> Result<()> {
+        if self.min_limit == 0 || self.min_limit > self.max_limit {
+            return Err(DeepSeekError::ConfigError(format!(
+                "Adaptive concurrency bounds must satisfy 1 <= min <= max, got {}..={}",
+                self.min_limit, self.max_limit
+            )));
+        }
+        if !(self.increase.is_finite() && self.increase > 0.0) {
+            return Err(DeepSeekError::ConfigError(
+                "Adaptive concurrency increase must be positive".to_string(),
+            ));
+        }
+        if !(self.decrease_factor > 0.0 && self.decrease_factor < 1.0) {
+            return Err(DeepSeekError::ConfigError(
+                "Adaptive concurrency decrease factor must be between 0 and 1".to_string(),
+            ));
+        }
+        if self.latency_tolerance.is_some_and(|tolerance| tolerance.is_nan() || tolerance <= 1.0) {
+            return Err(DeepSeekError::ConfigError(
+                "Adaptive concurrency latency tolerance must be greater than 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Current state of an adaptive concurrency limit
+#[derive(Debug)]
+struct Adaptive {
+    settings: AdaptiveConcurrency,
+    limit: f64,
+    /// Moving average of response latency in seconds
+    baseline: Option<f64>,
+    decreased_at: Option<Instant>,
+}
+
+impl Adaptive {
+    fn new(settings: AdaptiveConcurrency) -> Self {
+        let limit = settings
+            .initial_limit
+            .clamp(settings.min_limit.max(1), settings.max_limit.max(1)) as f64;
+        Self {
+            settings,
+            limit,
+            baseline: None,
+            decreased_at: None,
+        }
+    }
+
+    fn limit(&self) -> usize {
+        (self.limit as usize).max(1)
+    }
+
+    /// Shrink the limit unless the request was sent before the last decrease
+    fn decrease(&mut self, sent_at: Instant, now: Instant) {
+        if self.decreased_at.is_some_and(|at| sent_at < at) {
+            return;
+        }
+        self.limit = (self.limit * self.settings.decrease_factor).max(self.settings.min_limit as f64);
+        self.decreased_at = Some(now);
+    }
+
+    /// Record a response, returning whether the limit grew
+    fn record_latency(&mut self, sent_at: Instant, now: Instant) -> bool {
+        let latency = now.saturating_duration_since(sent_at).as_secs_f64();
+        let spike = self
+            .settings
+            .latency_tolerance
+            .zip(self.baseline)
+            .is_some_and(|(tolerance, baseline)| latency > baseline * tolerance);
+        self.baseline = Some(match self.baseline {
+            Some(baseline) => baseline + LATENCY_SMOOTHING * (latency - baseline),
+            None => latency,
+        });
+
+        if spike {
+            self.decrease(sent_at, now);
+            return false;
+        }
+        let before = self.limit();
+        self.limit = (self.limit + self.settings.increase / self.limit).min(self.settings.max_limit as f64);
+        self.limit() > before
+    }
+}
+
+/// Position in the queue; smaller tickets are served first
+///
+/// The first part is the arrival time, measured from [`queue_epoch`],
+/// pushed back by the aging step for each priority level below `High`. It
+/// saturates for huge aging steps, so ties fall back to priority, then
+/// arrival.
+type Ticket = (Duration, Reverse<Priority>, u64);
+
+/// Reference point for queue arrival times
+fn queue_epoch() -> Instant {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    *EPOCH.get_or_init(Instant::now)
+}
+
+/// A token bucket refilled continuously over a minute
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    available: f64,
+    refilled: Instant,
+}
+
+impl Bucket {
+    fn new(per_minute: u32) -> Self {
+        let capacity = f64::from(per_minute.max(1));
+        Self {
+            capacity,
+            available: capacity,
+            refilled: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.available = (self.available + elapsed * self.capacity / 60.0).min(self.capacity);
+        self.refilled = now;
+    }
+
+    /// Time until `amount` is available, capped at the capacity
+    fn wait_time(&self, amount: f64) -> Duration {
+        let amount = amount.min(self.capacity);
+        if self.available >= amount {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((amount - self.available) * 60.0 / self.capacity)
+        }
+    }
+
+    /// Take `amount`, capped at the capacity, returning what was charged
+    fn take(&mut self, amount: f64) -> f64 {
+        let charged = amount.min(self.capacity);
+        self.available -= charged;
+        charged
+    }
+
+    /// Give back (or charge, if negative) tokens after the fact
+    fn adjust(&mut self, amount: f64) {
+        self.available = (self.available + amount).min(self.capacity);
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    requests: Option<Bucket>,
+    tokens: Option<Bucket>,
+    max_concurrency: Option<usize>,
+    adaptive: Option<Adaptive>,
+    in_flight: usize,
+    queue: BTreeSet<Ticket>,
+    next_ticket: u64,
+    /// Aging step, if not the default
+    priority_aging: Option<Duration>,
+}
+
+impl State {
+    /// Queue a request arriving now
+    fn enqueue(&mut self, priority: Priority) -> Ticket {
+        let aging = self.priority_aging.unwrap_or(DEFAULT_PRIORITY_AGING);
+        let delay = aging.saturating_mul(priority.levels_below_high());
+        let ticket = (
+            queue_epoch().elapsed().saturating_add(delay),
+            Reverse(priority),
+            self.next_ticket,
+        );
+        self.next_ticket += 1;
+        self.queue.insert(ticket);
+        ticket
+    }
+
+    /// Admit the request holding `ticket` if it is at the head of the
+    /// queue and capacity allows, returning the tokens charged; otherwise
+    /// return how long to wait, or `None` to wait for a permit to be
+    /// released
+    fn try_admit(&mut self, ticket: Ticket, tokens: u32, now: Instant) -> std::result::Result<f64, Option<Duration>> {
+        if self.queue.first() != Some(&ticket) {
+            return Err(None);
+        }
+        if self.concurrency_limit().is_some_and(|max| self.in_flight >= max) {
+            return Err(None);
+        }
+
+        let tokens = f64::from(tokens);
+        let mut wait = Duration::ZERO;
+        if let Some(bucket) = &mut self.requests {
+            bucket.refill(now);
+            wait = wait.max(bucket.wait_time(1.0));
+        }
+        if let Some(bucket) = &mut self.tokens {
+            bucket.refill(now);
+            wait = wait.max(bucket.wait_time(tokens));
+        }
+        if !wait.is_zero() {
+            return Err(Some(wait));
+        }
+
+        if let Some(bucket) = &mut self.requests {
+            bucket.take(1.0);
+        }
+        let charged = self.tokens.as_mut().map_or(0.0, |bucket| bucket.take(tokens));
+        self.queue.remove(&ticket);
+        self.in_flight += 1;
+        Ok(charged)
+    }
+
+    /// The fixed or adaptive cap on requests in flight, whichever is lower
+    fn concurrency_limit(&self) -> Option<usize> {
+        let adaptive = self.adaptive.as_ref().map(Adaptive::limit);
+        match (self.max_concurrency, adaptive) {
+            (Some(fixed), Some(adaptive)) => Some(fixed.min(adaptive)),
+            (fixed, adaptive) => fixed.or(adaptive),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    notify: Notify,
+}
+
+impl fmt::Debug for Shared {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shared").field("state", &self.state).finish_non_exhaustive()
+    }
+}
+
+/// Request and token rate limits with a bounded number of requests in
+/// flight, shared by all clones
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    shared: Arc<Shared>,
+}
+
+impl RateLimiter {
+    /// Create a limiter without limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the number of requests started per minute (at least 1)
+    pub fn with_requests_per_minute(self, limit: u32) -> Self {
+        self.lock().requests = Some(Bucket::new(limit));
+        self
+    }
+
+    /// Limit the number of tokens used per minute (at least 1)
+    ///
+    /// A single request estimated to use more tokens than the limit waits
+    /// for a full bucket rather than forever.
+    pub fn with_tokens_per_minute(self, limit: u32) -> Self {
+        self.lock().tokens = Some(Bucket::new(limit));
+        self
+    }
+
+    /// Limit the number of requests in flight (at least 1)
+    pub fn with_max_concurrency(self, limit: usize) -> Self {
+        self.lock().max_concurrency = Some(limit.max(1));
+        self
+    }
+
+    /// Set how long a request waits before it is served ahead of newer
+    /// requests one priority higher
+    ///
+    /// Defaults to [`DEFAULT_PRIORITY_AGING`]. `Duration::ZERO` serves
+    /// requests strictly in arrival order and `Duration::MAX` strictly by
+    /// priority.
+    pub fn with_priority_aging(self, step: Duration) -> Self {
+        self.lock().priority_aging = Some(step);
+        self
+    }
+
+    /// Adapt the number of requests in flight to rate limits and latency
+    ///
+    /// A fixed limit set with [`with_max_concurrency`](Self::with_max_concurrency)
+    /// still caps the adaptive one.
+    pub fn with_adaptive_concurrency(self, settings: AdaptiveConcurrency) -> Self {
+        self.lock().adaptive = Some(Adaptive::new(settings));
+        self
+    }
+
+    /// Validate the adaptive concurrency settings, if any
+    pub fn validate(&self) -> Result<()> {
+        match &self.lock().adaptive {
+            Some(adaptive) => adaptive.settings.validate(),
+            None => Ok(()),
+        }
+    }
+
+    /// Current cap on requests in flight, if any
+    pub fn concurrency_limit(&self) -> Option<usize> {
+        self.lock().concurrency_limit()
+    }
+
+    /// Whether a tokens-per-minute limit is set
+    pub fn limits_tokens(&self) -> bool {
+        self.lock().tokens.is_some()
+    }
+
+    /// Number of requests in flight
+    pub fn in_flight(&self) -> usize {
+        self.lock().in_flight
+    }
+
+    /// Number of requests waiting in the queue
+    pub fn queue_depth(&self) -> usize {
+        self.lock().queue.len()
+    }
+
+    /// Wait for a turn to send a request estimated to use `tokens` tokens
+    ///
+    /// The returned permit holds a concurrency slot until dropped. Dropping
+    /// the future while it waits leaves the queue.
+    pub async fn acquire(&self, priority: Priority, tokens: u32) -> Permit {
+        let ticket = self.lock().enqueue(priority);
+        let mut queued = Queued {
+            limiter: self,
+            ticket: Some(ticket),
+        };
+
+        loop {
+            // Created before checking so a release in between is not missed
+            let notified = self.shared.notify.notified();
+            let admitted = self.lock().try_admit(ticket, tokens, Instant::now());
+            match admitted {
+                Ok(charged) => {
+                    queued.ticket = None;
+                    // The next request in the queue may be admitted too
+                    self.shared.notify.notify_waiters();
+                    return Permit {
+                        limiter: self.clone(),
+                        tokens: charged,
+                    };
+                }
+                Err(Some(wait)) => {
+                    tokio::select! {
+                        _ = notified => {}
+                        _ = tokio::time::sleep(wait) => {}
+                    }
+                }
+                Err(None) => notified.await,
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.shared.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Removes a cancelled request from the queue
+struct Queued<'a> {
+    limiter: &'a RateLimiter,
+    ticket: Option<Ticket>,
+}
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        if let Some(ticket) = self.ticket.take() {
+            self.limiter.lock().queue.remove(&ticket);
+            self.limiter.shared.notify.notify_waiters();
+        }
+    }
+}
+
+/// A turn to send a request, holding a concurrency slot until dropped
+#[derive(Debug)]
+pub struct Permit {
+    limiter: RateLimiter,
+    /// Tokens currently charged to the tokens-per-minute bucket
+    tokens: f64,
+}
+
+impl Permit {
+    /// Report that an attempt sent at `sent_at` was rate limited
+    ///
+    /// Shrinks the adaptive concurrency limit, if any.
+    pub fn record_rate_limit(&self, sent_at: Instant) {
+        let mut state = self.limiter.lock();
+        if let Some(adaptive) = &mut state.adaptive {
+            adaptive.decrease(sent_at, Instant::now());
+            #[cfg(feature = "logging")]
+            tracing::debug!(limit = adaptive.limit(), "rate limited; lowering concurrency limit");
+        }
+    }
+
+    /// Report a response to an attempt sent at `sent_at`
+    ///
+    /// Grows the adaptive concurrency limit, or shrinks it if the latency
+    /// spiked.
+    pub fn record_response(&self, sent_at: Instant) {
+        let mut state = self.limiter.lock();
+        let Some(adaptive) = &mut state.adaptive else {
+            return;
+        };
+        let grew = adaptive.record_latency(sent_at, Instant::now());
+        drop(state);
+        if grew {
+            self.limiter.shared.notify.notify_waiters();
+        }
+    }
+
+    /// Correct the tokens-per-minute bucket with the tokens actually used
+    pub fn record_tokens(&mut self, used: u32) {
+        if let Some(bucket) = &mut self.limiter.lock().tokens {
+            bucket.adjust(self.tokens - f64::from(used));
+        }
+        self.tokens = f64::from(used);
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut state = self.limiter.lock();
+        state.in_flight = state.in_flight.saturating_sub(1);
+        drop(state);
+        self.limiter.shared.notify.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_concurrency_and_priority() {
+        let limiter = RateLimiter::new().with_max_concurrency(1);
+        let first = limiter.acquire(Priority::Normal, 0).await;
+        assert_eq!(limiter.in_flight(), 1);
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for (name, priority) in [("low", Priority::Low), ("normal", Priority::Normal), ("high", Priority::High)] {
+            let task_limiter = limiter.clone();
+            let order = order.clone();
+            tasks.push(tokio::spawn(async move {
+                let _permit = task_limiter.acquire(priority, 0).await;
+                order.lock().unwrap().push(name);
+            }));
+            // Queue them in a known order
+            while limiter.queue_depth() < tasks.len() {
+                tokio::task::yield_now().await;
+            }
+        }
+
+        drop(first);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), ["high", "normal", "low"]);
+        assert_eq!(limiter.in_flight(), 0);
+        assert_eq!(limiter.queue_depth(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_request_leaves_queue() {
+        let limiter = RateLimiter::new().with_max_concurrency(1);
+        let permit = limiter.acquire(Priority::Normal, 0).await;
+
+        let waiting = tokio::time::timeout(Duration::from_millis(20), limiter.acquire(Priority::High, 0)).await;
+        assert!(waiting.is_err());
+        assert_eq!(limiter.queue_depth(), 0);
+
+        drop(permit);
+        let _permit = limiter.acquire(Priority::Low, 0).await;
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let now = Instant::now();
+        let mut bucket = Bucket::new(600);
+        bucket.refilled = now;
+        assert_eq!(bucket.wait_time(600.0), Duration::ZERO);
+
+        bucket.take(600.0);
+        assert_eq!(bucket.wait_time(10.0), Duration::from_secs(1));
+        assert_eq!(bucket.wait_time(10_000.0), Duration::from_secs(60));
+
+        bucket.refill(now + Duration::from_secs(2));
+        assert!((bucket.available - 20.0).abs() < 1e-9);
+        bucket.adjust(-50.0);
+        assert_eq!(bucket.wait_time(10.0), Duration::from_secs(4));
+        bucket.adjust(10_000.0);
+        assert_eq!(bucket.available, 600.0);
+    }
+
+    #[test]
+    fn test_adaptive_limit() {
+        let settings = AdaptiveConcurrency::new().with_initial_limit(4).with_bounds(2, 5);
+        assert!(settings.validate().is_ok());
+        let mut adaptive = Adaptive::new(settings);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        // About four healthy responses at a limit of 4 add one
+        for i in 0..5 {
+            adaptive.record_latency(at(i * 10), at(i * 10 + 100));
+        }
+        assert_eq!(adaptive.limit(), 5);
+        for i in 0..20 {
+            adaptive.record_latency(at(i), at(i + 100));
+        }
+        assert_eq!(adaptive.limit(), 5);
+
+        // Only the first of several concurrent 429s counts
+        adaptive.decrease(at(1000), at(1100));
+        adaptive.decrease(at(1050), at(1150));
+        assert_eq!(adaptive.limit(), 2);
+        adaptive.decrease(at(1200), at(1300));
+        assert_eq!(adaptive.limit(), 2);
+
+        // Latency is ignored by default
+        let mut adaptive = Adaptive::new(AdaptiveConcurrency::new().with_initial_limit(8));
+        adaptive.record_latency(at(0), at(100));
+        adaptive.record_latency(at(200), at(1200));
+        assert_eq!(adaptive.limit(), 8);
+
+        // With a tolerance, a response ten times slower than usual is a spike
+        let settings = AdaptiveConcurrency::new()
+            .with_initial_limit(8)
+            .with_latency_tolerance(Some(3.0));
+        let mut adaptive = Adaptive::new(settings);
+        adaptive.record_latency(at(0), at(100));
+        assert!(!adaptive.record_latency(at(200), at(1200)));
+        assert_eq!(adaptive.limit(), 4);
+    }
+
+    #[test]
+    fn test_adaptive_settings_validation() {
+        assert!(AdaptiveConcurrency::new().with_bounds(0, 4).validate().is_err());
+        assert!(AdaptiveConcurrency::new().with_bounds(5, 4).validate().is_err());
+        assert!(AdaptiveConcurrency::new().with_decrease_factor(1.0).validate().is_err());
+        assert!(AdaptiveConcurrency::new().with_latency_tolerance(Some(0.5)).validate().is_err());
+        assert!(AdaptiveConcurrency::new().with_latency_tolerance(None).validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_tokens_per_minute() {
+        let limiter = RateLimiter::new().with_tokens_per_minute(6_000);
+        assert!(limiter.limits_tokens());
+
+        let mut permit = limiter.acquire(Priority::Normal, 6_000).await;
+        // Only 600 tokens were used, so 5_400 are returned to the bucket
+        permit.record_tokens(600);
+        let waited = tokio::time::timeout(Duration::from_millis(100), limiter.acquire(Priority::Normal, 5_000)).await;
+        assert!(waited.is_ok());
+
+        let waited = tokio::time::timeout(Duration::from_millis(100), limiter.acquire(Priority::Normal, 5_000)).await;
+        assert!(waited.is_err());
+
+        // An oversized estimate only charges the capacity, so only the
+        // difference to that is returned
+        let limiter = RateLimiter::new().with_tokens_per_minute(6_000);
+        let mut permit = limiter.acquire(Priority::Normal, 10_000).await;
+        permit.record_tokens(600);
+        let available = limiter.lock().tokens.as_ref().unwrap().available;
+        assert!((5_400.0..5_500.0).contains(&available));
+    }
+
+    #[tokio::test]
+    async fn test_priority_aging() {
+        let limiter = RateLimiter::new()
+            .with_max_concurrency(1)
+            .with_priority_aging(Duration::from_millis(20));
+        let first = limiter.acquire(Priority::Normal, 0).await;
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for (name, priority) in [("low", Priority::Low), ("high", Priority::High)] {
+            let task_limiter = limiter.clone();
+            let order = order.clone();
+            tasks.push(tokio::spawn(async move {
+                let _permit = task_limiter.acquire(priority, 0).await;
+                order.lock().unwrap().push(name);
+            }));
+            while limiter.queue_depth() < tasks.len() {
+                tokio::task::yield_now().await;
+            }
+            // The low request has waited two aging steps when the high one
+            // arrives
+            tokio::time::sleep(Duration::from_millis(60)).await;
+        }
+
+        drop(first);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), ["low", "high"]);
+    }
+
+    #[tokio::test]
+    async fn test_huge_priority_aging() {
+        let limiter = RateLimiter::new()
+            .with_max_concurrency(1)
+            .with_priority_aging(Duration::MAX);
+        let first = limiter.acquire(Priority::Normal, 0).await;
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for (name, priority) in [("low", Priority::Low), ("normal", Priority::Normal), ("high", Priority::High)] {
+            let task_limiter = limiter.clone();
+            let order = order.clone();
+            tasks.push(tokio::spawn(async move {
+                let _permit = task_limiter.acquire(priority, 0).await;
+                order.lock().unwrap().push(name);
+            }));
+            while limiter.queue_depth() < tasks.len() {
+                tokio::task::yield_now().await;
+            }
+        }
+
+        drop(first);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), ["high", "normal", "low"]);
+    }
+}
@@ -0,0 +1,121 @@
+//! Account balance models for the `/user/balance` endpoint
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Account balance returned by [`DeepSeekClient::balance`]
+///
+/// [`DeepSeekClient::balance`]: crate::DeepSeekClient::balance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BalanceInfo {
+    /// Whether the balance is sufficient for API calls
+    pub is_available: bool,
+
+    /// Balance per currency
+    #[serde(default)]
+    pub balance_infos: Vec<CurrencyBalance>,
+}
+
+impl BalanceInfo {
+    /// Get the balance for a currency (e.g. "CNY" or "USD")
+    pub fn currency(&self, currency: &str) -> Option<&CurrencyBalance> {
+        self.balance_infos
+            .iter()
+            .find(|b| b.currency.eq_ignore_ascii_case(currency))
+    }
+
+    /// Check if the balance is unavailable or any currency's total is below
+    /// `threshold`
+    pub fn is_below(&self, threshold: f64) -> bool {
+        !self.is_available || self.balance_infos.iter().any(|b| b.total_balance < threshold)
+    }
+}
+
+/// Balance in a single currency
+///
+/// The API reports amounts as decimal strings; they are parsed into `f64`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CurrencyBalance {
+    /// Currency code (e.g. "CNY" or "USD")
+    pub currency: String,
+
+    /// Total available balance, granted plus topped-up
+    #[serde(deserialize_with = "decimal")]
+    pub total_balance: f64,
+
+    /// Balance from grants
+    #[serde(deserialize_with = "decimal")]
+    pub granted_balance: f64,
+
+    /// Balance from top-ups
+    #[serde(deserialize_with = "decimal")]
+    pub topped_up_balance: f64,
+}
+
+/// Deserialize an amount sent either as a decimal string or a number
+fn decimal<'de, D>(deserializer: D) -> std::result::Result<f64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Amount {
+        Number(f64),
+        Text(String),
+    }
+
+    match Amount::deserialize(deserializer)? {
+        Amount::Number(value) => Ok(value),
+        Amount::Text(text) => text
+            .trim()
+            .parse()
+            .map_err(|_| serde::de::Error::custom(format!("invalid amount '{}'", text))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_balance_deserialization() {
+        let balance: BalanceInfo = serde_json::from_value(json!({
+            "is_available": true,
+            "balance_infos": [{
+                "currency": "CNY",
+                "total_balance": "110.00",
+                "granted_balance": "10.00",
+                "topped_up_balance": "100.00"
+            }]
+        }))
+        .unwrap();
+
+        let cny = balance.currency("cny").unwrap();
+        assert_eq!(cny.total_balance, 110.0);
+        assert_eq!(cny.granted_balance, 10.0);
+        assert_eq!(cny.topped_up_balance, 100.0);
+        assert!(balance.currency("USD").is_none());
+
+        assert!(!balance.is_below(50.0));
+        assert!(balance.is_below(200.0));
+
+        let round_trip: BalanceInfo =
+            serde_json::from_value(serde_json::to_value(&balance).unwrap()).unwrap();
+        assert_eq!(round_trip, balance);
+    }
+
+    #[test]
+    fn test_balance_unavailable() {
+        let balance: BalanceInfo =
+            serde_json::from_value(json!({"is_available": false, "balance_infos": []})).unwrap();
+        assert!(balance.is_below(0.0));
+
+        let invalid = serde_json::from_value::<CurrencyBalance>(json!({
+            "currency": "USD",
+            "total_balance": "lots",
+            "granted_balance": "0",
+            "topped_up_balance": "0"
+        }));
+        assert!(invalid.is_err());
+    }
+}
@@ -0,0 +1,164 @@
+//! Per-model capabilities used to validate requests
+
+use crate::models::request::Model;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A sampling parameter some models accept but ignore
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestParam {
+    /// `temperature`
+    Temperature,
+    /// `top_p`
+    TopP,
+    /// `frequency_penalty`
+    FrequencyPenalty,
+    /// `presence_penalty`
+    PresencePenalty,
+}
+
+impl RequestParam {
+    /// Name of the parameter in the request body
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestParam::Temperature => "temperature",
+            RequestParam::TopP => "top_p",
+            RequestParam::FrequencyPenalty => "frequency_penalty",
+            RequestParam::PresencePenalty => "presence_penalty",
+        }
+    }
+}
+
+impl fmt::Display for RequestParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// How requests using features a model does not support are handled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationMode {
+    /// Reject the request with `InvalidParameter`
+    #[default]
+    Strict,
+    /// Strip unsupported parameters and log a warning
+    Lenient,
+}
+
+/// Limits and features of a model
+///
+/// Built-in values are returned by [`Model::capabilities`]; entries can be
+/// overridden per model with [`DeepSeekConfig::with_model_capabilities`].
+///
+/// [`DeepSeekConfig::with_model_capabilities`]: crate::DeepSeekConfig::with_model_capabilities
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelCapabilities {
+    /// Context window in tokens, if known
+    pub context_window: Option<u32>,
+
+    /// Maximum `max_tokens` accepted, if known
+    pub max_output_tokens: Option<u32>,
+
+    /// Whether the model can call tools
+    pub supports_tools: bool,
+
+    /// Whether the model supports JSON output mode
+    pub supports_json_output: bool,
+
+    /// Whether the model supports FIM completion
+    pub supports_fim: bool,
+
+    /// Whether the model can return log probabilities
+    pub supports_logprobs: bool,
+
+    /// Whether responses can be streamed
+    pub supports_streaming: bool,
+
+    /// Parameters the model accepts but ignores
+    pub ignored_params: Vec<RequestParam>,
+}
+
+impl ModelCapabilities {
+    /// Built-in capabilities for a model
+    ///
+    /// [`Model::Custom`] models get [`ModelCapabilities::unknown`].
+    pub fn for_model(model: &Model) -> Self {
+        match model {
+            Model::Chat | Model::Coder => Self {
+                context_window: Some(128_000),
+                max_output_tokens: Some(8_192),
+                supports_tools: true,
+                supports_json_output: true,
+                supports_fim: true,
+                supports_logprobs: true,
+                supports_streaming: true,
+                ignored_params: Vec::new(),
+            },
+            Model::Reasoner => Self {
+                context_window: Some(128_000),
+                max_output_tokens: Some(65_536),
+                supports_tools: true,
+                supports_json_output: true,
+                supports_fim: false,
+                supports_logprobs: false,
+                supports_streaming: true,
+                ignored_params: vec![
+                    RequestParam::Temperature,
+                    RequestParam::TopP,
+                    RequestParam::FrequencyPenalty,
+                    RequestParam::PresencePenalty,
+                ],
+            },
+            Model::Custom(_) => Self::unknown(),
+        }
+    }
+
+    /// Capabilities that allow everything, for models the crate does not know
+    pub fn unknown() -> Self {
+        Self {
+            context_window: None,
+            max_output_tokens: None,
+            supports_tools: true,
+            supports_json_output: true,
+            supports_fim: true,
+            supports_logprobs: true,
+            supports_streaming: true,
+            ignored_params: Vec::new(),
+        }
+    }
+
+    /// Check if the model ignores a parameter
+    pub fn ignores(&self, param: RequestParam) -> bool {
+        self.ignored_params.contains(&param)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_capabilities() {
+        let chat = Model::Chat.capabilities();
+        assert!(chat.supports_logprobs);
+        assert!(chat.supports_fim);
+        assert!(!chat.ignores(RequestParam::Temperature));
+
+        let reasoner = Model::Reasoner.capabilities();
+        assert!(!reasoner.supports_logprobs);
+        assert!(!reasoner.supports_fim);
+        assert!(reasoner.ignores(RequestParam::TopP));
+        assert!(reasoner.max_output_tokens > chat.max_output_tokens);
+
+        assert_eq!(Model::from_id("deepseek-v9").capabilities(), ModelCapabilities::unknown());
+    }
+
+    #[test]
+    fn test_capabilities_serialization() {
+        let json = serde_json::to_value(Model::Reasoner.capabilities()).unwrap();
+        assert_eq!(json["ignored_params"][0], "temperature");
+        assert_eq!(serde_json::to_value(ValidationMode::Lenient).unwrap(), "lenient");
+    }
+}
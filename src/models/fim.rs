@@ -0,0 +1,495 @@
+//! Fill-in-the-middle (FIM) completion models for the beta `/completions` endpoint
+
+use crate::error::{DeepSeekError, Result};
+use crate::models::capabilities::{ModelCapabilities, RequestParam};
+use crate::models::request::{apply_capability_fixes, CapabilityFix, Model, Temperature};
+use crate::models::response::{LogProbs, TokenLogProb, TopLogProb, Usage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Maximum number of tokens a FIM completion may generate
+pub const MAX_FIM_TOKENS: u32 = 4096;
+
+/// Maximum number of alternatives accepted for `logprobs`
+pub const MAX_FIM_LOGPROBS: u32 = 20;
+
+/// FIM completion request
+///
+/// The model generates the text between `prompt` and `suffix`.
+///
+/// # Example
+/// ```
+/// use deepseek_rust::models::fim::FimCompletionRequest;
+///
+/// let request = FimCompletionRequest::new("fn add(a: i32, b: i32) -> i32 {\n")
+///     .with_suffix("\n}")
+///     .with_max_tokens(64);
+/// assert!(request.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FimCompletionRequest {
+    /// The model to use
+    pub model: Model,
+
+    /// Text before the insertion point
+    pub prompt: String,
+
+    /// Text after the insertion point
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<String>,
+
+    /// Maximum tokens to generate (at most 4096)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+
+    /// Whether to echo the prompt back before the completion
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub echo: Option<bool>,
+
+    /// Temperature for randomness (0.0-2.0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<Temperature>,
+
+    /// Top-p sampling parameter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+
+    /// Frequency penalty (-2.0 to 2.0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+
+    /// Presence penalty (-2.0 to 2.0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+
+    /// Stop sequences
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+
+    /// Number of most likely alternatives to return per token (0-20)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<u32>,
+
+    /// Whether to stream the response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+}
+
+impl FimCompletionRequest {
+    /// Create a request for the Coder model with a prompt
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            model: Model::Coder,
+            prompt: prompt.into(),
+            suffix: None,
+            max_tokens: None,
+            echo: None,
+            temperature: None,
+            top_p: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            stop: None,
+            logprobs: None,
+            stream: None,
+        }
+    }
+
+    /// Set the model
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Set the text after the insertion point
+    pub fn with_suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.suffix = Some(suffix.into());
+        self
+    }
+
+    /// Set max tokens
+    pub fn with_max_tokens(mut self, tokens: u32) -> Self {
+        self.max_tokens = Some(tokens);
+        self
+    }
+
+    /// Echo the prompt back before the completion
+    pub fn with_echo(mut self, echo: bool) -> Self {
+        self.echo = Some(echo);
+        self
+    }
+
+    /// Set temperature
+    pub fn with_temperature(mut self, temp: Temperature) -> Self {
+        self.temperature = Some(temp);
+        self
+    }
+
+    /// Set top-p sampling
+    pub fn with_top_p(mut self, top_p: f32) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    /// Set stop sequences
+    pub fn with_stop(mut self, stop: Vec<String>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    /// Request log probabilities with up to this many alternatives per token
+    pub fn with_logprobs(mut self, logprobs: u32) -> Self {
+        self.logprobs = Some(logprobs);
+        self
+    }
+
+    /// Enable streaming
+    pub fn with_stream(mut self, stream: bool) -> Self {
+        self.stream = Some(stream);
+        self
+    }
+
+    /// Validate the request against the model's built-in capabilities
+    pub fn validate(&self) -> Result<()> {
+        self.validate_with(&self.model.capabilities())
+    }
+
+    /// Validate the request against the given model capabilities
+    pub fn validate_with(&self, capabilities: &ModelCapabilities) -> Result<()> {
+        if !capabilities.supports_fim {
+            return Err(DeepSeekError::InvalidParameter(format!(
+                "FIM completion is not supported by {}",
+                self.model
+            )));
+        }
+
+        if self.stream == Some(true) && !capabilities.supports_streaming {
+            return Err(DeepSeekError::InvalidParameter(format!(
+                "{} does not support streaming",
+                self.model
+            )));
+        }
+
+        if let Some((issue, _)) = self.capability_issues(capabilities).into_iter().next() {
+            return Err(DeepSeekError::InvalidParameter(issue));
+        }
+
+        if self.prompt.is_empty() {
+            return Err(DeepSeekError::InvalidParameter(
+                "FIM prompt cannot be empty".to_string(),
+            ));
+        }
+
+        if let Some(max_tokens) = self.max_tokens {
+            if max_tokens == 0 || max_tokens > MAX_FIM_TOKENS {
+                return Err(DeepSeekError::InvalidParameter(format!(
+                    "max_tokens must be between 1 and {} for FIM completion, got {}",
+                    MAX_FIM_TOKENS, max_tokens
+                )));
+            }
+        }
+
+        if let Some(top_p) = self.top_p {
+            if !(0.0..=1.0).contains(&top_p) {
+                return Err(DeepSeekError::InvalidParameter(format!(
+                    "top_p must be between 0.0 and 1.0, got {}",
+                    top_p
+                )));
+            }
+        }
+
+        for (name, penalty) in [
+            ("frequency_penalty", self.frequency_penalty),
+            ("presence_penalty", self.presence_penalty),
+        ] {
+            if let Some(penalty) = penalty {
+                if !(-2.0..=2.0).contains(&penalty) {
+                    return Err(DeepSeekError::InvalidParameter(format!(
+                        "{} must be between -2.0 and 2.0, got {}",
+                        name, penalty
+                    )));
+                }
+            }
+        }
+
+        if let Some(logprobs) = self.logprobs {
+            if logprobs > MAX_FIM_LOGPROBS {
+                return Err(DeepSeekError::InvalidParameter(format!(
+                    "logprobs must be between 0 and {}, got {}",
+                    MAX_FIM_LOGPROBS, logprobs
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove parameters the model ignores or does not support
+    ///
+    /// Returns a description of every change made. FIM support itself
+    /// cannot be stripped and is still checked by
+    /// [`FimCompletionRequest::validate_with`].
+    pub fn strip_unsupported(&mut self, capabilities: &ModelCapabilities) -> Vec<String> {
+        let issues = self.capability_issues(capabilities);
+        apply_capability_fixes(self, capabilities, issues)
+    }
+
+    /// Parameters that conflict with the capabilities, with how to drop them
+    fn capability_issues(&self, capabilities: &ModelCapabilities) -> Vec<(String, CapabilityFix<Self>)> {
+        let mut issues: Vec<(String, CapabilityFix<Self>)> = Vec::new();
+
+        if self.logprobs.is_some() && !capabilities.supports_logprobs {
+            issues.push((
+                format!("{} does not support logprobs", self.model),
+                |r, _| r.logprobs = None,
+            ));
+        }
+
+        let params: [(RequestParam, bool, CapabilityFix<Self>); 4] = [
+            (RequestParam::Temperature, self.temperature.is_some(), |r, _| r.temperature = None),
+            (RequestParam::TopP, self.top_p.is_some(), |r, _| r.top_p = None),
+            (RequestParam::FrequencyPenalty, self.frequency_penalty.is_some(), |r, _| r.frequency_penalty = None),
+            (RequestParam::PresencePenalty, self.presence_penalty.is_some(), |r, _| r.presence_penalty = None),
+        ];
+        for (param, is_set, fix) in params {
+            if is_set && capabilities.ignores(param) {
+                issues.push((format!("{} is ignored by {}", param, self.model), fix));
+            }
+        }
+
+        issues
+    }
+}
+
+/// FIM completion response (also the shape of each streamed chunk)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FimCompletionResponse {
+    /// Unique identifier for the completion
+    pub id: String,
+
+    /// Object type (usually "text_completion")
+    pub object: String,
+
+    /// Unix timestamp of when the completion was created
+    pub created: u64,
+
+    /// The model used for the completion
+    pub model: String,
+
+    /// List of completion choices
+    pub choices: Vec<FimChoice>,
+
+    /// Token usage information
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+
+    /// System fingerprint for the response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_fingerprint: Option<String>,
+}
+
+impl FimCompletionResponse {
+    /// Get the first choice's text if available
+    pub fn get_text(&self) -> Option<&str> {
+        self.choices.first().map(|choice| choice.text.as_str())
+    }
+
+    /// Get total tokens used
+    pub fn total_tokens(&self) -> Option<u32> {
+        self.usage.as_ref().map(|u| u.total_tokens)
+    }
+}
+
+/// A choice in a FIM completion response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FimChoice {
+    /// The index of this choice
+    pub index: u32,
+
+    /// The generated text
+    #[serde(default)]
+    pub text: String,
+
+    /// The reason the completion stopped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<String>,
+
+    /// Log probabilities (if requested)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<CompletionLogProbs>,
+}
+
+/// Log probabilities in the column-oriented format of the completions endpoint
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompletionLogProbs {
+    /// Generated tokens, in order
+    #[serde(default)]
+    pub tokens: Vec<String>,
+
+    /// Log probability of each token, `None` for echoed prompt tokens
+    /// the model did not score
+    #[serde(default)]
+    pub token_logprobs: Vec<Option<f64>>,
+
+    /// Most likely alternatives at each position, keyed by token text
+    #[serde(default)]
+    pub top_logprobs: Vec<Option<HashMap<String, f64>>>,
+
+    /// Character offset of each token in the text
+    #[serde(default)]
+    pub text_offset: Vec<u32>,
+}
+
+impl CompletionLogProbs {
+    /// Convert into the per-token [`LogProbs`] used by chat responses
+    ///
+    /// This gives access to the sequence probability and confidence helpers.
+    /// Alternatives are sorted from most to least likely. Tokens without a
+    /// log probability, such as the first echoed prompt token, are skipped.
+    pub fn to_logprobs(&self) -> LogProbs {
+        let content = self
+            .tokens
+            .iter()
+            .zip(&self.token_logprobs)
+            .enumerate()
+            .filter_map(|(i, (token, &logprob))| {
+                let logprob = logprob?;
+                let mut top_logprobs: Vec<TopLogProb> = self
+                    .top_logprobs
+                    .get(i)
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                    .map(|(token, &logprob)| TopLogProb {
+                        token: token.clone(),
+                        logprob,
+                        bytes: None,
+                    })
+                    .collect();
+                top_logprobs.sort_by(|a, b| b.logprob.total_cmp(&a.logprob));
+
+                Some(TokenLogProb {
+                    token: token.clone(),
+                    logprob,
+                    bytes: None,
+                    top_logprobs,
+                })
+            })
+            .collect();
+
+        LogProbs { content }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_fim_request_serialization() {
+        let request = FimCompletionRequest::new("def fib(n):")
+            .with_suffix("    return fib(n - 1) + fib(n - 2)")
+            .with_max_tokens(128)
+            .with_stop(vec!["\n\n".to_string()]);
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "model": "deepseek-coder",
+                "prompt": "def fib(n):",
+                "suffix": "    return fib(n - 1) + fib(n - 2)",
+                "max_tokens": 128,
+                "stop": ["\n\n"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_fim_request_validation() {
+        assert!(FimCompletionRequest::new("x").validate().is_ok());
+        assert!(FimCompletionRequest::new("").validate().is_err());
+        assert!(FimCompletionRequest::new("x").with_max_tokens(4097).validate().is_err());
+        assert!(FimCompletionRequest::new("x").with_logprobs(21).validate().is_err());
+        assert!(FimCompletionRequest::new("x").with_top_p(2.0).validate().is_err());
+        assert!(FimCompletionRequest::new("x")
+            .with_model(Model::Reasoner)
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_fim_strip_unsupported() {
+        let mut capabilities = ModelCapabilities::unknown();
+        capabilities.supports_logprobs = false;
+        capabilities.ignored_params = vec![RequestParam::TopP];
+
+        let mut request = FimCompletionRequest::new("x").with_logprobs(2).with_top_p(0.5);
+        assert!(request.validate_with(&capabilities).is_err());
+
+        let stripped = request.strip_unsupported(&capabilities);
+        assert_eq!(stripped.len(), 2);
+        assert_eq!(request.logprobs, None);
+        assert_eq!(request.top_p, None);
+        assert!(request.validate_with(&capabilities).is_ok());
+    }
+
+    #[test]
+    fn test_fim_response_logprobs() {
+        let response: FimCompletionResponse = serde_json::from_value(json!({
+            "id": "cmpl-1",
+            "object": "text_completion",
+            "created": 1,
+            "model": "deepseek-coder",
+            "choices": [{
+                "index": 0,
+                "text": "    if n < 2:\n        return n\n",
+                "finish_reason": "stop",
+                "logprobs": {
+                    "tokens": ["    if", " n"],
+                    "token_logprobs": [-0.5, -0.25],
+                    "top_logprobs": [{"    if": -0.5, "    return": -1.5}, {" n": -0.25}],
+                    "text_offset": [11, 17]
+                }
+            }],
+            "usage": {"prompt_tokens": 20, "completion_tokens": 10, "total_tokens": 30}
+        }))
+        .unwrap();
+
+        assert_eq!(response.get_text(), Some("    if n < 2:\n        return n\n"));
+        assert_eq!(response.total_tokens(), Some(30));
+
+        let logprobs = response.choices[0].logprobs.as_ref().unwrap().to_logprobs();
+        assert_eq!(logprobs.content.len(), 2);
+        assert!((logprobs.total_logprob() + 0.75).abs() < 1e-9);
+        assert_eq!(logprobs.content[0].top_logprobs[1].token, "    return");
+    }
+
+    #[test]
+    fn test_fim_response_echo_logprobs() {
+        let response: FimCompletionResponse = serde_json::from_value(json!({
+            "id": "cmpl-2",
+            "object": "text_completion",
+            "created": 1,
+            "model": "deepseek-coder",
+            "choices": [{
+                "index": 0,
+                "text": "def fib(n):\n    if",
+                "finish_reason": "length",
+                "logprobs": {
+                    "tokens": ["def", " fib", "(n):", "\n    if"],
+                    "token_logprobs": [null, -1.0, -0.5, -0.25],
+                    "top_logprobs": [null, {" fib": -1.0}, {"(n):": -0.5}, {"\n    if": -0.25}],
+                    "text_offset": [0, 3, 7, 11]
+                }
+            }],
+            "usage": {"prompt_tokens": 3, "completion_tokens": 1, "total_tokens": 4}
+        }))
+        .unwrap();
+
+        let logprobs = response.choices[0].logprobs.as_ref().unwrap().to_logprobs();
+        assert_eq!(logprobs.content.len(), 3);
+        assert_eq!(logprobs.content[0].token, " fib");
+        assert_eq!(logprobs.content[0].top_logprobs[0].token, " fib");
+        assert!((logprobs.total_logprob() + 1.75).abs() < 1e-9);
+    }
+}
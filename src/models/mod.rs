@@ -1,12 +1,25 @@
 //! Data models for DeepSeek API requests and responses
 
+pub mod balance;
+pub mod capabilities;
+pub mod fim;
+pub mod pricing;
 pub mod request;
 pub mod response;
+pub mod tool;
 
 // Re-export commonly used types
+pub use balance::{BalanceInfo, CurrencyBalance};
+pub use capabilities::{ModelCapabilities, RequestParam, ValidationMode};
+pub use fim::{CompletionLogProbs, FimChoice, FimCompletionRequest, FimCompletionResponse};
+pub use pricing::{ModelPricing, OffPeakWindow};
 pub use request::{
-    ChatCompletionRequest, Message, Model, Role, Temperature,
+    ChatCompletionRequest, Message, Model, ResponseFormat, Role, Temperature,
 };
 pub use response::{
-    ApiErrorDetail, ApiErrorResponse, ChatCompletionResponse, Choice, ResponseMessage, Usage,
-};
\ No newline at end of file
+    ApiErrorDetail, ApiErrorResponse, ChatCompletionResponse, Choice, DeltaContent,
+    FunctionCall, FunctionCallDelta, LogProbs, ModelInfo, ModelList, ReasoningPolicy,
+    ResponseMessage, StreamAccumulator, StreamChoice, StreamChunk, TokenLogProb, ToolCall,
+    ToolCallDelta, TopLogProb, Usage,
+};
+pub use tool::{FunctionDefinition, Tool, ToolChoice};
@@ -0,0 +1,169 @@
+//! Per-model token prices used for cost estimates and budgets
+
+use crate::models::request::Model;
+use crate::models::response::Usage;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Prices are quoted per this many tokens
+pub const TOKENS_PER_PRICE_UNIT: f64 = 1_000_000.0;
+
+/// A daily UTC time window with discounted prices
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OffPeakWindow {
+    /// Start of the window in minutes after midnight UTC
+    pub start_minute: u16,
+
+    /// End of the window in minutes after midnight UTC (exclusive); may be
+    /// before the start for windows spanning midnight
+    pub end_minute: u16,
+
+    /// Factor applied to all prices inside the window (e.g. 0.5)
+    pub multiplier: f64,
+}
+
+impl OffPeakWindow {
+    /// Check if the window contains a time
+    pub fn contains(&self, at: SystemTime) -> bool {
+        let secs = at.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        let minute = ((secs % 86_400) / 60) as u16;
+        if self.start_minute <= self.end_minute {
+            (self.start_minute..self.end_minute).contains(&minute)
+        } else {
+            minute >= self.start_minute || minute < self.end_minute
+        }
+    }
+}
+
+/// Token prices of a model in USD per million tokens
+///
+/// Built-in values are returned by [`Model::pricing`] and reflect the list
+/// prices when this version of the crate was released; override them with
+/// [`DeepSeekConfig::with_model_pricing`].
+///
+/// [`DeepSeekConfig::with_model_pricing`]: crate::DeepSeekConfig::with_model_pricing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    /// Price of prompt tokens served from the context cache
+    pub input_cache_hit: f64,
+
+    /// Price of prompt tokens not in the context cache
+    pub input_cache_miss: f64,
+
+    /// Price of completion tokens, including reasoning tokens
+    pub output: f64,
+
+    /// Discount windows; the first one containing a time applies
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub off_peak: Vec<OffPeakWindow>,
+}
+
+impl ModelPricing {
+    /// Built-in prices for a model, if known
+    ///
+    /// FIM completions on [`Model::Coder`] are billed like [`Model::Chat`].
+    pub fn for_model(model: &Model) -> Option<Self> {
+        // 16:30 to 00:30 UTC
+        let window = |multiplier| OffPeakWindow {
+            start_minute: 16 * 60 + 30,
+            end_minute: 30,
+            multiplier,
+        };
+
+        match model {
+            Model::Chat | Model::Coder => Some(Self {
+                input_cache_hit: 0.07,
+                input_cache_miss: 0.27,
+                output: 1.10,
+                off_peak: vec![window(0.5)],
+            }),
+            Model::Reasoner => Some(Self {
+                input_cache_hit: 0.14,
+                input_cache_miss: 0.55,
+                output: 2.19,
+                off_peak: vec![window(0.25)],
+            }),
+            Model::Custom(_) => None,
+        }
+    }
+
+    /// Factor applied to the prices at a time
+    pub fn multiplier_at(&self, at: SystemTime) -> f64 {
+        self.off_peak
+            .iter()
+            .find(|window| window.contains(at))
+            .map_or(1.0, |window| window.multiplier)
+    }
+
+    /// Cost in USD of a completed request
+    ///
+    /// Prompt tokens without a cache breakdown count as cache misses.
+    pub fn cost(&self, usage: &Usage, at: SystemTime) -> f64 {
+        let hit = usage.prompt_cache_hit_tokens.unwrap_or(0);
+        let miss = usage
+            .prompt_cache_miss_tokens
+            .unwrap_or(usage.prompt_tokens.saturating_sub(hit));
+
+        let cost = f64::from(hit) * self.input_cache_hit
+            + f64::from(miss) * self.input_cache_miss
+            + f64::from(usage.completion_tokens) * self.output;
+        cost / TOKENS_PER_PRICE_UNIT * self.multiplier_at(at)
+    }
+
+    /// Upper-bound cost in USD of a request that has not been sent
+    ///
+    /// All prompt tokens are priced as cache misses.
+    pub fn estimate(&self, prompt_tokens: usize, output_tokens: usize, at: SystemTime) -> f64 {
+        let cost = prompt_tokens as f64 * self.input_cache_miss + output_tokens as f64 * self.output;
+        cost / TOKENS_PER_PRICE_UNIT * self.multiplier_at(at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// 1970-01-01 at the given UTC time
+    fn at(hour: u64, minute: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(hour * 3600 + minute * 60)
+    }
+
+    #[test]
+    fn test_off_peak_window() {
+        let pricing = Model::Chat.pricing().unwrap();
+        let window = pricing.off_peak[0];
+        assert!(window.contains(at(16, 30)));
+        assert!(window.contains(at(23, 59)));
+        assert!(window.contains(at(0, 29)));
+        assert!(!window.contains(at(0, 30)));
+        assert!(!window.contains(at(12, 0)));
+
+        let daytime = OffPeakWindow { start_minute: 60, end_minute: 120, multiplier: 0.5 };
+        assert!(daytime.contains(at(1, 30)));
+        assert!(!daytime.contains(at(2, 0)));
+    }
+
+    #[test]
+    fn test_cost() {
+        let pricing = Model::Chat.pricing().unwrap();
+        let usage = Usage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 1_000_000,
+            total_tokens: 2_000_000,
+            reasoning_tokens: None,
+            prompt_cache_hit_tokens: Some(400_000),
+            prompt_cache_miss_tokens: Some(600_000),
+        };
+
+        let peak = pricing.cost(&usage, at(12, 0));
+        assert!((peak - (0.4 * 0.07 + 0.6 * 0.27 + 1.10)).abs() < 1e-9);
+        assert!((pricing.cost(&usage, at(18, 0)) - peak * 0.5).abs() < 1e-9);
+
+        let uncached = Usage { prompt_cache_hit_tokens: None, prompt_cache_miss_tokens: None, ..usage };
+        assert!((pricing.cost(&uncached, at(12, 0)) - (0.27 + 1.10)).abs() < 1e-9);
+        assert!((pricing.estimate(1_000_000, 1_000_000, at(12, 0)) - (0.27 + 1.10)).abs() < 1e-9);
+
+        assert!(Model::from_id("my-model").pricing().is_none());
+    }
+}
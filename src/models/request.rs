@@ -1,33 +1,63 @@
 //! Request models for DeepSeek API
 
 use crate::error::{DeepSeekError, Result};
+use crate::models::capabilities::{ModelCapabilities, RequestParam};
+use crate::models::pricing::ModelPricing;
+use crate::models::response::ToolCall;
+use crate::models::tool::{Tool, ToolChoice, MAX_TOOLS};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
+/// Maximum number of alternatives accepted for `top_logprobs`
+pub const MAX_TOP_LOGPROBS: u32 = 20;
+
 /// Available DeepSeek models
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
-#[serde(rename_all = "kebab-case")]
+/// 
+/// Model ids without a dedicated variant deserialize to [`Model::Custom`],
+/// so models released after this version of the crate can still be used.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub enum Model {
     /// DeepSeek Chat model for general conversations
-    #[serde(rename = "deepseek-chat")]
+    #[default]
     Chat,
     
     /// DeepSeek Reasoner model for complex reasoning tasks
-    #[serde(rename = "deepseek-reasoner")]
     Reasoner,
     
     /// DeepSeek Coder model for programming tasks
-    #[serde(rename = "deepseek-coder")]
     Coder,
+    
+    /// Any other model id
+    Custom(String),
 }
 
 impl Model {
     /// Get the model's string representation
-    pub fn as_str(&self) -> &'static str {
+    pub fn as_str(&self) -> &str {
         match self {
             Model::Chat => "deepseek-chat",
             Model::Reasoner => "deepseek-reasoner",
             Model::Coder => "deepseek-coder",
+            Model::Custom(id) => id,
+        }
+    }
+    
+    /// Get the model for an id, using a dedicated variant when one exists
+    /// 
+    /// # Example
+    /// ```
+    /// use deepseek_rust::Model;
+    /// 
+    /// assert_eq!(Model::from_id("deepseek-chat"), Model::Chat);
+    /// assert_eq!(Model::from_id("deepseek-v9"), Model::Custom("deepseek-v9".to_string()));
+    /// ```
+    pub fn from_id(id: impl Into<String>) -> Self {
+        let id = id.into();
+        match id.as_str() {
+            "deepseek-chat" => Model::Chat,
+            "deepseek-reasoner" => Model::Reasoner,
+            "deepseek-coder" => Model::Coder,
+            _ => Model::Custom(id),
         }
     }
     
@@ -35,11 +65,15 @@ impl Model {
     pub fn supports_reasoning(&self) -> bool {
         matches!(self, Model::Reasoner)
     }
-}
-
-impl Default for Model {
-    fn default() -> Self {
-        Self::Chat
+    
+    /// Get the built-in capabilities of this model
+    pub fn capabilities(&self) -> ModelCapabilities {
+        ModelCapabilities::for_model(self)
+    }
+    
+    /// Built-in token prices, if known
+    pub fn pricing(&self) -> Option<ModelPricing> {
+        ModelPricing::for_model(self)
     }
 }
 
@@ -49,6 +83,44 @@ impl fmt::Display for Model {
     }
 }
 
+impl From<&str> for Model {
+    fn from(id: &str) -> Self {
+        Model::from_id(id)
+    }
+}
+
+impl From<String> for Model {
+    fn from(id: String) -> Self {
+        Model::from_id(id)
+    }
+}
+
+impl std::str::FromStr for Model {
+    type Err = std::convert::Infallible;
+    
+    fn from_str(id: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(Model::from_id(id))
+    }
+}
+
+impl Serialize for Model {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Model {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        String::deserialize(deserializer).map(Model::from_id)
+    }
+}
+
 /// Message role in conversation
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -59,6 +131,8 @@ pub enum Role {
     User,
     /// Assistant response
     Assistant,
+    /// Result of a tool call
+    Tool,
 }
 
 impl fmt::Display for Role {
@@ -67,6 +141,7 @@ impl fmt::Display for Role {
             Role::System => write!(f, "system"),
             Role::User => write!(f, "user"),
             Role::Assistant => write!(f, "assistant"),
+            Role::Tool => write!(f, "tool"),
         }
     }
 }
@@ -79,6 +154,28 @@ pub struct Message {
     
     /// The content of the message
     pub content: String,
+    
+    /// Tool calls requested by the assistant (assistant messages only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>,
+    
+    /// ID of the tool call this message answers (tool messages only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    
+    /// Marks the final assistant message as a prefix the model continues
+    /// from (beta)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<bool>,
+    
+    /// Reasoning the reasoner model continues from (prefix messages only)
+    /// 
+    /// Other messages may carry reasoning kept for auditing with
+    /// [`ReasoningPolicy::Keep`]; the client drops it before sending.
+    /// 
+    /// [`ReasoningPolicy::Keep`]: crate::models::response::ReasoningPolicy::Keep
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
 }
 
 impl Message {
@@ -87,6 +184,10 @@ impl Message {
         Self {
             role,
             content: content.into(),
+            tool_calls: None,
+            tool_call_id: None,
+            prefix: None,
+            reasoning_content: None,
         }
     }
     
@@ -126,14 +227,76 @@ impl Message {
         Self::new(Role::Assistant, content)
     }
     
+    /// Create an assistant message that requests tool calls
+    ///
+    /// Use this to echo the model's tool calls back into the conversation
+    /// before the matching [`Message::tool`] results.
+    pub fn assistant_with_tool_calls(content: impl Into<String>, tool_calls: Vec<ToolCall>) -> Self {
+        Self {
+            tool_calls: Some(tool_calls),
+            ..Self::new(Role::Assistant, content)
+        }
+    }
+    
+    /// Create an assistant prefix the model must continue from
+    /// 
+    /// Prefix completion is a beta feature: the request is sent to the beta
+    /// endpoint and the prefix has to be the last message. The response only
+    /// contains the continuation, not the prefix itself.
+    /// 
+    /// # Example
+    /// ```
+    /// use deepseek_rust::Message;
+    /// 
+    /// let msg = Message::assistant_prefix("```rust\n");
+    /// assert!(msg.is_prefix());
+    /// ```
+    pub fn assistant_prefix(content: impl Into<String>) -> Self {
+        Self {
+            prefix: Some(true),
+            ..Self::new(Role::Assistant, content)
+        }
+    }
+    
+    /// Set the reasoning the reasoner model continues from (prefix only)
+    pub fn with_reasoning_content(mut self, reasoning: impl Into<String>) -> Self {
+        self.reasoning_content = Some(reasoning.into());
+        self
+    }
+    
+    /// Check if this is an assistant prefix message
+    pub fn is_prefix(&self) -> bool {
+        self.prefix == Some(true)
+    }
+    
+    /// Create a tool result message
+    /// 
+    /// # Example
+    /// ```
+    /// use deepseek_rust::Message;
+    /// 
+    /// let msg = Message::tool("call_abc123", r#"{"temperature": 21}"#);
+    /// ```
+    pub fn tool(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: Some(tool_call_id.into()),
+            ..Self::new(Role::Tool, content)
+        }
+    }
+    
     /// Get the length of the message content
     pub fn len(&self) -> usize {
         self.content.len()
     }
     
     /// Check if the message is empty
+    /// 
+    /// An assistant message that only carries tool calls or prefix
+    /// reasoning is not empty.
     pub fn is_empty(&self) -> bool {
         self.content.is_empty()
+            && self.tool_calls.as_ref().is_none_or(Vec::is_empty)
+            && self.reasoning_content.as_deref().is_none_or(str::is_empty)
     }
 }
 
@@ -219,6 +382,38 @@ impl<'de> Deserialize<'de> for Temperature {
     }
 }
 
+/// Format the model must produce
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Free-form text
+    #[default]
+    Text,
+    /// A single valid JSON object
+    /// 
+    /// The API requires the word "json" to appear in the system or user
+    /// prompt when this mode is used.
+    JsonObject,
+}
+
+/// Removes one unsupported parameter from a request
+pub(crate) type CapabilityFix<R> = fn(&mut R, &ModelCapabilities);
+
+/// Apply the fixes of capability issues, returning their descriptions
+pub(crate) fn apply_capability_fixes<R>(
+    request: &mut R,
+    capabilities: &ModelCapabilities,
+    issues: Vec<(String, CapabilityFix<R>)>,
+) -> Vec<String> {
+    issues
+        .into_iter()
+        .map(|(issue, fix)| {
+            fix(request, capabilities);
+            issue
+        })
+        .collect()
+}
+
 /// Chat completion request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatCompletionRequest {
@@ -263,6 +458,26 @@ pub struct ChatCompletionRequest {
     /// User identifier for tracking
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<String>,
+    
+    /// Tools the model may call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<Tool>>,
+    
+    /// Controls which tool the model calls
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    
+    /// Output format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    
+    /// Whether to return log probabilities of the output tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<bool>,
+    
+    /// Number of most likely alternatives to return per token (0-20)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_logprobs: Option<u32>,
 }
 
 impl ChatCompletionRequest {
@@ -280,6 +495,11 @@ impl ChatCompletionRequest {
             stream: None,
             n: None,
             user: None,
+            tools: None,
+            tool_choice: None,
+            response_format: None,
+            logprobs: None,
+            top_logprobs: None,
         }
     }
     
@@ -348,8 +568,72 @@ impl ChatCompletionRequest {
         self
     }
     
-    /// Validate the request
+    /// Set the tools the model may call
+    pub fn with_tools(mut self, tools: Vec<Tool>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+    
+    /// Add a tool the model may call
+    pub fn add_tool(mut self, tool: Tool) -> Self {
+        self.tools.get_or_insert_with(Vec::new).push(tool);
+        self
+    }
+    
+    /// Set the tool choice
+    pub fn with_tool_choice(mut self, choice: ToolChoice) -> Self {
+        self.tool_choice = Some(choice);
+        self
+    }
+    
+    /// Set the response format
+    pub fn with_response_format(mut self, format: ResponseFormat) -> Self {
+        self.response_format = Some(format);
+        self
+    }
+    
+    /// Request log probabilities of the output tokens
+    pub fn with_logprobs(mut self, logprobs: bool) -> Self {
+        self.logprobs = Some(logprobs);
+        self
+    }
+    
+    /// Request the most likely alternatives for each token
+    /// 
+    /// Also enables `logprobs`, which the API requires for this option.
+    pub fn with_top_logprobs(mut self, top_logprobs: u32) -> Self {
+        self.logprobs = Some(true);
+        self.top_logprobs = Some(top_logprobs);
+        self
+    }
+    
+    /// Check if the request ends with an assistant prefix (beta)
+    pub fn has_prefix(&self) -> bool {
+        self.messages.last().is_some_and(Message::is_prefix)
+    }
+    
+    /// Remove reasoning content from every message except a prefix
+    /// 
+    /// The API rejects reasoning in earlier turns. Returns the number of
+    /// messages changed.
+    pub fn strip_history_reasoning(&mut self) -> usize {
+        self.messages
+            .iter_mut()
+            .filter(|msg| !msg.is_prefix())
+            .filter_map(|msg| msg.reasoning_content.take())
+            .count()
+    }
+    
+    /// Validate the request against the model's built-in capabilities
     pub fn validate(&self) -> Result<()> {
+        self.validate_with(&self.model.capabilities())
+    }
+    
+    /// Validate the request against the given model capabilities
+    /// 
+    /// Parameters the model ignores or does not support are rejected; use
+    /// [`ChatCompletionRequest::strip_unsupported`] first to drop them instead.
+    pub fn validate_with(&self, capabilities: &ModelCapabilities) -> Result<()> {
         // Check messages
         if self.messages.is_empty() {
             return Err(DeepSeekError::InvalidParameter(
@@ -402,6 +686,221 @@ impl ChatCompletionRequest {
             }
         }
         
+        // Validate top_logprobs
+        if let Some(top_logprobs) = self.top_logprobs {
+            if top_logprobs > MAX_TOP_LOGPROBS {
+                return Err(DeepSeekError::InvalidParameter(
+                    format!("top_logprobs must be between 0 and {}, got {}", MAX_TOP_LOGPROBS, top_logprobs)
+                ));
+            }
+            if self.logprobs != Some(true) {
+                return Err(DeepSeekError::InvalidParameter(
+                    "top_logprobs requires logprobs to be enabled".to_string()
+                ));
+            }
+        }
+        
+        self.validate_tools()?;
+        self.validate_tool_messages()?;
+        self.validate_prefix()?;
+        
+        if self.stream == Some(true) && !capabilities.supports_streaming {
+            return Err(DeepSeekError::InvalidParameter(
+                format!("{} does not support streaming", self.model)
+            ));
+        }
+        
+        if let Some((issue, _)) = self.capability_issues(capabilities).into_iter().next() {
+            return Err(DeepSeekError::InvalidParameter(issue));
+        }
+        
+        // JSON mode requires the prompt to ask for JSON
+        if self.response_format == Some(ResponseFormat::JsonObject) {
+            let mentions_json = self.messages.iter().any(|m| {
+                matches!(m.role, Role::System | Role::User)
+                    && m.content.to_lowercase().contains("json")
+            });
+            if !mentions_json {
+                return Err(DeepSeekError::InvalidParameter(
+                    "JSON output mode requires a system or user message that mentions JSON".to_string()
+                ));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Remove parameters the model ignores or does not support
+    /// 
+    /// `max_tokens` is clamped to the model's output limit. Returns a
+    /// description of every change made.
+    pub fn strip_unsupported(&mut self, capabilities: &ModelCapabilities) -> Vec<String> {
+        let issues = self.capability_issues(capabilities);
+        apply_capability_fixes(self, capabilities, issues)
+    }
+    
+    /// Parameters that conflict with the capabilities, with how to drop them
+    fn capability_issues(&self, capabilities: &ModelCapabilities) -> Vec<(String, CapabilityFix<Self>)> {
+        let mut issues: Vec<(String, CapabilityFix<Self>)> = Vec::new();
+        let model = &self.model;
+        
+        let params: [(RequestParam, bool, CapabilityFix<Self>); 4] = [
+            (RequestParam::Temperature, self.temperature.is_some(), |r, _| r.temperature = None),
+            (RequestParam::TopP, self.top_p.is_some(), |r, _| r.top_p = None),
+            (RequestParam::FrequencyPenalty, self.frequency_penalty.is_some(), |r, _| r.frequency_penalty = None),
+            (RequestParam::PresencePenalty, self.presence_penalty.is_some(), |r, _| r.presence_penalty = None),
+        ];
+        for (param, is_set, fix) in params {
+            if is_set && capabilities.ignores(param) {
+                issues.push((format!("{} is ignored by {}", param, model), fix));
+            }
+        }
+        
+        if let (Some(max_tokens), Some(limit)) = (self.max_tokens, capabilities.max_output_tokens) {
+            if max_tokens > limit {
+                issues.push((
+                    format!("max_tokens {} exceeds the {} token output limit of {}", max_tokens, limit, model),
+                    |r, caps| r.max_tokens = caps.max_output_tokens,
+                ));
+            }
+        }
+        
+        if self.tools.as_ref().is_some_and(|t| !t.is_empty()) && !capabilities.supports_tools {
+            issues.push((
+                format!("{} does not support tool calls", model),
+                |r, _| {
+                    r.tools = None;
+                    r.tool_choice = None;
+                },
+            ));
+        }
+        
+        if self.response_format == Some(ResponseFormat::JsonObject) && !capabilities.supports_json_output {
+            issues.push((
+                format!("{} does not support JSON output mode", model),
+                |r, _| r.response_format = None,
+            ));
+        }
+        
+        if (self.logprobs == Some(true) || self.top_logprobs.is_some()) && !capabilities.supports_logprobs {
+            issues.push((
+                format!("{} does not support logprobs", model),
+                |r, _| {
+                    r.logprobs = None;
+                    r.top_logprobs = None;
+                },
+            ));
+        }
+        
+        issues
+    }
+    
+    /// Validate assistant prefix messages
+    fn validate_prefix(&self) -> Result<()> {
+        let last = self.messages.len().saturating_sub(1);
+        
+        for (i, msg) in self.messages.iter().enumerate() {
+            if msg.is_prefix() {
+                if msg.role != Role::Assistant {
+                    return Err(DeepSeekError::InvalidParameter(
+                        format!("Prefix message at index {} must be an assistant message", i)
+                    ));
+                }
+                if i != last {
+                    return Err(DeepSeekError::InvalidParameter(
+                        format!("Prefix message at index {} must be the last message", i)
+                    ));
+                }
+            } else if msg.reasoning_content.is_some() {
+                return Err(DeepSeekError::InvalidParameter(
+                    format!("Message at index {} has reasoning_content but is not a prefix message", i)
+                ));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Validate tool definitions and the tool choice
+    fn validate_tools(&self) -> Result<()> {
+        let tools = self.tools.as_deref().unwrap_or_default();
+        
+        if tools.len() > MAX_TOOLS {
+            return Err(DeepSeekError::InvalidParameter(
+                format!("At most {} tools are allowed, got {}", MAX_TOOLS, tools.len())
+            ));
+        }
+        
+        for (i, tool) in tools.iter().enumerate() {
+            tool.validate()?;
+            if tools[..i].iter().any(|t| t.function.name == tool.function.name) {
+                return Err(DeepSeekError::InvalidParameter(
+                    format!("Duplicate tool name '{}'", tool.function.name)
+                ));
+            }
+        }
+        
+        match &self.tool_choice {
+            Some(ToolChoice::Required) if tools.is_empty() => {
+                Err(DeepSeekError::InvalidParameter(
+                    "tool_choice 'required' needs at least one tool".to_string()
+                ))
+            }
+            Some(ToolChoice::Function(name)) if !tools.iter().any(|t| &t.function.name == name) => {
+                Err(DeepSeekError::InvalidParameter(
+                    format!("tool_choice names unknown function '{}'", name)
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+    
+    /// Check that tool messages answer the tool calls of the preceding
+    /// assistant message
+    fn validate_tool_messages(&self) -> Result<()> {
+        let mut pending: Vec<&str> = Vec::new();
+        
+        for (i, msg) in self.messages.iter().enumerate() {
+            if msg.role == Role::Tool {
+                let id = msg.tool_call_id.as_deref().ok_or_else(|| {
+                    DeepSeekError::InvalidParameter(
+                        format!("Tool message at index {} is missing tool_call_id", i)
+                    )
+                })?;
+                
+                if !pending.contains(&id) {
+                    return Err(DeepSeekError::InvalidParameter(format!(
+                        "Tool message at index {} answers unknown tool call '{}'",
+                        i, id
+                    )));
+                }
+                pending.retain(|p| *p != id);
+                continue;
+            }
+            
+            if !pending.is_empty() {
+                return Err(DeepSeekError::InvalidParameter(format!(
+                    "Tool calls {:?} must be answered before the message at index {}",
+                    pending, i
+                )));
+            }
+            
+            if msg.tool_call_id.is_some() {
+                return Err(DeepSeekError::InvalidParameter(
+                    format!("Only tool messages may set tool_call_id (index {})", i)
+                ));
+            }
+            
+            if let Some(calls) = &msg.tool_calls {
+                if msg.role != Role::Assistant {
+                    return Err(DeepSeekError::InvalidParameter(
+                        format!("Only assistant messages may carry tool_calls (index {})", i)
+                    ));
+                }
+                pending = calls.iter().map(|c| c.id.as_str()).collect();
+            }
+        }
+        
         Ok(())
     }
 }
@@ -415,6 +914,22 @@ mod tests {
         assert_eq!(Model::Chat.as_str(), "deepseek-chat");
         assert_eq!(Model::Reasoner.as_str(), "deepseek-reasoner");
         assert_eq!(Model::Coder.as_str(), "deepseek-coder");
+        assert_eq!(Model::Custom("deepseek-v4".to_string()).as_str(), "deepseek-v4");
+    }
+    
+    #[test]
+    fn test_model_custom_round_trip() {
+        for model in [Model::Chat, Model::Reasoner, Model::Coder, Model::from_id("deepseek-v4")] {
+            let json = serde_json::to_value(&model).unwrap();
+            assert_eq!(json, serde_json::json!(model.to_string()));
+            assert_eq!(serde_json::from_value::<Model>(json).unwrap(), model);
+        }
+        
+        let custom: Model = serde_json::from_str("\"deepseek-v4\"").unwrap();
+        assert_eq!(custom, Model::Custom("deepseek-v4".to_string()));
+        assert_eq!(custom.to_string(), "deepseek-v4");
+        assert_eq!("deepseek-reasoner".parse::<Model>().unwrap(), Model::Reasoner);
+        assert_eq!(Model::from("deepseek-coder"), Model::Coder);
     }
     
     #[test]
@@ -479,4 +994,187 @@ mod tests {
             .with_frequency_penalty(3.0);
         assert!(invalid_freq.validate().is_err());
     }
+    
+    fn weather_call(id: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            r#type: "function".to_string(),
+            function: crate::models::response::FunctionCall {
+                name: "get_weather".to_string(),
+                arguments: r#"{"city":"Paris"}"#.to_string(),
+            },
+        }
+    }
+    
+    #[test]
+    fn test_tool_request_validation() {
+        use crate::models::tool::FunctionDefinition;
+        
+        let tool = Tool::function(FunctionDefinition::new("get_weather"));
+        let base = ChatCompletionRequest::from_user_message("Weather?").add_tool(tool.clone());
+        assert!(base.clone().with_tool_choice(ToolChoice::Auto).validate().is_ok());
+        assert!(base.clone().with_tool_choice(ToolChoice::function("get_weather")).validate().is_ok());
+        assert!(base.clone().with_tool_choice(ToolChoice::function("other")).validate().is_err());
+        assert!(base.clone().add_tool(tool).validate().is_err());
+        
+        let no_tools = ChatCompletionRequest::from_user_message("Hi")
+            .with_tool_choice(ToolChoice::Required);
+        assert!(no_tools.validate().is_err());
+    }
+    
+    #[test]
+    fn test_tool_message_validation() {
+        let answered = ChatCompletionRequest::new(vec![
+            Message::user("Weather in Paris and Rome?"),
+            Message::assistant_with_tool_calls("", vec![weather_call("a"), weather_call("b")]),
+            Message::tool("a", "21C"),
+            Message::tool("b", "25C"),
+            Message::user("Thanks"),
+        ]);
+        assert!(answered.validate().is_ok());
+        
+        let unanswered = ChatCompletionRequest::new(vec![
+            Message::user("Weather?"),
+            Message::assistant_with_tool_calls("", vec![weather_call("a")]),
+            Message::user("Hello?"),
+        ]);
+        assert!(unanswered.validate().is_err());
+        
+        let unknown_id = ChatCompletionRequest::new(vec![
+            Message::user("Weather?"),
+            Message::assistant_with_tool_calls("", vec![weather_call("a")]),
+            Message::tool("z", "21C"),
+        ]);
+        assert!(unknown_id.validate().is_err());
+        
+        let mut missing_id = Message::tool("a", "21C");
+        missing_id.tool_call_id = None;
+        let missing = ChatCompletionRequest::new(vec![Message::user("Hi"), missing_id]);
+        assert!(missing.validate().is_err());
+    }
+    
+    #[test]
+    fn test_json_mode_validation() {
+        let without_mention = ChatCompletionRequest::from_user_message("List three colors")
+            .with_response_format(ResponseFormat::JsonObject);
+        assert!(without_mention.validate().is_err());
+        
+        let with_mention = ChatCompletionRequest::new(vec![
+            Message::system("Reply in JSON with a `colors` array"),
+            Message::user("List three colors"),
+        ])
+        .with_response_format(ResponseFormat::JsonObject);
+        assert!(with_mention.validate().is_ok());
+        
+        let json = serde_json::to_value(&with_mention).unwrap();
+        assert_eq!(json["response_format"], serde_json::json!({"type": "json_object"}));
+    }
+    
+    #[test]
+    fn test_logprobs_validation() {
+        let request = ChatCompletionRequest::from_user_message("Hi").with_top_logprobs(5);
+        assert_eq!(request.logprobs, Some(true));
+        assert!(request.validate().is_ok());
+        
+        assert!(ChatCompletionRequest::from_user_message("Hi")
+            .with_top_logprobs(21)
+            .validate()
+            .is_err());
+        
+        let mut without_logprobs = ChatCompletionRequest::from_user_message("Hi");
+        without_logprobs.top_logprobs = Some(2);
+        assert!(without_logprobs.validate().is_err());
+        
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["logprobs"], serde_json::json!(true));
+        assert_eq!(json["top_logprobs"], serde_json::json!(5));
+    }
+    
+    #[test]
+    fn test_capability_validation() {
+        let request = ChatCompletionRequest::from_user_message("Hi")
+            .with_model(Model::Reasoner)
+            .with_temperature(Temperature::medium())
+            .with_top_p(0.9)
+            .with_max_tokens(100_000)
+            .with_logprobs(true);
+        assert!(request.validate().is_err());
+        assert!(request.validate_with(&ModelCapabilities::unknown()).is_ok());
+        
+        let mut lenient = request.clone();
+        let stripped = lenient.strip_unsupported(&Model::Reasoner.capabilities());
+        assert_eq!(stripped.len(), 4);
+        assert!(stripped[0].contains("temperature"));
+        assert_eq!(lenient.temperature, None);
+        assert_eq!(lenient.top_p, None);
+        assert_eq!(lenient.logprobs, None);
+        assert_eq!(lenient.max_tokens, Some(65_536));
+        assert!(lenient.validate().is_ok());
+        
+        let chat = request.with_model(Model::Chat).with_max_tokens(1000);
+        assert!(chat.validate().is_ok());
+    }
+    
+    #[test]
+    fn test_prefix_validation() {
+        let request = ChatCompletionRequest::new(vec![
+            Message::user("Write a hello world program"),
+            Message::assistant_prefix("```rust\n"),
+        ]);
+        assert!(request.has_prefix());
+        assert!(request.validate().is_ok());
+        
+        let json = serde_json::to_value(&request.messages[1]).unwrap();
+        assert_eq!(json, serde_json::json!({"role": "assistant", "content": "```rust\n", "prefix": true}));
+        
+        let not_last = ChatCompletionRequest::new(vec![
+            Message::user("Hi"),
+            Message::assistant_prefix("Hello"),
+            Message::user("Again"),
+        ]);
+        assert!(!not_last.has_prefix());
+        assert!(not_last.validate().is_err());
+        
+        let mut wrong_role = Message::user("Hi");
+        wrong_role.prefix = Some(true);
+        assert!(ChatCompletionRequest::new(vec![wrong_role]).validate().is_err());
+        
+        let reasoning_prefix = ChatCompletionRequest::new(vec![
+            Message::user("What is 2 + 2?"),
+            Message::assistant_prefix("").with_reasoning_content("Adding two and two"),
+        ]);
+        assert!(reasoning_prefix.validate().is_ok());
+        
+        let stray_reasoning = ChatCompletionRequest::new(vec![
+            Message::user("Hi"),
+            Message::assistant("Hello").with_reasoning_content("Greeting"),
+            Message::user("Again"),
+        ]);
+        assert!(stray_reasoning.validate().is_err());
+    }
+    
+    #[test]
+    fn test_strip_history_reasoning() {
+        let mut request = ChatCompletionRequest::new(vec![
+            Message::user("Hi"),
+            Message::assistant("Hello").with_reasoning_content("Greeting"),
+            Message::user("Continue"),
+            Message::assistant_prefix("").with_reasoning_content("Thinking"),
+        ]);
+        
+        assert_eq!(request.strip_history_reasoning(), 1);
+        assert!(request.messages[1].reasoning_content.is_none());
+        assert_eq!(request.messages[3].reasoning_content.as_deref(), Some("Thinking"));
+        assert!(request.validate().is_ok());
+        assert_eq!(request.strip_history_reasoning(), 0);
+    }
+    
+    #[test]
+    fn test_tool_message_serialization() {
+        let json = serde_json::to_value(Message::tool("call_1", "ok")).unwrap();
+        assert_eq!(json, serde_json::json!({"role": "tool", "content": "ok", "tool_call_id": "call_1"}));
+        
+        let json = serde_json::to_value(Message::user("Hi")).unwrap();
+        assert_eq!(json, serde_json::json!({"role": "user", "content": "Hi"}));
+    }
 }
\ No newline at end of file
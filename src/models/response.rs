@@ -1,9 +1,13 @@
 //! Response models for DeepSeek API
 
+use crate::error::{DeepSeekError, Result};
+use crate::models::request::{Message, Model, Role};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Chat completion response from the API
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct ChatCompletionResponse {
     /// Unique identifier for the completion
     pub id: String,
@@ -27,6 +31,10 @@ pub struct ChatCompletionResponse {
     /// System fingerprint for the response
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system_fingerprint: Option<String>,
+    
+    /// Whether the response was served from the response cache
+    #[serde(skip)]
+    pub cached: bool,
 }
 
 impl ChatCompletionResponse {
@@ -56,10 +64,70 @@ impl ChatCompletionResponse {
     pub fn total_tokens(&self) -> Option<u32> {
         self.usage.as_ref().map(|u| u.total_tokens)
     }
+    
+    /// Get the first choice's log probabilities if they were requested
+    pub fn logprobs(&self) -> Option<&LogProbs> {
+        self.choices
+            .first()
+            .and_then(|choice| choice.logprobs.as_ref())
+    }
+    
+    /// Deserialize the first choice's content as JSON
+    /// 
+    /// Markdown code fences around the JSON are stripped first.
+    /// 
+    /// # Errors
+    /// Returns `EmptyResponse` if there is no content, or `JsonError` if it
+    /// does not deserialize into `T`
+    pub fn parse_json<T: DeserializeOwned>(&self) -> Result<T> {
+        let content = self.get_content().ok_or(DeepSeekError::EmptyResponse)?;
+        Ok(serde_json::from_str(strip_code_fences(content))?)
+    }
+    
+    /// Convert the first choice into a message for the conversation history
+    /// 
+    /// Reasoning content is dropped; see [`ResponseMessage::to_message`].
+    /// 
+    /// # Errors
+    /// Returns `EmptyResponse` if the response has no choices
+    pub fn into_message(self) -> Result<Message> {
+        self.into_message_with(ReasoningPolicy::Drop)
+    }
+    
+    /// Convert the first choice into a message using a reasoning policy
+    /// 
+    /// # Errors
+    /// Returns `EmptyResponse` if the response has no choices
+    pub fn into_message_with(self, policy: ReasoningPolicy) -> Result<Message> {
+        self.choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.into_message_with(policy))
+            .ok_or(DeepSeekError::EmptyResponse)
+    }
+}
+
+/// Remove a surrounding markdown code fence (e.g. "```json ... ```")
+pub fn strip_code_fences(text: &str) -> &str {
+    let trimmed = text.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    
+    // Skip the language tag on the opening fence line
+    let body = match rest.find('\n') {
+        Some(newline) => &rest[newline + 1..],
+        None => rest,
+    };
+    
+    body.trim_end()
+        .strip_suffix("```")
+        .unwrap_or(body)
+        .trim()
 }
 
 /// A choice in the completion response
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Choice {
     /// The index of this choice
     pub index: u32,
@@ -72,12 +140,125 @@ pub struct Choice {
     pub finish_reason: Option<String>,
     
     /// Log probabilities (if requested)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub logprobs: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<LogProbs>,
+}
+
+/// Log probability information for a choice
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct LogProbs {
+    /// Log probabilities of the content tokens, in order
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub content: Vec<TokenLogProb>,
+}
+
+impl LogProbs {
+    /// Sum of the token log probabilities
+    pub fn total_logprob(&self) -> f64 {
+        self.content.iter().map(|t| t.logprob).sum()
+    }
+    
+    /// Joint probability of the whole token sequence
+    pub fn sequence_probability(&self) -> f64 {
+        self.total_logprob().exp()
+    }
+    
+    /// Mean token log probability, or `None` with no tokens
+    pub fn mean_logprob(&self) -> Option<f64> {
+        if self.content.is_empty() {
+            None
+        } else {
+            Some(self.total_logprob() / self.content.len() as f64)
+        }
+    }
+    
+    /// Perplexity of the sequence (`exp` of the negated mean log probability)
+    pub fn perplexity(&self) -> Option<f64> {
+        self.mean_logprob().map(|mean| (-mean).exp())
+    }
+    
+    /// Probability the model assigned to each sampled token, in order
+    pub fn confidences(&self) -> Vec<f64> {
+        self.content.iter().map(TokenLogProb::probability).collect()
+    }
+    
+    /// The token the model was least confident about
+    pub fn least_confident(&self) -> Option<&TokenLogProb> {
+        self.content
+            .iter()
+            .min_by(|a, b| a.logprob.total_cmp(&b.logprob))
+    }
+}
+
+/// Log probability of a single output token
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TokenLogProb {
+    /// The token text
+    pub token: String,
+    
+    /// Log probability of the token
+    pub logprob: f64,
+    
+    /// UTF-8 bytes of the token (tokens may split multi-byte characters)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<Vec<u8>>,
+    
+    /// Most likely alternatives at this position (if `top_logprobs` was set)
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub top_logprobs: Vec<TopLogProb>,
+}
+
+impl TokenLogProb {
+    /// Probability of the token (between 0.0 and 1.0)
+    pub fn probability(&self) -> f64 {
+        self.logprob.exp()
+    }
+    
+    /// Probability margin over the most likely different alternative
+    /// 
+    /// Returns `None` when no other alternative was returned.
+    pub fn margin(&self) -> Option<f64> {
+        self.top_logprobs
+            .iter()
+            .filter(|alt| alt.token != self.token)
+            .map(TopLogProb::probability)
+            .max_by(f64::total_cmp)
+            .map(|runner_up| self.probability() - runner_up)
+    }
+}
+
+/// One of the most likely alternatives for a token position
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TopLogProb {
+    /// The token text
+    pub token: String,
+    
+    /// Log probability of the token
+    pub logprob: f64,
+    
+    /// UTF-8 bytes of the token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<Vec<u8>>,
+}
+
+impl TopLogProb {
+    /// Probability of the token (between 0.0 and 1.0)
+    pub fn probability(&self) -> f64 {
+        self.logprob.exp()
+    }
+}
+
+/// Deserialize `null` as the type's default value
+fn null_as_default<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
 }
 
 /// Response message from the assistant
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct ResponseMessage {
     /// The role of the message (usually "assistant")
     pub role: String,
@@ -116,10 +297,67 @@ impl ResponseMessage {
         let reasoning_len = self.reasoning_content.as_ref().map(|s| s.len()).unwrap_or(0);
         content_len + reasoning_len
     }
+    
+    /// Convert into a message for the conversation history, dropping the
+    /// reasoning content
+    /// 
+    /// The API rejects requests that send reasoning back in earlier
+    /// assistant turns, so this is the right form for multi-turn chats.
+    pub fn to_message(&self) -> Message {
+        self.clone().into_message_with(ReasoningPolicy::Drop)
+    }
+    
+    /// Convert into a message for the conversation history using a
+    /// reasoning policy
+    pub fn into_message_with(self, policy: ReasoningPolicy) -> Message {
+        let role = match self.role.as_str() {
+            "system" => Role::System,
+            "user" => Role::User,
+            "tool" => Role::Tool,
+            _ => Role::Assistant,
+        };
+        let reasoning_content = match policy {
+            ReasoningPolicy::Drop => None,
+            ReasoningPolicy::Keep => self.reasoning_content.filter(|r| !r.is_empty()),
+        };
+        
+        Message {
+            tool_calls: self.tool_calls.filter(|calls| !calls.is_empty()),
+            reasoning_content,
+            ..Message::new(role, self.content.unwrap_or_default())
+        }
+    }
+}
+
+impl From<ResponseMessage> for Message {
+    fn from(message: ResponseMessage) -> Self {
+        message.into_message_with(ReasoningPolicy::Drop)
+    }
+}
+
+impl From<&ResponseMessage> for Message {
+    fn from(message: &ResponseMessage) -> Self {
+        message.to_message()
+    }
+}
+
+/// What to do with reasoning content when a response becomes history
+/// 
+/// Kept reasoning is stored in [`Message::reasoning_content`] for auditing
+/// only: the client removes it from every non-prefix message before a
+/// request is sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasoningPolicy {
+    /// Discard the reasoning
+    #[default]
+    Drop,
+    /// Keep the reasoning on the message
+    Keep,
 }
 
 /// Function call information
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct FunctionCall {
     /// The name of the function to call
     pub name: String,
@@ -128,8 +366,25 @@ pub struct FunctionCall {
     pub arguments: String,
 }
 
+impl FunctionCall {
+    /// Deserialize the arguments into a typed value
+    /// 
+    /// # Errors
+    /// Returns `ToolError` naming the function if the arguments are not
+    /// valid JSON or do not match `T`
+    pub fn parse_arguments<T: DeserializeOwned>(&self) -> Result<T> {
+        let arguments = if self.arguments.trim().is_empty() { "{}" } else { &self.arguments };
+        serde_json::from_str(arguments).map_err(|e| {
+            DeepSeekError::ToolError(format!(
+                "Invalid arguments for function '{}': {}",
+                self.name, e
+            ))
+        })
+    }
+}
+
 /// Tool call information
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ToolCall {
     /// Unique identifier for the tool call
     pub id: String,
@@ -142,7 +397,7 @@ pub struct ToolCall {
 }
 
 /// Token usage information
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 pub struct Usage {
     /// Number of tokens in the prompt
     pub prompt_tokens: u32,
@@ -169,6 +424,7 @@ pub struct Usage {
 impl Usage {
     /// Calculate the total cost (simplified example)
     /// Note: You should adjust these rates based on actual pricing
+    #[deprecated(note = "uses placeholder rates; use `ModelPricing::cost` instead")]
     pub fn estimate_cost(&self) -> f64 {
         const PROMPT_RATE: f64 = 0.0001;  // per token
         const COMPLETION_RATE: f64 = 0.0002;  // per token
@@ -180,15 +436,66 @@ impl Usage {
     }
 }
 
+impl std::ops::AddAssign<&Usage> for Usage {
+    fn add_assign(&mut self, other: &Usage) {
+        fn add(a: Option<u32>, b: Option<u32>) -> Option<u32> {
+            match (a, b) {
+                (None, None) => None,
+                (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+            }
+        }
+        
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+        self.reasoning_tokens = add(self.reasoning_tokens, other.reasoning_tokens);
+        self.prompt_cache_hit_tokens = add(self.prompt_cache_hit_tokens, other.prompt_cache_hit_tokens);
+        self.prompt_cache_miss_tokens = add(self.prompt_cache_miss_tokens, other.prompt_cache_miss_tokens);
+    }
+}
+
+/// A model available to the API key
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ModelInfo {
+    /// Model identifier (e.g. "deepseek-chat")
+    pub id: String,
+    
+    /// Object type (usually "model")
+    #[serde(default)]
+    pub object: String,
+    
+    /// Organization that owns the model
+    #[serde(default)]
+    pub owned_by: String,
+}
+
+impl ModelInfo {
+    /// The [`Model`] to use in requests for this model
+    pub fn model(&self) -> Model {
+        Model::from_id(self.id.clone())
+    }
+}
+
+/// Response of the list models endpoint
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ModelList {
+    /// Object type (usually "list")
+    #[serde(default)]
+    pub object: String,
+    
+    /// Available models
+    pub data: Vec<ModelInfo>,
+}
+
 /// API Error response
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct ApiErrorResponse {
     /// The error details
     pub error: ApiErrorDetail,
 }
 
 /// API Error details
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct ApiErrorDetail {
     /// Error message
     pub message: String,
@@ -207,7 +514,7 @@ pub struct ApiErrorDetail {
 }
 
 /// Streaming response chunk
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct StreamChunk {
     /// Unique identifier for the chunk
     pub id: String,
@@ -223,10 +530,18 @@ pub struct StreamChunk {
     
     /// Choices in this chunk
     pub choices: Vec<StreamChoice>,
+    
+    /// Token usage (sent with the final chunk)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    
+    /// System fingerprint for the response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_fingerprint: Option<String>,
 }
 
 /// A choice in a streaming response
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct StreamChoice {
     /// Index of the choice
     pub index: u32,
@@ -237,10 +552,14 @@ pub struct StreamChoice {
     /// Finish reason
     #[serde(skip_serializing_if = "Option::is_none")]
     pub finish_reason: Option<String>,
+    
+    /// Log probabilities of the tokens in this delta (if requested)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<LogProbs>,
 }
 
 /// Delta content in streaming responses
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct DeltaContent {
     /// Role (only in first chunk)
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -253,6 +572,223 @@ pub struct DeltaContent {
     /// Reasoning content delta
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reasoning_content: Option<String>,
+    
+    /// Tool call fragments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCallDelta>>,
+}
+
+/// Incremental tool call fragment in streaming responses
+///
+/// The first fragment for a call carries its `id`, `type` and function
+/// name; later fragments with the same `index` append to the arguments.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ToolCallDelta {
+    /// Position of the tool call in the message
+    #[serde(default)]
+    pub index: u32,
+    
+    /// Tool call identifier (first fragment only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    
+    /// Tool type (first fragment only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<String>,
+    
+    /// Function name and argument fragment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function: Option<FunctionCallDelta>,
+}
+
+/// Incremental function call fragment in streaming responses
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FunctionCallDelta {
+    /// Function name (first fragment only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    
+    /// Fragment of the JSON arguments string
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<String>,
+}
+
+/// Rebuilds a full [`ChatCompletionResponse`] from streamed chunks
+///
+/// Content and reasoning content are concatenated separately for every
+/// choice, tool call fragments are merged by index, and the final
+/// `finish_reason` and trailing usage are kept.
+///
+/// # Example
+/// ```
+/// use deepseek_rust::models::response::StreamAccumulator;
+///
+/// let mut accumulator = StreamAccumulator::new();
+/// // for chunk in stream { accumulator.push(&chunk); }
+/// assert!(accumulator.finish().is_err()); // nothing was pushed
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StreamAccumulator {
+    id: Option<String>,
+    created: u64,
+    model: String,
+    system_fingerprint: Option<String>,
+    usage: Option<Usage>,
+    choices: BTreeMap<u32, ChoiceAccumulator>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ChoiceAccumulator {
+    role: Option<String>,
+    content: Option<String>,
+    reasoning_content: Option<String>,
+    tool_calls: BTreeMap<u32, ToolCallAccumulator>,
+    logprobs: Option<LogProbs>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ToolCallAccumulator {
+    id: String,
+    r#type: Option<String>,
+    name: String,
+    arguments: String,
+}
+
+impl StreamAccumulator {
+    /// Create an empty accumulator
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Add a chunk to the accumulated response
+    pub fn push(&mut self, chunk: &StreamChunk) {
+        if self.id.is_none() {
+            self.id = Some(chunk.id.clone());
+            self.created = chunk.created;
+            self.model = chunk.model.clone();
+        }
+        if chunk.system_fingerprint.is_some() {
+            self.system_fingerprint = chunk.system_fingerprint.clone();
+        }
+        if chunk.usage.is_some() {
+            self.usage = chunk.usage.clone();
+        }
+        
+        for choice in &chunk.choices {
+            let state = self.choices.entry(choice.index).or_default();
+            let delta = &choice.delta;
+            
+            if delta.role.is_some() {
+                state.role = delta.role.clone();
+            }
+            if let Some(content) = &delta.content {
+                state.content.get_or_insert_with(String::new).push_str(content);
+            }
+            if let Some(reasoning) = &delta.reasoning_content {
+                state.reasoning_content.get_or_insert_with(String::new).push_str(reasoning);
+            }
+            for fragment in delta.tool_calls.iter().flatten() {
+                let call = state.tool_calls.entry(fragment.index).or_default();
+                if let Some(id) = &fragment.id {
+                    call.id = id.clone();
+                }
+                if fragment.r#type.is_some() {
+                    call.r#type = fragment.r#type.clone();
+                }
+                if let Some(function) = &fragment.function {
+                    if let Some(name) = &function.name {
+                        call.name.push_str(name);
+                    }
+                    if let Some(arguments) = &function.arguments {
+                        call.arguments.push_str(arguments);
+                    }
+                }
+            }
+            if let Some(logprobs) = &choice.logprobs {
+                state
+                    .logprobs
+                    .get_or_insert_with(LogProbs::default)
+                    .content
+                    .extend(logprobs.content.iter().cloned());
+            }
+            if choice.finish_reason.is_some() {
+                state.finish_reason = choice.finish_reason.clone();
+            }
+        }
+    }
+    
+    /// Usage reported so far (normally only present after the last chunk)
+    pub fn usage(&self) -> Option<&Usage> {
+        self.usage.as_ref()
+    }
+    
+    /// Content accumulated so far for a choice
+    pub fn content(&self, index: u32) -> Option<&str> {
+        self.choices.get(&index).and_then(|c| c.content.as_deref())
+    }
+    
+    /// Reasoning content accumulated so far for a choice
+    pub fn reasoning_content(&self, index: u32) -> Option<&str> {
+        self.choices.get(&index).and_then(|c| c.reasoning_content.as_deref())
+    }
+    
+    /// Build the final response
+    ///
+    /// # Errors
+    /// Returns `EmptyResponse` if no chunk was pushed
+    pub fn finish(self) -> Result<ChatCompletionResponse> {
+        let id = self.id.ok_or(DeepSeekError::EmptyResponse)?;
+        
+        let choices = self
+            .choices
+            .into_iter()
+            .map(|(index, state)| {
+                let tool_calls = if state.tool_calls.is_empty() {
+                    None
+                } else {
+                    Some(
+                        state
+                            .tool_calls
+                            .into_values()
+                            .map(|call| ToolCall {
+                                id: call.id,
+                                r#type: call.r#type.unwrap_or_else(|| "function".to_string()),
+                                function: FunctionCall {
+                                    name: call.name,
+                                    arguments: call.arguments,
+                                },
+                            })
+                            .collect(),
+                    )
+                };
+                
+                Choice {
+                    index,
+                    message: ResponseMessage {
+                        role: state.role.unwrap_or_else(|| "assistant".to_string()),
+                        content: state.content,
+                        reasoning_content: state.reasoning_content,
+                        function_call: None,
+                        tool_calls,
+                    },
+                    finish_reason: state.finish_reason,
+                    logprobs: state.logprobs,
+                }
+            })
+            .collect();
+        
+        Ok(ChatCompletionResponse {
+            id,
+            object: "chat.completion".to_string(),
+            created: self.created,
+            model: self.model,
+            choices,
+            usage: self.usage,
+            system_fingerprint: self.system_fingerprint,
+            cached: false,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -289,6 +825,7 @@ mod tests {
                 prompt_cache_miss_tokens: None,
             }),
             system_fingerprint: None,
+            cached: false,
         };
         
         assert_eq!(response.get_content(), Some("Hello!"));
@@ -309,10 +846,243 @@ mod tests {
         
         assert!(message.has_content());
         assert!(message.has_reasoning());
-        assert_eq!(message.total_length(), 28); // "Hello world!" (12) + "This is reasoning" (16)
+        assert_eq!(message.total_length(), 29); // "Hello world!" (12) + "This is reasoning" (17)
+    }
+    
+    #[test]
+    fn test_message_conversion() {
+        let message = ResponseMessage {
+            role: "assistant".to_string(),
+            content: Some("42".to_string()),
+            reasoning_content: Some("6 * 7".to_string()),
+            function_call: None,
+            tool_calls: None,
+        };
+        
+        let dropped = Message::from(&message);
+        assert_eq!(dropped, Message::assistant("42"));
+        
+        let kept = message.clone().into_message_with(ReasoningPolicy::Keep);
+        assert_eq!(kept.reasoning_content.as_deref(), Some("6 * 7"));
+        assert!(!kept.is_prefix());
+        
+        let response = ChatCompletionResponse {
+            id: "test".to_string(),
+            object: "chat.completion".to_string(),
+            created: 0,
+            model: "deepseek-reasoner".to_string(),
+            choices: vec![Choice {
+                index: 0,
+                message: ResponseMessage { content: None, ..message },
+                finish_reason: Some("stop".to_string()),
+                logprobs: None,
+            }],
+            usage: None,
+            system_fingerprint: None,
+            cached: false,
+        };
+        assert_eq!(response.clone().into_message().unwrap(), Message::assistant(""));
+        
+        let empty = ChatCompletionResponse { choices: Vec::new(), ..response };
+        assert!(matches!(empty.into_message(), Err(DeepSeekError::EmptyResponse)));
     }
     
     #[test]
+    fn test_stream_accumulator() {
+        let chunks: Vec<StreamChunk> = [
+            r#"{"id":"c1","object":"chat.completion.chunk","created":7,"model":"deepseek-reasoner","choices":[{"index":0,"delta":{"role":"assistant","reasoning_content":"Think"}},{"index":1,"delta":{"role":"assistant","content":"B"}}]}"#,
+            r#"{"id":"c1","object":"chat.completion.chunk","created":7,"model":"deepseek-reasoner","choices":[{"index":0,"delta":{"reasoning_content":"ing","content":"A"}},{"index":1,"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"get_weather","arguments":"{\"city\":"}}]}}]}"#,
+            r#"{"id":"c1","object":"chat.completion.chunk","created":7,"model":"deepseek-reasoner","choices":[{"index":0,"delta":{"content":"nswer"},"finish_reason":"stop"},{"index":1,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"Paris\"}"}}]},"finish_reason":"tool_calls"}]}"#,
+            r#"{"id":"c1","object":"chat.completion.chunk","created":7,"model":"deepseek-reasoner","choices":[],"usage":{"prompt_tokens":3,"completion_tokens":4,"total_tokens":7}}"#,
+        ]
+        .iter()
+        .map(|json| serde_json::from_str(json).unwrap())
+        .collect();
+        
+        let mut accumulator = StreamAccumulator::new();
+        for chunk in &chunks {
+            accumulator.push(chunk);
+        }
+        assert_eq!(accumulator.content(0), Some("Answer"));
+        assert_eq!(accumulator.usage().map(|u| u.total_tokens), Some(7));
+        
+        let response = accumulator.finish().unwrap();
+        assert_eq!(response.object, "chat.completion");
+        assert_eq!(response.model, "deepseek-reasoner");
+        assert_eq!(response.choices.len(), 2);
+        
+        let first = &response.choices[0];
+        assert_eq!(first.message.content.as_deref(), Some("Answer"));
+        assert_eq!(first.message.reasoning_content.as_deref(), Some("Thinking"));
+        assert_eq!(first.finish_reason.as_deref(), Some("stop"));
+        
+        let second = &response.choices[1];
+        assert_eq!(second.message.content.as_deref(), Some("B"));
+        let calls = second.message.tool_calls.as_ref().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].id, "call_1");
+        assert_eq!(calls[0].function.name, "get_weather");
+        assert_eq!(calls[0].function.arguments, r#"{"city":"Paris"}"#);
+        assert_eq!(second.finish_reason.as_deref(), Some("tool_calls"));
+        assert_eq!(response.total_tokens(), Some(7));
+    }
+    
+    #[test]
+    fn test_strip_code_fences() {
+        assert_eq!(strip_code_fences("{\"a\": 1}"), "{\"a\": 1}");
+        assert_eq!(strip_code_fences("```json\n{\"a\": 1}\n```"), "{\"a\": 1}");
+        assert_eq!(strip_code_fences("  ```\n[1, 2]\n```  "), "[1, 2]");
+        assert_eq!(strip_code_fences("```json\n{\"a\": 1}"), "{\"a\": 1}");
+    }
+    
+    #[test]
+    fn test_parse_json() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Colors {
+            colors: Vec<String>,
+        }
+        
+        let mut response: ChatCompletionResponse = serde_json::from_value(serde_json::json!({
+            "id": "x",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "deepseek-chat",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "```json\n{\"colors\": [\"red\"]}\n```"},
+                "finish_reason": "stop"
+            }]
+        }))
+        .unwrap();
+        
+        assert_eq!(
+            response.parse_json::<Colors>().unwrap(),
+            Colors { colors: vec!["red".to_string()] }
+        );
+        
+        response.choices[0].message.content = Some("not json".to_string());
+        assert!(matches!(response.parse_json::<Colors>(), Err(DeepSeekError::JsonError(_))));
+        
+        response.choices[0].message.content = None;
+        assert!(matches!(response.parse_json::<Colors>(), Err(DeepSeekError::EmptyResponse)));
+    }
+    
+    #[test]
+    fn test_function_call_parse_arguments() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Args {
+            city: String,
+        }
+        
+        let call = FunctionCall {
+            name: "get_weather".to_string(),
+            arguments: r#"{"city":"Paris"}"#.to_string(),
+        };
+        assert_eq!(call.parse_arguments::<Args>().unwrap(), Args { city: "Paris".to_string() });
+        
+        let bad = FunctionCall {
+            name: "get_weather".to_string(),
+            arguments: r#"{"town":"Paris"}"#.to_string(),
+        };
+        match bad.parse_arguments::<Args>() {
+            Err(DeepSeekError::ToolError(message)) => {
+                assert!(message.contains("get_weather"));
+                assert!(message.contains("missing field `city`"));
+            }
+            other => panic!("Expected ToolError, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_logprobs() {
+        let choice: Choice = serde_json::from_value(serde_json::json!({
+            "index": 0,
+            "message": {"role": "assistant", "content": "Yes."},
+            "finish_reason": "stop",
+            "logprobs": {
+                "content": [
+                    {
+                        "token": "Yes",
+                        "logprob": -0.1,
+                        "bytes": [89, 101, 115],
+                        "top_logprobs": [
+                            {"token": "Yes", "logprob": -0.1, "bytes": [89, 101, 115]},
+                            {"token": "No", "logprob": -2.5, "bytes": null}
+                        ]
+                    },
+                    {"token": ".", "logprob": 0.0, "bytes": null, "top_logprobs": null}
+                ]
+            }
+        }))
+        .unwrap();
+        
+        let logprobs = choice.logprobs.as_ref().unwrap();
+        assert_eq!(logprobs.content.len(), 2);
+        assert_eq!(logprobs.content[0].bytes.as_deref(), Some(&b"Yes"[..]));
+        assert!(logprobs.content[1].top_logprobs.is_empty());
+        
+        assert!((logprobs.total_logprob() + 0.1).abs() < 1e-9);
+        assert!((logprobs.sequence_probability() - (-0.1f64).exp()).abs() < 1e-9);
+        assert!((logprobs.mean_logprob().unwrap() + 0.05).abs() < 1e-9);
+        assert_eq!(logprobs.confidences()[1], 1.0);
+        assert_eq!(logprobs.least_confident().unwrap().token, "Yes");
+        
+        let margin = logprobs.content[0].margin().unwrap();
+        assert!((margin - ((-0.1f64).exp() - (-2.5f64).exp())).abs() < 1e-9);
+        assert_eq!(logprobs.content[1].margin(), None);
+        
+        assert_eq!(LogProbs::default().mean_logprob(), None);
+        let empty: LogProbs = serde_json::from_str(r#"{"content": null}"#).unwrap();
+        assert!(empty.content.is_empty());
+    }
+    
+    #[test]
+    fn test_stream_accumulator_logprobs() {
+        let chunks: Vec<StreamChunk> = [
+            r#"{"id":"c1","object":"chat.completion.chunk","created":7,"model":"deepseek-chat","choices":[{"index":0,"delta":{"content":"Hi"},"logprobs":{"content":[{"token":"Hi","logprob":-0.5,"bytes":[72,105],"top_logprobs":[]}]}}]}"#,
+            r#"{"id":"c1","object":"chat.completion.chunk","created":7,"model":"deepseek-chat","choices":[{"index":0,"delta":{"content":"!"},"logprobs":{"content":[{"token":"!","logprob":-0.25,"bytes":[33],"top_logprobs":[]}]},"finish_reason":"stop"}]}"#,
+        ]
+        .iter()
+        .map(|json| serde_json::from_str(json).unwrap())
+        .collect();
+        
+        let mut accumulator = StreamAccumulator::new();
+        for chunk in &chunks {
+            accumulator.push(chunk);
+        }
+        
+        let response = accumulator.finish().unwrap();
+        let logprobs = response.logprobs().unwrap();
+        let tokens: Vec<_> = logprobs.content.iter().map(|t| t.token.as_str()).collect();
+        assert_eq!(tokens, vec!["Hi", "!"]);
+        assert!((logprobs.total_logprob() + 0.75).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn test_model_list() {
+        let list: ModelList = serde_json::from_value(serde_json::json!({
+            "object": "list",
+            "data": [
+                {"id": "deepseek-chat", "object": "model", "owned_by": "deepseek"},
+                {"id": "deepseek-v4", "object": "model", "owned_by": "deepseek"}
+            ]
+        }))
+        .unwrap();
+        
+        let models: Vec<Model> = list.data.iter().map(ModelInfo::model).collect();
+        assert_eq!(models, vec![Model::Chat, Model::Custom("deepseek-v4".to_string())]);
+    }
+    
+    #[test]
+    fn test_stream_accumulator_empty() {
+        assert!(matches!(
+            StreamAccumulator::new().finish(),
+            Err(DeepSeekError::EmptyResponse)
+        ));
+    }
+    
+    #[test]
+    #[allow(deprecated)]
     fn test_usage_cost_estimation() {
         let usage = Usage {
             prompt_tokens: 100,
@@ -326,4 +1096,31 @@ mod tests {
         let cost = usage.estimate_cost();
         assert!((cost - 0.02).abs() < 0.0001); // 100 * 0.0001 + 50 * 0.0002 = 0.02
     }
+    
+    #[test]
+    fn test_usage_add_assign() {
+        let mut total = Usage::default();
+        total += &Usage {
+            prompt_tokens: 10,
+            completion_tokens: 5,
+            total_tokens: 15,
+            reasoning_tokens: Some(3),
+            prompt_cache_hit_tokens: None,
+            prompt_cache_miss_tokens: Some(10),
+        };
+        total += &Usage {
+            prompt_tokens: 20,
+            completion_tokens: 5,
+            total_tokens: 25,
+            reasoning_tokens: None,
+            prompt_cache_hit_tokens: Some(8),
+            prompt_cache_miss_tokens: Some(12),
+        };
+        
+        assert_eq!(total.prompt_tokens, 30);
+        assert_eq!(total.total_tokens, 40);
+        assert_eq!(total.reasoning_tokens, Some(3));
+        assert_eq!(total.prompt_cache_hit_tokens, Some(8));
+        assert_eq!(total.prompt_cache_miss_tokens, Some(22));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,377 @@
+//! Tool (function calling) definitions for DeepSeek API requests
+
+use crate::error::{DeepSeekError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Maximum number of tools accepted in a single request
+pub const MAX_TOOLS: usize = 128;
+
+/// Maximum length of a function name
+pub const MAX_FUNCTION_NAME_LEN: usize = 64;
+
+/// A tool the model may call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tool {
+    /// The type of tool (currently always "function")
+    pub r#type: String,
+
+    /// The function definition
+    pub function: FunctionDefinition,
+}
+
+impl Tool {
+    /// Create a function tool
+    ///
+    /// # Example
+    /// ```
+    /// use deepseek_rust::models::tool::{FunctionDefinition, Tool};
+    /// use serde_json::json;
+    ///
+    /// let tool = Tool::function(
+    ///     FunctionDefinition::new("get_weather")
+    ///         .with_description("Get the current weather for a city")
+    ///         .with_parameters(json!({
+    ///             "type": "object",
+    ///             "properties": {"city": {"type": "string"}},
+    ///             "required": ["city"]
+    ///         })),
+    /// );
+    /// ```
+    pub fn function(function: FunctionDefinition) -> Self {
+        Self {
+            r#type: "function".to_string(),
+            function,
+        }
+    }
+
+    /// Validate the tool definition
+    pub fn validate(&self) -> Result<()> {
+        if self.r#type != "function" {
+            return Err(DeepSeekError::InvalidParameter(format!(
+                "Unsupported tool type '{}', only 'function' is supported",
+                self.r#type
+            )));
+        }
+        self.function.validate()
+    }
+}
+
+/// A function the model may call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionDefinition {
+    /// Function name (a-z, A-Z, 0-9, underscores and dashes, max 64 chars)
+    pub name: String,
+
+    /// Description used by the model to decide when to call the function
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Parameters accepted by the function, as a JSON schema object
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<serde_json::Value>,
+}
+
+impl FunctionDefinition {
+    /// Create a function definition with no parameters
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            description: None,
+            parameters: None,
+        }
+    }
+
+    /// Set the description
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Set the JSON schema for the parameters
+    pub fn with_parameters(mut self, parameters: serde_json::Value) -> Self {
+        self.parameters = Some(parameters);
+        self
+    }
+
+    /// Validate the function name and parameter schema
+    pub fn validate(&self) -> Result<()> {
+        validate_function_name(&self.name)?;
+
+        if let Some(parameters) = &self.parameters {
+            let is_object_schema = parameters
+                .as_object()
+                .map(|schema| {
+                    schema
+                        .get("type")
+                        .is_none_or(|t| t.as_str() == Some("object"))
+                })
+                .unwrap_or(false);
+
+            if !is_object_schema {
+                return Err(DeepSeekError::InvalidParameter(format!(
+                    "Parameters of function '{}' must be a JSON schema of type 'object'",
+                    self.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Check that a function name is accepted by the API
+pub fn validate_function_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_FUNCTION_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+
+    if !valid {
+        return Err(DeepSeekError::InvalidParameter(format!(
+            "Invalid function name '{}': use 1-{} characters from a-z, A-Z, 0-9, '_' and '-'",
+            name, MAX_FUNCTION_NAME_LEN
+        )));
+    }
+
+    Ok(())
+}
+
+/// A type whose JSON schema can describe a tool parameter
+///
+/// Implemented for primitives, strings, `Option`, `Vec`, maps and
+/// `serde_json::Value`. Structs get an implementation from
+/// `#[derive(DeepSeekTool)]` (with the `derive` feature).
+pub trait ToolParameter {
+    /// Whether a struct field of this type must be present
+    const REQUIRED: bool = true;
+
+    /// JSON schema describing the type
+    fn json_schema() -> serde_json::Value;
+}
+
+/// A Rust type that describes the arguments of a tool
+///
+/// Usually derived with `#[derive(DeepSeekTool)]` (with the `derive`
+/// feature), which builds the schema from the struct's fields and doc
+/// comments.
+pub trait DeepSeekTool: ToolParameter {
+    /// Function definition sent to the model
+    fn function_definition() -> FunctionDefinition;
+
+    /// Tool wrapping [`DeepSeekTool::function_definition`]
+    fn tool() -> Tool {
+        Tool::function(Self::function_definition())
+    }
+}
+
+macro_rules! impl_tool_parameter {
+    ($schema_type:literal => $($ty:ty),+) => {
+        $(
+            impl ToolParameter for $ty {
+                fn json_schema() -> serde_json::Value {
+                    serde_json::json!({ "type": $schema_type })
+                }
+            }
+        )+
+    };
+}
+
+impl_tool_parameter!("string" => String, char);
+impl_tool_parameter!("boolean" => bool);
+impl_tool_parameter!("integer" => i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+impl_tool_parameter!("number" => f32, f64);
+
+impl<T: ToolParameter> ToolParameter for Option<T> {
+    const REQUIRED: bool = false;
+
+    fn json_schema() -> serde_json::Value {
+        T::json_schema()
+    }
+}
+
+impl<T: ToolParameter> ToolParameter for Box<T> {
+    const REQUIRED: bool = T::REQUIRED;
+
+    fn json_schema() -> serde_json::Value {
+        T::json_schema()
+    }
+}
+
+impl<T: ToolParameter> ToolParameter for Vec<T> {
+    fn json_schema() -> serde_json::Value {
+        serde_json::json!({ "type": "array", "items": T::json_schema() })
+    }
+}
+
+impl<T: ToolParameter> ToolParameter for HashMap<String, T> {
+    fn json_schema() -> serde_json::Value {
+        serde_json::json!({ "type": "object", "additionalProperties": T::json_schema() })
+    }
+}
+
+impl<T: ToolParameter> ToolParameter for BTreeMap<String, T> {
+    fn json_schema() -> serde_json::Value {
+        serde_json::json!({ "type": "object", "additionalProperties": T::json_schema() })
+    }
+}
+
+impl ToolParameter for serde_json::Value {
+    fn json_schema() -> serde_json::Value {
+        serde_json::json!({})
+    }
+}
+
+/// Controls which (if any) tool the model calls
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolChoice {
+    /// Never call a tool
+    None,
+    /// Let the model decide
+    Auto,
+    /// Call at least one tool
+    Required,
+    /// Call the named function
+    Function(String),
+}
+
+impl ToolChoice {
+    /// Force a call to a specific function
+    pub fn function(name: impl Into<String>) -> Self {
+        Self::Function(name.into())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ToolChoiceRepr {
+    Mode(String),
+    Named {
+        r#type: String,
+        function: NamedFunction,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct NamedFunction {
+    name: String,
+}
+
+impl Serialize for ToolChoice {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let repr = match self {
+            ToolChoice::None => ToolChoiceRepr::Mode("none".to_string()),
+            ToolChoice::Auto => ToolChoiceRepr::Mode("auto".to_string()),
+            ToolChoice::Required => ToolChoiceRepr::Mode("required".to_string()),
+            ToolChoice::Function(name) => ToolChoiceRepr::Named {
+                r#type: "function".to_string(),
+                function: NamedFunction { name: name.clone() },
+            },
+        };
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ToolChoice {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match ToolChoiceRepr::deserialize(deserializer)? {
+            ToolChoiceRepr::Mode(mode) => match mode.as_str() {
+                "none" => Ok(ToolChoice::None),
+                "auto" => Ok(ToolChoice::Auto),
+                "required" => Ok(ToolChoice::Required),
+                other => Err(serde::de::Error::custom(format!(
+                    "unknown tool_choice '{}'",
+                    other
+                ))),
+            },
+            ToolChoiceRepr::Named { function, .. } => Ok(ToolChoice::Function(function.name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_tool_serialization() {
+        let tool = Tool::function(
+            FunctionDefinition::new("get_weather")
+                .with_description("Get weather")
+                .with_parameters(json!({"type": "object", "properties": {}})),
+        );
+
+        assert_eq!(
+            serde_json::to_value(&tool).unwrap(),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "get_weather",
+                    "description": "Get weather",
+                    "parameters": {"type": "object", "properties": {}}
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_tool_choice_round_trip() {
+        let cases = [
+            (ToolChoice::None, json!("none")),
+            (ToolChoice::Auto, json!("auto")),
+            (ToolChoice::Required, json!("required")),
+            (
+                ToolChoice::function("get_weather"),
+                json!({"type": "function", "function": {"name": "get_weather"}}),
+            ),
+        ];
+
+        for (choice, expected) in cases {
+            assert_eq!(serde_json::to_value(&choice).unwrap(), expected);
+            assert_eq!(serde_json::from_value::<ToolChoice>(expected).unwrap(), choice);
+        }
+
+        assert!(serde_json::from_value::<ToolChoice>(json!("sometimes")).is_err());
+    }
+
+    #[test]
+    fn test_tool_parameter_schemas() {
+        assert_eq!(String::json_schema(), json!({"type": "string"}));
+        assert_eq!(u32::json_schema(), json!({"type": "integer"}));
+        assert_eq!(<Option<f64>>::json_schema(), json!({"type": "number"}));
+        const { assert!(!<Option<f64> as ToolParameter>::REQUIRED) };
+        const { assert!(<bool as ToolParameter>::REQUIRED) };
+        assert_eq!(
+            <Vec<String>>::json_schema(),
+            json!({"type": "array", "items": {"type": "string"}})
+        );
+        assert_eq!(
+            <HashMap<String, i64>>::json_schema(),
+            json!({"type": "object", "additionalProperties": {"type": "integer"}})
+        );
+    }
+
+    #[test]
+    fn test_function_validation() {
+        assert!(FunctionDefinition::new("get_weather").validate().is_ok());
+        assert!(FunctionDefinition::new("").validate().is_err());
+        assert!(FunctionDefinition::new("has space").validate().is_err());
+        assert!(FunctionDefinition::new("a".repeat(65)).validate().is_err());
+        assert!(FunctionDefinition::new("f")
+            .with_parameters(json!({"type": "string"}))
+            .validate()
+            .is_err());
+        assert!(FunctionDefinition::new("f")
+            .with_parameters(json!([1, 2]))
+            .validate()
+            .is_err());
+    }
+}
@@ -0,0 +1,497 @@
+//! Saving conversations across process restarts
+//!
+//! A [`ConversationSnapshot`] holds everything needed to restore a
+//! [`Conversation`]: its messages, model, parameters, cumulative usage,
+//! metadata and timestamps. Snapshots are written as pretty JSON or as
+//! JSONL (a header line followed by one line per message) and carry a
+//! schema version so older files can be migrated on load.
+//!
+//! The context strategy and request timeout are not saved; set them again
+//! after restoring.
+//!
+//! # Example
+//! ```no_run
+//! use deepseek_rust::persistence::FileConversationStore;
+//! use deepseek_rust::{Conversation, DeepSeekClient, Result};
+//!
+//! # async fn run(client: DeepSeekClient) -> Result<()> {
+//! let store = FileConversationStore::new("sessions")?;
+//!
+//! let mut conversation = client.conversation();
+//! conversation.ask("Remember the number 42").await?;
+//! store.save(&conversation.snapshot())?;
+//!
+//! // After a restart
+//! let snapshot = store.load(conversation.id())?;
+//! let mut conversation = Conversation::restore(client, snapshot);
+//! conversation.ask("Which number did I mention?").await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Conversation`]: crate::Conversation
+
+use crate::error::{DeepSeekError, Result};
+use crate::models::request::{Message, Model, Role, Temperature};
+use crate::models::response::{ReasoningPolicy, Usage};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Current version of the snapshot schema
+///
+/// Version 0 is a plain serialized [`ChatCompletionRequest`]; its leading
+/// system message becomes the system prompt.
+///
+/// [`ChatCompletionRequest`]: crate::ChatCompletionRequest
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Saved state of a [`Conversation`](crate::Conversation)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversationSnapshot {
+    /// Schema version the snapshot was written with
+    pub version: u32,
+
+    /// Conversation identifier, also used as the file name
+    pub id: String,
+
+    /// The model used for replies
+    pub model: Model,
+
+    /// System prompt sent before the history
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+
+    /// The history, without the system prompt
+    #[serde(default)]
+    pub messages: Vec<Message>,
+
+    /// Temperature for replies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<Temperature>,
+
+    /// Maximum number of tokens per reply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+
+    /// Whether replies keep their reasoning in the history
+    #[serde(default)]
+    pub reasoning_policy: ReasoningPolicy,
+
+    /// Token usage summed over every request sent
+    #[serde(default)]
+    pub usage: Usage,
+
+    /// Application-defined metadata
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, Value>,
+
+    /// Unix timestamp of when the conversation was created
+    pub created_at: u64,
+
+    /// Unix timestamp of the last change to the conversation
+    pub updated_at: u64,
+}
+
+impl ConversationSnapshot {
+    /// Serialize as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Deserialize from JSON, migrating older schema versions
+    ///
+    /// # Errors
+    /// Returns `JsonError` for malformed input and `InvalidParameter` for
+    /// snapshots written by a newer version of the crate
+    pub fn from_json(json: &str) -> Result<Self> {
+        migrate(serde_json::from_str(json)?)
+    }
+
+    /// Serialize as JSONL: a header line, then one line per message
+    pub fn to_jsonl(&self) -> Result<String> {
+        let mut header = serde_json::to_value(self)?;
+        if let Some(fields) = header.as_object_mut() {
+            fields.remove("messages");
+        }
+
+        let mut jsonl = serde_json::to_string(&header)?;
+        jsonl.push('\n');
+        for message in &self.messages {
+            jsonl.push_str(&serde_json::to_string(message)?);
+            jsonl.push('\n');
+        }
+        Ok(jsonl)
+    }
+
+    /// Deserialize from JSONL, migrating older schema versions
+    ///
+    /// # Errors
+    /// Returns `JsonError` for malformed lines and `InvalidParameter` for
+    /// empty input or newer schema versions
+    pub fn from_jsonl(jsonl: &str) -> Result<Self> {
+        let mut lines = jsonl.lines().filter(|line| !line.trim().is_empty());
+        let header = lines
+            .next()
+            .ok_or_else(|| DeepSeekError::InvalidParameter("JSONL conversation is empty".to_string()))?;
+
+        let mut value: Value = serde_json::from_str(header)?;
+        let messages = lines
+            .map(serde_json::from_str)
+            .collect::<std::result::Result<Vec<Value>, _>>()?;
+        match value.as_object_mut() {
+            Some(fields) => fields.insert("messages".to_string(), Value::Array(messages)),
+            None => {
+                return Err(DeepSeekError::InvalidParameter(
+                    "JSONL conversation header must be an object".to_string(),
+                ))
+            }
+        };
+        migrate(value)
+    }
+}
+
+/// Bring a snapshot of any supported version to the current schema
+fn migrate(mut value: Value) -> Result<ConversationSnapshot> {
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > u64::from(SCHEMA_VERSION) {
+        return Err(DeepSeekError::InvalidParameter(format!(
+            "Conversation schema version {} is newer than the supported version {}",
+            version, SCHEMA_VERSION
+        )));
+    }
+
+    if version == 0 {
+        value = migrate_v0(value)?;
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Convert a serialized chat request into a version 1 snapshot
+fn migrate_v0(value: Value) -> Result<Value> {
+    let Value::Object(mut request) = value else {
+        return Err(DeepSeekError::InvalidParameter(
+            "Conversation snapshot must be a JSON object".to_string(),
+        ));
+    };
+
+    let mut messages: Vec<Message> = match request.remove("messages") {
+        Some(messages) => serde_json::from_value(messages)?,
+        None => Vec::new(),
+    };
+    let system_prompt = match messages.first() {
+        Some(first) if first.role == Role::System => Some(messages.remove(0).content),
+        _ => None,
+    };
+
+    let now = unix_now();
+    let mut snapshot = Map::new();
+    snapshot.insert("version".to_string(), SCHEMA_VERSION.into());
+    snapshot.insert("id".to_string(), new_id().into());
+    snapshot.insert(
+        "model".to_string(),
+        request.remove("model").unwrap_or_else(|| Model::default().as_str().into()),
+    );
+    snapshot.insert("system_prompt".to_string(), serde_json::to_value(system_prompt)?);
+    snapshot.insert("messages".to_string(), serde_json::to_value(messages)?);
+    for field in ["temperature", "max_tokens"] {
+        if let Some(value) = request.remove(field) {
+            snapshot.insert(field.to_string(), value);
+        }
+    }
+    snapshot.insert("created_at".to_string(), now.into());
+    snapshot.insert("updated_at".to_string(), now.into());
+    Ok(Value::Object(snapshot))
+}
+
+/// On-disk format used by [`FileConversationStore`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StoreFormat {
+    /// One pretty-printed JSON document per conversation (`<id>.json`)
+    #[default]
+    Json,
+    /// A header line and one line per message (`<id>.jsonl`)
+    Jsonl,
+}
+
+impl StoreFormat {
+    /// File extension for the format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            StoreFormat::Json => "json",
+            StoreFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// Stores conversation snapshots as files in a directory
+///
+/// Files are named after the conversation id. Both formats are read
+/// regardless of the format used for saving.
+#[derive(Debug, Clone)]
+pub struct FileConversationStore {
+    dir: PathBuf,
+    format: StoreFormat,
+}
+
+impl FileConversationStore {
+    /// Use `dir` for storage, creating it if needed
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            format: StoreFormat::default(),
+        })
+    }
+
+    /// Set the format used when saving
+    pub fn with_format(mut self, format: StoreFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// The storage directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Ids of the stored conversations, sorted
+    pub fn list(&self) -> Result<Vec<String>> {
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if Self::format_of(&path).is_some() {
+                if let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    ids.push(id.to_string());
+                }
+            }
+        }
+        ids.sort();
+        ids.dedup();
+        Ok(ids)
+    }
+
+    /// Load a conversation snapshot
+    ///
+    /// # Errors
+    /// Returns `IoError` with `NotFound` if no conversation has this id
+    pub fn load(&self, id: &str) -> Result<ConversationSnapshot> {
+        validate_id(id)?;
+        for format in [StoreFormat::Json, StoreFormat::Jsonl] {
+            let path = self.path(id, format);
+            match fs::read_to_string(&path) {
+                Ok(contents) => {
+                    return match format {
+                        StoreFormat::Json => ConversationSnapshot::from_json(&contents),
+                        StoreFormat::Jsonl => ConversationSnapshot::from_jsonl(&contents),
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Err(std::io::Error::new(ErrorKind::NotFound, format!("conversation '{}' not found", id)).into())
+    }
+
+    /// Save a conversation snapshot, replacing any earlier save
+    ///
+    /// The file is written to a temporary path first and then renamed, so
+    /// an interrupted save never leaves a truncated file behind.
+    pub fn save(&self, snapshot: &ConversationSnapshot) -> Result<()> {
+        validate_id(&snapshot.id)?;
+        let contents = match self.format {
+            StoreFormat::Json => snapshot.to_json()?,
+            StoreFormat::Jsonl => snapshot.to_jsonl()?,
+        };
+
+        let path = self.path(&snapshot.id, self.format);
+        let tmp = path.with_extension(format!("{}.tmp", self.format.extension()));
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, &path)?;
+
+        // Remove a save in the other format so loading is unambiguous
+        for format in [StoreFormat::Json, StoreFormat::Jsonl] {
+            if format != self.format {
+                remove_if_exists(&self.path(&snapshot.id, format))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete a conversation, returning whether it existed
+    pub fn delete(&self, id: &str) -> Result<bool> {
+        validate_id(id)?;
+        let json = remove_if_exists(&self.path(id, StoreFormat::Json))?;
+        let jsonl = remove_if_exists(&self.path(id, StoreFormat::Jsonl))?;
+        Ok(json || jsonl)
+    }
+
+    /// Path of a conversation file
+    fn path(&self, id: &str, format: StoreFormat) -> PathBuf {
+        self.dir.join(format!("{}.{}", id, format.extension()))
+    }
+
+    /// Format of a file in the store, based on its extension
+    fn format_of(path: &Path) -> Option<StoreFormat> {
+        match path.extension()?.to_str()? {
+            "json" => Some(StoreFormat::Json),
+            "jsonl" => Some(StoreFormat::Jsonl),
+            _ => None,
+        }
+    }
+}
+
+/// Check that an id is safe to use as a file name
+fn validate_id(id: &str) -> Result<()> {
+    let valid = !id.is_empty()
+        && id.len() <= 128
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(DeepSeekError::InvalidParameter(format!(
+            "Invalid conversation id '{}': use 1 to 128 ASCII letters, digits, '-' or '_'",
+            id
+        )))
+    }
+}
+
+/// Remove a file, returning whether it existed
+fn remove_if_exists(path: &Path) -> Result<bool> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Current Unix time in seconds
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Generate a conversation id that is unique within the process and
+/// unlikely to collide across processes
+pub(crate) fn new_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("conv-{:x}-{:x}-{:x}", nanos, std::process::id(), count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn snapshot() -> ConversationSnapshot {
+        ConversationSnapshot {
+            version: SCHEMA_VERSION,
+            id: "session-1".to_string(),
+            model: Model::Reasoner,
+            system_prompt: Some("Be brief".to_string()),
+            messages: vec![Message::user("Hi"), Message::assistant("Hello")],
+            temperature: Some(Temperature::low()),
+            max_tokens: Some(100),
+            reasoning_policy: ReasoningPolicy::Keep,
+            usage: Usage {
+                prompt_tokens: 3,
+                completion_tokens: 2,
+                total_tokens: 5,
+                ..Usage::default()
+            },
+            metadata: BTreeMap::from([("user".to_string(), json!("alice"))]),
+            created_at: 1_700_000_000,
+            updated_at: 1_700_000_100,
+        }
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let snapshot = snapshot();
+        let json = snapshot.to_json().unwrap();
+        assert_eq!(ConversationSnapshot::from_json(&json).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn test_jsonl_round_trip() {
+        let snapshot = snapshot();
+        let jsonl = snapshot.to_jsonl().unwrap();
+        assert_eq!(jsonl.lines().count(), 3);
+        assert!(!jsonl.lines().next().unwrap().contains("messages"));
+        assert_eq!(ConversationSnapshot::from_jsonl(&jsonl).unwrap(), snapshot);
+
+        assert!(ConversationSnapshot::from_jsonl("\n").is_err());
+    }
+
+    #[test]
+    fn test_migrate_v0_request() {
+        let request = json!({
+            "model": "deepseek-reasoner",
+            "messages": [
+                {"role": "system", "content": "Be brief"},
+                {"role": "user", "content": "Hi"}
+            ],
+            "max_tokens": 50,
+            "stream": false
+        });
+
+        let snapshot = ConversationSnapshot::from_json(&request.to_string()).unwrap();
+        assert_eq!(snapshot.version, SCHEMA_VERSION);
+        assert_eq!(snapshot.model, Model::Reasoner);
+        assert_eq!(snapshot.system_prompt.as_deref(), Some("Be brief"));
+        assert_eq!(snapshot.messages, vec![Message::user("Hi")]);
+        assert_eq!(snapshot.max_tokens, Some(50));
+        assert!(validate_id(&snapshot.id).is_ok());
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() {
+        let mut value = serde_json::to_value(snapshot()).unwrap();
+        value["version"] = json!(SCHEMA_VERSION + 1);
+        assert!(matches!(
+            ConversationSnapshot::from_json(&value.to_string()),
+            Err(DeepSeekError::InvalidParameter(_))
+        ));
+    }
+
+    #[test]
+    fn test_file_store() {
+        let dir = std::env::temp_dir().join(new_id());
+        let store = FileConversationStore::new(&dir).unwrap();
+        let mut snapshot = snapshot();
+
+        store.save(&snapshot).unwrap();
+        assert_eq!(store.list().unwrap(), vec!["session-1"]);
+        assert_eq!(store.load("session-1").unwrap(), snapshot);
+
+        snapshot.messages.push(Message::user("Again"));
+        let jsonl_store = store.clone().with_format(StoreFormat::Jsonl);
+        jsonl_store.save(&snapshot).unwrap();
+        assert!(dir.join("session-1.jsonl").exists());
+        assert!(!dir.join("session-1.json").exists());
+        assert_eq!(store.load("session-1").unwrap(), snapshot);
+
+        assert!(store.delete("session-1").unwrap());
+        assert!(!store.delete("session-1").unwrap());
+        assert!(matches!(store.load("session-1"), Err(DeepSeekError::IoError(_))));
+        assert!(store.load("../escape").is_err());
+        assert!(store.list().unwrap().is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
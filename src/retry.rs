@@ -0,0 +1,406 @@
+//! Retry policy for transient failures
+//!
+//! Every request is retried up to [`DeepSeekConfig::max_retries`] times
+//! according to the configured [`RetryPolicy`]:
+//!
+//! - connection failures, timeouts and 408/429/500/502/503/504 responses are
+//!   retried unless a per-status rule says otherwise;
+//! - delays grow exponentially from a base delay up to a maximum, with
+//!   optional jitter;
+//! - a `Retry-After` header on 429 and 503 responses replaces the computed
+//!   delay, giving up if it asks for more than the maximum delay;
+//! - a POST whose response body had started to arrive is never retried,
+//!   since the server may already have processed it;
+//! - an optional deadline bounds the total time spent retrying.
+//!
+//! When a request fails after being retried, the final error is
+//! [`DeepSeekError::RetriesExhausted`] with the number of attempts made.
+//! Streams are only retried until the response headers arrive.
+//!
+//! # Example
+//! ```
+//! use deepseek_rust::retry::{Jitter, RetryPolicy};
+//! use deepseek_rust::DeepSeekConfig;
+//! use std::time::Duration;
+//!
+//! let policy = RetryPolicy::new()
+//!     .with_base_delay(Duration::from_millis(200))
+//!     .with_max_delay(Duration::from_secs(10))
+//!     .with_jitter(Jitter::Decorrelated)
+//!     .with_deadline(Duration::from_secs(60))
+//!     .with_status_rule(500, false);
+//! let config = DeepSeekConfig::new("your-api-key")
+//!     .with_max_retries(5)
+//!     .with_retry_policy(policy);
+//! ```
+//!
+//! [`DeepSeekConfig::max_retries`]: crate::DeepSeekConfig::max_retries
+
+use crate::error::{DeepSeekError, Result};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Default delay before the first retry
+pub const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Default upper bound for a single delay
+pub const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Default growth factor between consecutive delays
+pub const DEFAULT_MULTIPLIER: f64 = 2.0;
+
+/// How much randomness is added to retry delays
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Jitter {
+    /// Wait exactly the exponential backoff
+    None,
+
+    /// Wait a random time between zero and the exponential backoff
+    #[default]
+    Full,
+
+    /// Wait a random time between the base delay and three times the
+    /// previous delay, capped at the maximum delay
+    Decorrelated,
+}
+
+/// How failed requests are retried
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Delay before the first retry
+    pub base_delay: Duration,
+
+    /// Growth factor between consecutive delays
+    pub multiplier: f64,
+
+    /// Upper bound for a single delay
+    pub max_delay: Duration,
+
+    /// Randomness added to delays
+    pub jitter: Jitter,
+
+    /// Total time after which no new attempt is started
+    pub deadline: Option<Duration>,
+
+    /// Whether a status is retried, overriding the default classification
+    pub status_rules: BTreeMap<u16, bool>,
+
+    /// Whether `Retry-After` headers on 429 and 503 responses are honoured
+    pub respect_retry_after: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: DEFAULT_BASE_DELAY,
+            multiplier: DEFAULT_MULTIPLIER,
+            max_delay: DEFAULT_MAX_DELAY,
+            jitter: Jitter::default(),
+            deadline: None,
+            status_rules: BTreeMap::new(),
+            respect_retry_after: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Create the default policy
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the delay before the first retry
+    pub fn with_base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    /// Set the growth factor between consecutive delays
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Set the upper bound for a single delay
+    pub fn with_max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Set the randomness added to delays
+    pub fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Stop retrying once this much time has passed since the first attempt
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Always (`true`) or never (`false`) retry responses with a status
+    pub fn with_status_rule(mut self, status: u16, retry: bool) -> Self {
+        self.status_rules.insert(status, retry);
+        self
+    }
+
+    /// Set whether `Retry-After` headers are honoured
+    pub fn with_respect_retry_after(mut self, respect: bool) -> Self {
+        self.respect_retry_after = respect;
+        self
+    }
+
+    /// Validate the policy
+    pub fn validate(&self) -> Result<()> {
+        if !self.multiplier.is_finite() || self.multiplier < 1.0 {
+            return Err(DeepSeekError::ConfigError(format!(
+                "Retry multiplier must be at least 1.0, got {}",
+                self.multiplier
+            )));
+        }
+        if self.base_delay > self.max_delay {
+            return Err(DeepSeekError::ConfigError(
+                "Retry base delay cannot exceed the max delay".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check if a failed attempt should be retried
+    ///
+    /// Status rules take precedence over [`DeepSeekError::is_retryable`].
+    pub fn should_retry(&self, err: &DeepSeekError) -> bool {
+        err.status_code()
+            .and_then(|status| self.status_rules.get(&status).copied())
+            .unwrap_or_else(|| err.is_retryable())
+    }
+
+    /// Exponential backoff for a zero-based retry attempt, before jitter
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.powi(attempt.min(i32::MAX as u32) as i32);
+        let secs = self.base_delay.as_secs_f64() * factor;
+        if secs.is_finite() && secs < self.max_delay.as_secs_f64() {
+            Duration::from_secs_f64(secs)
+        } else {
+            self.max_delay
+        }
+    }
+
+    /// Delay before a zero-based retry attempt
+    ///
+    /// `previous` is the delay before the last retry, or zero for the first
+    /// one; only decorrelated jitter uses it.
+    pub fn delay(&self, attempt: u32, previous: Duration) -> Duration {
+        self.delay_with(attempt, previous, random_unit())
+    }
+
+    /// Delay before retrying a failure, or `None` to give up
+    ///
+    /// A honoured `Retry-After` longer than the maximum delay gives up
+    /// rather than retrying early.
+    pub(crate) fn next_delay(
+        &self,
+        attempt: u32,
+        previous: Duration,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration> {
+        match retry_after {
+            Some(delay) if self.respect_retry_after => (delay <= self.max_delay).then_some(delay),
+            _ => Some(self.delay(attempt, previous)),
+        }
+    }
+
+    /// Delay for a random value `unit` in `[0, 1)`
+    fn delay_with(&self, attempt: u32, previous: Duration, unit: f64) -> Duration {
+        match self.jitter {
+            Jitter::None => self.backoff(attempt),
+            Jitter::Full => self.backoff(attempt).mul_f64(unit),
+            Jitter::Decorrelated => {
+                let low = self.base_delay;
+                let high = previous.max(self.base_delay).saturating_mul(3).min(self.max_delay);
+                (low + high.saturating_sub(low).mul_f64(unit)).min(self.max_delay)
+            }
+        }
+    }
+}
+
+/// A failed attempt, with what the retry loop needs to know about it
+#[derive(Debug)]
+pub(crate) struct Failure {
+    pub(crate) error: DeepSeekError,
+
+    /// Delay requested by a `Retry-After` header
+    pub(crate) retry_after: Option<Duration>,
+
+    /// Whether part of a successful response body had been received
+    pub(crate) body_received: bool,
+}
+
+impl From<DeepSeekError> for Failure {
+    fn from(error: DeepSeekError) -> Self {
+        Self {
+            error,
+            retry_after: None,
+            body_received: false,
+        }
+    }
+}
+
+/// Parse a `Retry-After` value, either delay seconds or an HTTP date
+pub(crate) fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let at = UNIX_EPOCH + Duration::from_secs(parse_http_date(value)?);
+    Some(at.duration_since(now).unwrap_or_default())
+}
+
+/// Parse an IMF-fixdate such as `Sun, 06 Nov 1994 08:49:37 GMT` into
+/// seconds since the Unix epoch
+fn parse_http_date(value: &str) -> Option<u64> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [_, day, month, year, time, "GMT"] = parts.as_slice() else {
+        return None;
+    };
+
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let month = MONTHS.iter().position(|m| m == month)? as i64 + 1;
+    let day: i64 = day.parse().ok().filter(|d| (1..=31).contains(d))?;
+    let year: i64 = year.parse().ok().filter(|y| *y >= 1970)?;
+
+    let mut clock = time.split(':').map(|part| part.parse::<i64>().ok());
+    let (Some(Some(hour)), Some(Some(minute)), Some(Some(second)), None) =
+        (clock.next(), clock.next(), clock.next(), clock.next())
+    else {
+        return None;
+    };
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let secs = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second;
+    u64::try_from(secs).ok()
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// A pseudo-random value in `[0, 1)`; good enough to spread out retries
+fn random_unit() -> f64 {
+    static STATE: AtomicU64 = AtomicU64::new(0);
+    const GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u64)
+        .unwrap_or_default();
+    // splitmix64
+    let mut z = STATE.fetch_add(GAMMA, Ordering::Relaxed).wrapping_add(GAMMA) ^ nanos;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_error(status: u16) -> DeepSeekError {
+        DeepSeekError::ApiError { status, message: String::new() }
+    }
+
+    #[test]
+    fn test_should_retry() {
+        let policy = RetryPolicy::default();
+        assert!(policy.should_retry(&DeepSeekError::TimeoutError(30)));
+        assert!(policy.should_retry(&api_error(503)));
+        assert!(policy.should_retry(&api_error(429)));
+        assert!(!policy.should_retry(&api_error(400)));
+
+        let policy = policy.with_status_rule(503, false).with_status_rule(409, true);
+        assert!(!policy.should_retry(&api_error(503)));
+        assert!(policy.should_retry(&api_error(409)));
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(0), Duration::from_millis(500));
+        assert_eq!(policy.backoff(1), Duration::from_millis(1000));
+        assert_eq!(policy.backoff(2), Duration::from_millis(2000));
+        assert_eq!(policy.backoff(20), DEFAULT_MAX_DELAY);
+        assert_eq!(policy.backoff(u32::MAX), DEFAULT_MAX_DELAY);
+
+        let policy = RetryPolicy::new().with_multiplier(3.0).with_jitter(Jitter::None);
+        assert_eq!(policy.delay(2, Duration::ZERO), Duration::from_millis(4500));
+    }
+
+    #[test]
+    fn test_jitter() {
+        let full = RetryPolicy::new();
+        assert_eq!(full.delay_with(1, Duration::ZERO, 0.0), Duration::ZERO);
+        assert_eq!(full.delay_with(1, Duration::ZERO, 0.5), Duration::from_millis(500));
+        for attempt in 0..10 {
+            assert!(full.delay(attempt, Duration::ZERO) <= full.backoff(attempt));
+        }
+
+        let decorrelated = RetryPolicy::new().with_jitter(Jitter::Decorrelated);
+        let previous = Duration::from_secs(2);
+        assert_eq!(decorrelated.delay_with(3, previous, 0.0), DEFAULT_BASE_DELAY);
+        assert_eq!(decorrelated.delay_with(3, previous, 0.5), Duration::from_millis(3250));
+        let capped = decorrelated.delay_with(3, Duration::from_secs(60), 0.999);
+        assert!(capped > Duration::from_secs(29) && capped <= DEFAULT_MAX_DELAY);
+    }
+
+    #[test]
+    fn test_next_delay_honours_retry_after() {
+        let policy = RetryPolicy::new().with_jitter(Jitter::None);
+        let retry_after = Some(Duration::from_secs(3));
+        assert_eq!(policy.next_delay(0, Duration::ZERO, retry_after), retry_after);
+        assert_eq!(policy.next_delay(0, Duration::ZERO, Some(Duration::from_secs(60))), None);
+
+        let policy = policy.with_respect_retry_after(false);
+        assert_eq!(policy.next_delay(0, Duration::ZERO, retry_after), Some(DEFAULT_BASE_DELAY));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        assert_eq!(parse_retry_after(" 7 ", now), Some(Duration::from_secs(7)));
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:50:07 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(parse_retry_after("Sun, 06 Nov 1994 08:49:37 CET", now), None);
+        assert_eq!(parse_retry_after("soon", now), None);
+        assert_eq!(parse_retry_after("-1", now), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(RetryPolicy::default().validate().is_ok());
+        assert!(RetryPolicy::new().with_multiplier(0.5).validate().is_err());
+        assert!(RetryPolicy::new()
+            .with_max_delay(Duration::from_millis(100))
+            .validate()
+            .is_err());
+    }
+}
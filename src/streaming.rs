@@ -0,0 +1,343 @@
+//! Server-sent events (SSE) parsing for streaming chat completions
+//!
+//! Enabled with the `streaming` feature. Use [`ChatBuilder::stream`] or
+//! [`DeepSeekClient::chat_completion_stream`] to obtain a [`ChatStream`].
+//!
+//! [`ChatBuilder::stream`]: crate::ChatBuilder::stream
+//! [`DeepSeekClient::chat_completion_stream`]: crate::DeepSeekClient::chat_completion_stream
+
+use crate::error::{DeepSeekError, Result};
+use crate::models::fim::FimCompletionResponse;
+use crate::models::response::{
+    ApiErrorResponse, ChatCompletionResponse, StreamAccumulator, StreamChunk,
+};
+use bytes::Bytes;
+use futures::stream::{self, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use std::collections::VecDeque;
+use std::pin::Pin;
+
+/// Data payload the API sends as the last event of a stream
+pub const DONE_MARKER: &str = "[DONE]";
+
+/// A stream of chat completion chunks
+pub type ChatStream = Pin<Box<dyn Stream<Item = Result<StreamChunk>> + Send>>;
+
+/// A stream of FIM completion chunks
+pub type FimStream = Pin<Box<dyn Stream<Item = Result<FimCompletionResponse>> + Send>>;
+
+/// A stream of raw body bytes
+pub(crate) type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes>> + Send>>;
+
+/// An event decoded from an SSE body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SseEvent {
+    /// The `data:` payload of an event (multiple data lines joined by `\n`)
+    Data(String),
+    /// The `[DONE]` terminator
+    Done,
+}
+
+/// Incremental parser for a `text/event-stream` body
+///
+/// Bytes can be fed in arbitrarily sized pieces. Lines are only decoded once
+/// they are complete, so multi-byte UTF-8 characters split across network
+/// chunks are handled transparently. Comment lines (such as the API's
+/// `: keep-alive`) and fields other than `data` are ignored.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    /// Create a new parser
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a piece of the body and return every event it completes
+    ///
+    /// # Errors
+    /// Returns an error if a complete line is not valid UTF-8
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<Vec<SseEvent>> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut events = Vec::new();
+        let mut start = 0;
+        while let Some(offset) = self.buffer[start..].iter().position(|&b| b == b'\n') {
+            let end = start + offset;
+            let line = decode_line(&self.buffer[start..end])?;
+            if let Some(event) = self.process_line(&line) {
+                events.push(event);
+            }
+            start = end + 1;
+        }
+        self.buffer.drain(..start);
+
+        Ok(events)
+    }
+
+    /// Flush the event left at the end of the body, if any
+    ///
+    /// Servers are expected to terminate each event with a blank line, but
+    /// a final event without one is still delivered.
+    pub fn finish(&mut self) -> Result<Option<SseEvent>> {
+        if !self.buffer.is_empty() {
+            let buffer = std::mem::take(&mut self.buffer);
+            let line = decode_line(&buffer)?;
+            if let Some(event) = self.process_line(&line) {
+                return Ok(Some(event));
+            }
+        }
+        Ok(self.dispatch())
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+
+        if field == "data" {
+            self.data.push(value.to_string());
+        }
+
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let data = self.data.join("\n");
+        self.data.clear();
+
+        if data.trim() == DONE_MARKER {
+            Some(SseEvent::Done)
+        } else {
+            Some(SseEvent::Data(data))
+        }
+    }
+}
+
+/// Decode a line without its terminator, dropping a trailing `\r`
+fn decode_line(bytes: &[u8]) -> Result<String> {
+    let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+    String::from_utf8(bytes.to_vec())
+        .map_err(|e| DeepSeekError::StreamError(format!("Invalid UTF-8 in stream: {}", e)))
+}
+
+/// Parse the data payload of one event into a chunk
+///
+/// Error payloads sent mid-stream are turned into `StreamError`.
+pub fn parse_chunk(data: &str) -> Result<StreamChunk> {
+    parse_event(data)
+}
+
+/// Parse the data payload of one event into any chunk type
+fn parse_event<T: DeserializeOwned>(data: &str) -> Result<T> {
+    let value: serde_json::Value = serde_json::from_str(data)?;
+
+    if value.get("error").is_some() {
+        let message = serde_json::from_value::<ApiErrorResponse>(value)
+            .map(|e| e.error.message)
+            .unwrap_or_else(|_| data.to_string());
+        return Err(DeepSeekError::StreamError(message));
+    }
+
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Turn a raw SSE body into a stream of chat completion chunks
+pub(crate) fn chunk_stream(body: ByteStream) -> ChatStream {
+    event_stream(body)
+}
+
+/// Turn a raw SSE body into a stream of FIM completion chunks
+pub(crate) fn fim_chunk_stream(body: ByteStream) -> FimStream {
+    event_stream(body)
+}
+
+/// Turn a raw SSE body into a stream of decoded chunks
+///
+/// The stream ends after `[DONE]`, at the end of the body, or after the
+/// first error.
+fn event_stream<T>(body: ByteStream) -> Pin<Box<dyn Stream<Item = Result<T>> + Send>>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    struct State {
+        body: ByteStream,
+        parser: SseParser,
+        pending: VecDeque<SseEvent>,
+        eof: bool,
+        finished: bool,
+    }
+
+    let state = State {
+        body,
+        parser: SseParser::new(),
+        pending: VecDeque::new(),
+        eof: false,
+        finished: false,
+    };
+
+    Box::pin(stream::unfold(state, |mut state| async move {
+        loop {
+            if state.finished {
+                return None;
+            }
+
+            if let Some(event) = state.pending.pop_front() {
+                match event {
+                    SseEvent::Done => return None,
+                    SseEvent::Data(data) => {
+                        let item = parse_event(&data);
+                        state.finished = item.is_err();
+                        return Some((item, state));
+                    }
+                }
+            }
+
+            if state.eof {
+                return None;
+            }
+
+            let result = match state.body.next().await {
+                Some(Ok(bytes)) => state.parser.feed(&bytes),
+                Some(Err(err)) => Err(err),
+                None => {
+                    state.eof = true;
+                    state.parser.finish().map(|event| event.into_iter().collect())
+                }
+            };
+
+            match result {
+                Ok(events) => state.pending.extend(events),
+                Err(err) => {
+                    state.finished = true;
+                    return Some((Err(err), state));
+                }
+            }
+        }
+    }))
+}
+
+/// Drain a stream into the full response a non-streaming call would return
+///
+/// Stops at the first error.
+pub async fn accumulate(mut stream: ChatStream) -> Result<ChatCompletionResponse> {
+    let mut accumulator = StreamAccumulator::new();
+    while let Some(chunk) = stream.next().await {
+        accumulator.push(&chunk?);
+    }
+    accumulator.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk_json(content: &str) -> String {
+        format!(
+            r#"{{"id":"c1","object":"chat.completion.chunk","created":1,"model":"deepseek-chat","choices":[{{"index":0,"delta":{{"content":"{}"}}}}]}}"#,
+            content
+        )
+    }
+
+    fn body_from(pieces: Vec<Vec<u8>>) -> ByteStream {
+        Box::pin(stream::iter(pieces.into_iter().map(|p| Ok(Bytes::from(p)))))
+    }
+
+    #[test]
+    fn test_parser_events() {
+        let mut parser = SseParser::new();
+        let events = parser
+            .feed(b": keep-alive\n\ndata: one\n\ndata:two\r\n\r\nevent: x\ndata: a\ndata: b\n\ndata: [DONE]\n\n")
+            .unwrap();
+
+        assert_eq!(
+            events,
+            vec![
+                SseEvent::Data("one".to_string()),
+                SseEvent::Data("two".to_string()),
+                SseEvent::Data("a\nb".to_string()),
+                SseEvent::Done,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_split_utf8() {
+        let text = "data: héllo 世界\n\n".as_bytes();
+        let mut parser = SseParser::new();
+        let mut events = Vec::new();
+        for byte in text {
+            events.extend(parser.feed(std::slice::from_ref(byte)).unwrap());
+        }
+        assert_eq!(events, vec![SseEvent::Data("héllo 世界".to_string())]);
+    }
+
+    #[test]
+    fn test_parser_finish_without_blank_line() {
+        let mut parser = SseParser::new();
+        assert!(parser.feed(b"data: tail").unwrap().is_empty());
+        assert_eq!(parser.finish().unwrap(), Some(SseEvent::Data("tail".to_string())));
+        assert_eq!(parser.finish().unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_chunk_error_payload() {
+        let err = parse_chunk(r#"{"error":{"message":"Server overloaded"}}"#).unwrap_err();
+        match err {
+            DeepSeekError::StreamError(message) => assert_eq!(message, "Server overloaded"),
+            other => panic!("Expected StreamError, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chunk_stream() {
+        let body = format!("data: {}\n\n: keep-alive\n\ndata: {}\n\ndata: [DONE]\n\n", chunk_json("Hel"), chunk_json("lo"));
+        let (first, second) = body.as_bytes().split_at(30);
+        let stream = chunk_stream(body_from(vec![first.to_vec(), second.to_vec()]));
+
+        let chunks: Vec<_> = stream.collect().await;
+        assert_eq!(chunks.len(), 2);
+        let contents: Vec<_> = chunks
+            .into_iter()
+            .map(|c| c.unwrap().choices[0].delta.content.clone().unwrap())
+            .collect();
+        assert_eq!(contents, vec!["Hel", "lo"]);
+    }
+
+    #[tokio::test]
+    async fn test_accumulate() {
+        let body = format!("data: {}\n\ndata: {}\n\ndata: [DONE]\n\n", chunk_json("Hi"), chunk_json("!"));
+        let response = accumulate(chunk_stream(body_from(vec![body.into_bytes()]))).await.unwrap();
+        assert_eq!(response.get_content(), Some("Hi!"));
+    }
+
+    #[tokio::test]
+    async fn test_chunk_stream_stops_on_error() {
+        let body = format!(
+            "data: {}\n\ndata: {{\"error\":{{\"message\":\"boom\"}}}}\n\ndata: {}\n\n",
+            chunk_json("a"),
+            chunk_json("b")
+        );
+        let items: Vec<_> = chunk_stream(body_from(vec![body.into_bytes()])).collect().await;
+
+        assert_eq!(items.len(), 2);
+        assert!(items[0].is_ok());
+        assert!(matches!(items[1], Err(DeepSeekError::StreamError(_))));
+    }
+}
@@ -0,0 +1,487 @@
+//! Token counting before requests
+//!
+//! A [`Tokenizer`] counts the tokens of messages, tool definitions and whole
+//! requests so oversized prompts can be rejected or trimmed before they are
+//! sent. It either loads DeepSeek's byte-level BPE vocabulary from a local
+//! `tokenizer.json` file or, without one, estimates counts from character
+//! counts (about 0.3 tokens per ASCII character and 0.6 per other
+//! character).
+//!
+//! Counts are close to, but not always exactly, what the API reports: text
+//! is split into words with a simplified version of DeepSeek's
+//! pre-tokenizer, and the chat template overhead is approximate.
+//!
+//! # Example
+//! ```no_run
+//! use deepseek_rust::tokenizer::Tokenizer;
+//! use deepseek_rust::{ChatCompletionRequest, Message, Result};
+//!
+//! # fn run() -> Result<()> {
+//! let tokenizer = Tokenizer::from_file("tokenizer.json")?;
+//! let request = ChatCompletionRequest::new(vec![Message::user("Hello!")]);
+//! println!("{} prompt tokens", tokenizer.count_request(&request));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{DeepSeekError, Result};
+use crate::models::capabilities::ModelCapabilities;
+use crate::models::request::{ChatCompletionRequest, Message};
+use crate::models::tool::Tool;
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Template tokens added around every message (role and end markers)
+pub const MESSAGE_OVERHEAD: usize = 2;
+
+/// Template tokens added once per request (start of text and the
+/// assistant marker the reply follows)
+pub const REQUEST_OVERHEAD: usize = 2;
+
+/// Template tokens added around every tool definition
+pub const TOOL_OVERHEAD: usize = 4;
+
+/// Estimated tenths of a token per ASCII character
+const ASCII_TENTHS_PER_CHAR: usize = 3;
+
+/// Estimated tenths of a token per non-ASCII character (e.g. Chinese)
+const OTHER_TENTHS_PER_CHAR: usize = 6;
+
+/// Counts tokens for DeepSeek models
+///
+/// Cheap to clone; clones share the loaded vocabulary.
+#[derive(Clone, Default)]
+pub struct Tokenizer {
+    vocabulary: Option<Arc<Vocabulary>>,
+}
+
+impl Tokenizer {
+    /// A tokenizer that estimates counts from character counts
+    pub fn approximate() -> Self {
+        Self::default()
+    }
+
+    /// Load a Hugging Face `tokenizer.json` file
+    ///
+    /// # Errors
+    /// Returns `IoError` if the file cannot be read, or an error from
+    /// [`Tokenizer::from_json`]
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parse the contents of a Hugging Face `tokenizer.json` file
+    ///
+    /// # Errors
+    /// Returns `JsonError` for malformed JSON and `InvalidParameter` if the
+    /// file does not describe a byte-level BPE model
+    pub fn from_json(json: &str) -> Result<Self> {
+        let file: TokenizerFile = serde_json::from_str(json)?;
+        Ok(Self {
+            vocabulary: Some(Arc::new(Vocabulary::new(file.model)?)),
+        })
+    }
+
+    /// Check if counts come from a vocabulary rather than an estimate
+    pub fn is_exact(&self) -> bool {
+        self.vocabulary.is_some()
+    }
+
+    /// Token ids of a text, if a vocabulary is loaded
+    pub fn encode(&self, text: &str) -> Option<Vec<u32>> {
+        let vocabulary = self.vocabulary.as_ref()?;
+        Some(pre_tokenize(text).into_iter().flat_map(|piece| vocabulary.bpe(piece)).collect())
+    }
+
+    /// Number of tokens in a text
+    pub fn count(&self, text: &str) -> usize {
+        match &self.vocabulary {
+            Some(vocabulary) => pre_tokenize(text).into_iter().map(|piece| vocabulary.bpe(piece).len()).sum(),
+            None => estimate(text),
+        }
+    }
+
+    /// Number of tokens a message adds to the prompt
+    ///
+    /// Includes tool calls, prefix reasoning and the template overhead.
+    pub fn count_message(&self, message: &Message) -> usize {
+        let tool_calls: usize = message
+            .tool_calls
+            .iter()
+            .flatten()
+            .map(|call| self.count(&call.function.name) + self.count(&call.function.arguments))
+            .sum();
+        let reasoning = message.reasoning_content.as_deref().map_or(0, |r| self.count(r));
+
+        MESSAGE_OVERHEAD + self.count(&message.content) + reasoning + tool_calls
+    }
+
+    /// Number of tokens the tool definitions add to the prompt
+    pub fn count_tools(&self, tools: &[Tool]) -> usize {
+        tools
+            .iter()
+            .map(|tool| {
+                let definition = serde_json::to_string(&tool.function).unwrap_or_default();
+                TOOL_OVERHEAD + self.count(&definition)
+            })
+            .sum()
+    }
+
+    /// Number of prompt tokens of a request
+    pub fn count_request(&self, request: &ChatCompletionRequest) -> usize {
+        let messages: usize = request.messages.iter().map(|m| self.count_message(m)).sum();
+        let tools = request.tools.as_deref().map_or(0, |tools| self.count_tools(tools));
+        REQUEST_OVERHEAD + messages + tools
+    }
+
+    /// Count the prompt tokens of a request and check that the prompt and
+    /// `max_tokens` fit in the model's context window
+    ///
+    /// # Errors
+    /// Returns `InvalidParameter` if the request does not fit
+    pub fn check_request(&self, request: &ChatCompletionRequest, capabilities: &ModelCapabilities) -> Result<usize> {
+        let prompt = self.count_request(request);
+        let Some(window) = capabilities.context_window else {
+            return Ok(prompt);
+        };
+
+        let reserved = request.max_tokens.unwrap_or(0) as usize;
+        if prompt + reserved > window as usize {
+            let reply = match request.max_tokens {
+                Some(max_tokens) => format!(" plus max_tokens {}", max_tokens),
+                None => String::new(),
+            };
+            return Err(DeepSeekError::InvalidParameter(format!(
+                "Prompt of about {} tokens{} exceeds the {} token context window of {}",
+                prompt, reply, window, request.model
+            )));
+        }
+        Ok(prompt)
+    }
+}
+
+impl fmt::Debug for Tokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tokenizer")
+            .field("vocabulary_size", &self.vocabulary.as_ref().map(|v| v.size))
+            .finish()
+    }
+}
+
+/// Estimate the token count of a text from its characters
+fn estimate(text: &str) -> usize {
+    let tenths: usize = text
+        .chars()
+        .map(|c| if c.is_ascii() { ASCII_TENTHS_PER_CHAR } else { OTHER_TENTHS_PER_CHAR })
+        .sum();
+    tenths.div_ceil(10)
+}
+
+/// The parts of `tokenizer.json` used for counting
+#[derive(Deserialize)]
+struct TokenizerFile {
+    model: BpeModel,
+}
+
+#[derive(Deserialize)]
+struct BpeModel {
+    #[serde(rename = "type", default)]
+    kind: Option<String>,
+    vocab: HashMap<String, u32>,
+    merges: Vec<Merge>,
+}
+
+/// A merge rule, written as "left right" or as a pair
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Merge {
+    Text(String),
+    Pair(String, String),
+}
+
+/// Byte-level BPE vocabulary with merges keyed by token ids
+struct Vocabulary {
+    size: usize,
+    byte_tokens: [u32; 256],
+    merges: HashMap<(u32, u32), (usize, u32)>,
+}
+
+impl Vocabulary {
+    fn new(model: BpeModel) -> Result<Self> {
+        if let Some(kind) = model.kind.as_deref().filter(|kind| *kind != "BPE") {
+            return Err(DeepSeekError::InvalidParameter(format!(
+                "Unsupported tokenizer model '{}', expected BPE",
+                kind
+            )));
+        }
+
+        let chars = byte_chars();
+        let mut byte_tokens = [0; 256];
+        for (byte, c) in chars.iter().enumerate() {
+            byte_tokens[byte] = *model.vocab.get(c.to_string().as_str()).ok_or_else(|| {
+                DeepSeekError::InvalidParameter(format!(
+                    "Tokenizer vocabulary has no token for byte 0x{:02x}; only byte-level BPE is supported",
+                    byte
+                ))
+            })?;
+        }
+
+        let mut merges = HashMap::with_capacity(model.merges.len());
+        for (rank, merge) in model.merges.into_iter().enumerate() {
+            let (left, right) = match merge {
+                Merge::Pair(left, right) => (left, right),
+                Merge::Text(text) => match text.split_once(' ') {
+                    Some((left, right)) => (left.to_string(), right.to_string()),
+                    None => continue,
+                },
+            };
+            let merged = format!("{}{}", left, right);
+            if let (Some(&l), Some(&r), Some(&m)) =
+                (model.vocab.get(&left), model.vocab.get(&right), model.vocab.get(&merged))
+            {
+                merges.entry((l, r)).or_insert((rank, m));
+            }
+        }
+
+        Ok(Self {
+            size: model.vocab.len(),
+            byte_tokens,
+            merges,
+        })
+    }
+
+    /// Encode one pre-tokenized piece
+    ///
+    /// Applies the lowest-ranked merge first, leftmost on ties. Candidate
+    /// pairs are kept in a heap over a linked list of tokens, so long pieces
+    /// take O(n log n) rather than a rescan per merge.
+    fn bpe(&self, piece: &str) -> Vec<u32> {
+        let mut tokens: Vec<u32> = piece.bytes().map(|b| self.byte_tokens[b as usize]).collect();
+        let len = tokens.len();
+        let mut prev: Vec<Option<usize>> = (0..len).map(|i| i.checked_sub(1)).collect();
+        let mut next: Vec<Option<usize>> = (0..len).map(|i| Some(i + 1).filter(|&j| j < len)).collect();
+        let mut alive = vec![true; len];
+
+        // (rank, left position, right position, left token, right token)
+        let mut heap = BinaryHeap::new();
+        let candidate = |tokens: &[u32], i: usize, j: usize| {
+            self.merges
+                .get(&(tokens[i], tokens[j]))
+                .map(|&(rank, _)| Reverse((rank, i, j, tokens[i], tokens[j])))
+        };
+        heap.extend((1..len).filter_map(|j| candidate(&tokens, j - 1, j)));
+
+        while let Some(Reverse((_, i, j, left, right))) = heap.pop() {
+            // Skip pairs changed by an earlier merge
+            if !alive[i] || !alive[j] || next[i] != Some(j) || tokens[i] != left || tokens[j] != right {
+                continue;
+            }
+
+            tokens[i] = self.merges[&(left, right)].1;
+            alive[j] = false;
+            next[i] = next[j];
+            if let Some(k) = next[j] {
+                prev[k] = Some(i);
+            }
+
+            heap.extend(prev[i].and_then(|h| candidate(&tokens, h, i)));
+            heap.extend(next[i].and_then(|k| candidate(&tokens, i, k)));
+        }
+
+        tokens
+            .into_iter()
+            .zip(alive)
+            .filter_map(|(token, alive)| alive.then_some(token))
+            .collect()
+    }
+}
+
+/// The printable characters byte-level BPE uses for each byte
+fn byte_chars() -> [char; 256] {
+    let mut chars = ['\0'; 256];
+    let mut next = 256;
+    for byte in 0..=255u8 {
+        let printable = matches!(byte, b'!'..=b'~' | 0xA1..=0xAC | 0xAE..=0xFF);
+        chars[byte as usize] = if printable {
+            byte as char
+        } else {
+            next += 1;
+            char::from_u32(next - 1).unwrap_or_default()
+        };
+    }
+    chars
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharKind {
+    Letter,
+    Digit,
+    Cjk,
+    Space,
+    Newline,
+    Other,
+}
+
+impl CharKind {
+    fn of(c: char) -> Self {
+        match c {
+            '\n' | '\r' => CharKind::Newline,
+            c if c.is_whitespace() => CharKind::Space,
+            c if c.is_numeric() => CharKind::Digit,
+            '\u{4E00}'..='\u{9FA5}' | '\u{3040}'..='\u{30FF}' => CharKind::Cjk,
+            c if c.is_alphabetic() => CharKind::Letter,
+            _ => CharKind::Other,
+        }
+    }
+}
+
+/// Split text into the pieces BPE runs on
+///
+/// Follows DeepSeek's pre-tokenizer: digits in groups of up to three, runs
+/// of CJK characters, words and punctuation runs with one leading space,
+/// and whitespace runs that leave their last space to the next word.
+fn pre_tokenize(text: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let offset = |i: usize| chars.get(i).map_or(text.len(), |&(pos, _)| pos);
+    let kind = |i: usize| chars.get(i).map(|&(_, c)| CharKind::of(c));
+    let run = |mut i: usize, pred: &dyn Fn(CharKind) -> bool| {
+        while kind(i).is_some_and(pred) {
+            i += 1;
+        }
+        i
+    };
+
+    let mut pieces = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        if chars[i].1 == ' ' && matches!(kind(i + 1), Some(CharKind::Letter | CharKind::Other)) {
+            i += 1;
+        }
+
+        i = match CharKind::of(chars[i].1) {
+            CharKind::Letter => run(i, &|k| k == CharKind::Letter),
+            CharKind::Cjk => run(i, &|k| k == CharKind::Cjk),
+            CharKind::Digit => run(i, &|k| k == CharKind::Digit).min(i + 3),
+            CharKind::Other => {
+                let end = run(i, &|k| k == CharKind::Other);
+                run(end, &|k| k == CharKind::Newline)
+            }
+            CharKind::Space | CharKind::Newline => {
+                let end = run(i, &|k| matches!(k, CharKind::Space | CharKind::Newline));
+                match (start..end).rev().find(|&k| kind(k) == Some(CharKind::Newline)) {
+                    Some(newline) => newline + 1,
+                    None if end < chars.len() && end - start > 1 && chars[end - 1].1 == ' ' => end - 1,
+                    None => end,
+                }
+            }
+        };
+        pieces.push(&text[offset(start)..offset(i)]);
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::request::Model;
+    use serde_json::json;
+
+    /// A vocabulary of all bytes plus merges spelling "hello" and " w"
+    fn tokenizer() -> Tokenizer {
+        let mut vocab: HashMap<String, u32> = byte_chars()
+            .iter()
+            .enumerate()
+            .map(|(id, c)| (c.to_string(), id as u32))
+            .collect();
+        for (id, token) in ["ll", "he", "hell", "hello", "Ġw"].iter().enumerate() {
+            vocab.insert(token.to_string(), 256 + id as u32);
+        }
+
+        let file = json!({
+            "model": {
+                "type": "BPE",
+                "vocab": vocab,
+                "merges": ["l l", "h e", "he ll", ["hell", "o"], "Ġ w"]
+            }
+        });
+        Tokenizer::from_json(&file.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_pre_tokenize() {
+        assert_eq!(pre_tokenize("Hello, world!"), vec!["Hello", ",", " world", "!"]);
+        assert_eq!(pre_tokenize("a  b\n\nc"), vec!["a", " ", " b", "\n\n", "c"]);
+        assert_eq!(pre_tokenize("12345"), vec!["123", "45"]);
+        assert_eq!(pre_tokenize("你好 world"), vec!["你好", " world"]);
+        assert!(pre_tokenize("").is_empty());
+    }
+
+    #[test]
+    fn test_bpe_encoding() {
+        let tokenizer = tokenizer();
+        assert!(tokenizer.is_exact());
+        assert_eq!(tokenizer.encode("hello"), Some(vec![259]));
+        // " world" becomes "Ġw" followed by the single bytes of "orld"
+        assert_eq!(tokenizer.count("hello world"), 6);
+        assert_eq!(tokenizer.count("héllo"), 5);
+        assert_eq!(Tokenizer::approximate().encode("hello"), None);
+    }
+
+    #[test]
+    fn test_bpe_long_piece() {
+        let tokenizer = tokenizer();
+        let word = "hello".repeat(20_000);
+        assert_eq!(pre_tokenize(&word).len(), 1);
+        assert_eq!(tokenizer.count(&word), 20_000);
+        assert_eq!(tokenizer.count(&"l".repeat(100_000)), 50_000);
+    }
+
+    #[test]
+    fn test_invalid_vocabulary() {
+        let missing_bytes = json!({"model": {"type": "BPE", "vocab": {"a": 0}, "merges": []}});
+        assert!(Tokenizer::from_json(&missing_bytes.to_string()).is_err());
+
+        let unigram = json!({"model": {"type": "Unigram", "vocab": {}, "merges": []}});
+        assert!(Tokenizer::from_json(&unigram.to_string()).is_err());
+    }
+
+    #[test]
+    fn test_approximate_counts() {
+        let tokenizer = Tokenizer::approximate();
+        assert_eq!(tokenizer.count("Hello world"), 4); // 11 * 0.3 = 3.3
+        assert_eq!(tokenizer.count("你好"), 2); // 2 * 0.6 = 1.2
+        assert_eq!(tokenizer.count(""), 0);
+        assert_eq!(tokenizer.count_message(&Message::user("Hello world")), 4 + MESSAGE_OVERHEAD);
+    }
+
+    #[test]
+    fn test_count_request() {
+        let tokenizer = Tokenizer::approximate();
+        let request = ChatCompletionRequest::new(vec![Message::system("Be brief"), Message::user("Hi")]);
+        let messages = tokenizer.count_message(&request.messages[0]) + tokenizer.count_message(&request.messages[1]);
+        assert_eq!(tokenizer.count_request(&request), REQUEST_OVERHEAD + messages);
+
+        let with_tools = request.clone().add_tool(Tool::function(
+            crate::FunctionDefinition::new("get_weather").with_description("Get the weather"),
+        ));
+        assert!(tokenizer.count_request(&with_tools) > tokenizer.count_request(&request) + TOOL_OVERHEAD);
+    }
+
+    #[test]
+    fn test_check_request() {
+        let tokenizer = Tokenizer::approximate();
+        let capabilities = Model::Chat.capabilities();
+        let request = ChatCompletionRequest::from_user_message("Hi");
+        assert!(tokenizer.check_request(&request, &capabilities).is_ok());
+        assert!(tokenizer.check_request(&request.clone().with_max_tokens(200_000), &capabilities).is_err());
+
+        let huge = ChatCompletionRequest::from_user_message("word ".repeat(100_000));
+        assert!(tokenizer.check_request(&huge, &capabilities).is_err());
+        assert!(tokenizer.check_request(&huge, &ModelCapabilities::unknown()).is_ok());
+    }
+}
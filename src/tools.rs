@@ -0,0 +1,450 @@
+//! Automatic tool execution
+//!
+//! A [`ToolRegistry`] maps function names to async Rust handlers.
+//! [`DeepSeekClient::run_with_tools`] then drives the function calling loop:
+//! it sends the request, runs every tool call the model makes, appends the
+//! results and resends until the model answers without calling a tool.
+//!
+//! # Example
+//! ```no_run
+//! use deepseek_rust::tools::ToolRegistry;
+//! use deepseek_rust::{ChatCompletionRequest, DeepSeekClient, FunctionDefinition, Result};
+//! use serde_json::json;
+//!
+//! # async fn run(client: DeepSeekClient) -> Result<()> {
+//! let registry = ToolRegistry::new().register(
+//!     FunctionDefinition::new("get_weather")
+//!         .with_description("Get the current weather for a city")
+//!         .with_parameters(json!({
+//!             "type": "object",
+//!             "properties": {"city": {"type": "string"}},
+//!             "required": ["city"]
+//!         })),
+//!     |args| async move { Ok(json!({"city": args["city"], "temperature": 21})) },
+//! );
+//!
+//! let request = ChatCompletionRequest::from_user_message("What's the weather in Paris?");
+//! let run = client.run_with_tools(request, &registry).await?;
+//! println!("{}", run.response.get_content().unwrap_or_default());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`DeepSeekClient::run_with_tools`]: crate::DeepSeekClient::run_with_tools
+
+use crate::error::{DeepSeekError, Result};
+use crate::models::response::{ChatCompletionResponse, ToolCall};
+use crate::models::tool::{DeepSeekTool, FunctionDefinition, Tool};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Default maximum number of model round trips in a tool loop
+pub const DEFAULT_MAX_ITERATIONS: u32 = 8;
+
+type HandlerFuture = Pin<Box<dyn Future<Output = Result<serde_json::Value>> + Send>>;
+type Handler = Arc<dyn Fn(serde_json::Value) -> HandlerFuture + Send + Sync>;
+
+#[derive(Clone)]
+struct RegisteredTool {
+    definition: FunctionDefinition,
+    handler: Handler,
+}
+
+/// Registry of tools backed by async Rust handlers
+#[derive(Clone)]
+pub struct ToolRegistry {
+    tools: Vec<RegisteredTool>,
+    index: HashMap<String, usize>,
+    max_iterations: u32,
+}
+
+impl ToolRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self {
+            tools: Vec::new(),
+            index: HashMap::new(),
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+        }
+    }
+
+    /// Register a handler for a function
+    ///
+    /// The handler receives the parsed JSON arguments. Its output is sent back
+    /// to the model; strings are sent as-is and other values as JSON.
+    /// Registering a name twice replaces the previous handler.
+    pub fn register<F, Fut, T>(mut self, definition: FunctionDefinition, handler: F) -> Self
+    where
+        F: Fn(serde_json::Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+        T: Serialize,
+    {
+        let handler: Handler = Arc::new(move |args| {
+            let future = handler(args);
+            Box::pin(async move { serde_json::to_value(future.await?).map_err(Into::into) })
+        });
+
+        let tool = RegisteredTool { definition, handler };
+        match self.index.get(&tool.definition.name) {
+            Some(&i) => self.tools[i] = tool,
+            None => {
+                self.index.insert(tool.definition.name.clone(), self.tools.len());
+                self.tools.push(tool);
+            }
+        }
+        self
+    }
+
+    /// Register a handler taking typed arguments
+    ///
+    /// The function definition comes from [`DeepSeekTool`] and the model's
+    /// arguments are deserialized into `T` before the handler runs.
+    pub fn register_tool<T, F, Fut, R>(self, handler: F) -> Self
+    where
+        T: DeepSeekTool + DeserializeOwned + Send + 'static,
+        F: Fn(T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R>> + Send + 'static,
+        R: Serialize,
+    {
+        let definition = T::function_definition();
+        let name = definition.name.clone();
+        let handler = Arc::new(handler);
+
+        self.register(definition, move |args| {
+            let handler = handler.clone();
+            let parsed = serde_json::from_value::<T>(args).map_err(|e| {
+                DeepSeekError::ToolError(format!("Invalid arguments for function '{}': {}", name, e))
+            });
+            async move { handler(parsed?).await }
+        })
+    }
+
+    /// Set the maximum number of model round trips
+    pub fn with_max_iterations(mut self, max_iterations: u32) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Maximum number of model round trips
+    pub fn max_iterations(&self) -> u32 {
+        self.max_iterations
+    }
+
+    /// Tool definitions to send with a request
+    pub fn tools(&self) -> Vec<Tool> {
+        self.tools
+            .iter()
+            .map(|t| Tool::function(t.definition.clone()))
+            .collect()
+    }
+
+    /// Number of registered tools
+    pub fn len(&self) -> usize {
+        self.tools.len()
+    }
+
+    /// Check if no tools are registered
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    /// Execute a single tool call
+    ///
+    /// Unknown functions, malformed arguments and handler errors do not fail
+    /// the call; they are reported in the invocation so the model can see
+    /// them and correct itself.
+    pub async fn execute(&self, call: &ToolCall) -> ToolInvocation {
+        let output = match self.prepare(call) {
+            Ok((handler, args)) => handler(args).await,
+            Err(err) => Err(err),
+        };
+        ToolInvocation::from_output(call.clone(), output)
+    }
+
+    /// Execute several tool calls concurrently, preserving their order
+    ///
+    /// Every call gets an invocation; a panicking handler is reported as a
+    /// tool error.
+    pub async fn execute_all(&self, calls: &[ToolCall]) -> Vec<ToolInvocation> {
+        let mut tasks = tokio::task::JoinSet::new();
+        let mut indices = HashMap::new();
+        let mut results: Vec<Option<ToolInvocation>> = vec![None; calls.len()];
+
+        for (i, call) in calls.iter().enumerate() {
+            match self.prepare(call) {
+                Ok((handler, args)) => {
+                    let task = tasks.spawn(async move { handler(args).await });
+                    indices.insert(task.id(), i);
+                }
+                Err(err) => results[i] = Some(ToolInvocation::from_output(call.clone(), Err(err))),
+            }
+        }
+
+        while let Some(joined) = tasks.join_next_with_id().await {
+            let (id, output) = match joined {
+                Ok((id, output)) => (id, output),
+                Err(err) => {
+                    let output = Err(DeepSeekError::ToolError(format!("handler panicked: {}", err)));
+                    (err.id(), output)
+                }
+            };
+            let i = indices[&id];
+            results[i] = Some(ToolInvocation::from_output(calls[i].clone(), output));
+        }
+
+        results.into_iter().flatten().collect()
+    }
+
+    /// Look up the handler and parse the arguments of a call
+    fn prepare(&self, call: &ToolCall) -> Result<(Handler, serde_json::Value)> {
+        let tool = self
+            .index
+            .get(&call.function.name)
+            .map(|&i| &self.tools[i])
+            .ok_or_else(|| {
+                DeepSeekError::ToolError(format!("unknown function '{}'", call.function.name))
+            })?;
+
+        let args = parse_arguments(&call.function.arguments)?;
+        check_required(&tool.definition, &args)?;
+
+        Ok((tool.handler.clone(), args))
+    }
+}
+
+impl Default for ToolRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ToolRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ToolRegistry")
+            .field("tools", &self.tools.iter().map(|t| &t.definition.name).collect::<Vec<_>>())
+            .field("max_iterations", &self.max_iterations)
+            .finish()
+    }
+}
+
+/// Parse a function call's argument string into a JSON object
+fn parse_arguments(arguments: &str) -> Result<serde_json::Value> {
+    let arguments = if arguments.trim().is_empty() { "{}" } else { arguments };
+    let value: serde_json::Value = serde_json::from_str(arguments)
+        .map_err(|e| DeepSeekError::ToolError(format!("arguments are not valid JSON: {}", e)))?;
+
+    if !value.is_object() {
+        return Err(DeepSeekError::ToolError(
+            "arguments must be a JSON object".to_string(),
+        ));
+    }
+
+    Ok(value)
+}
+
+/// Check that every property listed as required by the schema is present
+fn check_required(definition: &FunctionDefinition, args: &serde_json::Value) -> Result<()> {
+    let required = definition
+        .parameters
+        .as_ref()
+        .and_then(|schema| schema.get("required"))
+        .and_then(|required| required.as_array());
+
+    let missing: Vec<&str> = required
+        .into_iter()
+        .flatten()
+        .filter_map(|name| name.as_str())
+        .filter(|name| args.get(name).is_none())
+        .collect();
+
+    if !missing.is_empty() {
+        return Err(DeepSeekError::ToolError(format!(
+            "missing required arguments: {}",
+            missing.join(", ")
+        )));
+    }
+
+    Ok(())
+}
+
+/// Result of running one tool call
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolInvocation {
+    /// The call made by the model
+    pub call: ToolCall,
+
+    /// Content sent back to the model in the tool message
+    pub output: String,
+
+    /// Whether the call failed (the output then describes the error)
+    pub is_error: bool,
+}
+
+impl ToolInvocation {
+    fn from_output(call: ToolCall, output: Result<serde_json::Value>) -> Self {
+        match output {
+            Ok(serde_json::Value::String(text)) => Self { call, output: text, is_error: false },
+            Ok(value) => Self { call, output: value.to_string(), is_error: false },
+            Err(err) => {
+                let message = match err {
+                    DeepSeekError::ToolError(message) => message,
+                    other => other.to_string(),
+                };
+                Self {
+                    call,
+                    output: serde_json::json!({ "error": message }).to_string(),
+                    is_error: true,
+                }
+            }
+        }
+    }
+}
+
+/// One model round trip in a tool loop
+#[derive(Debug, Clone)]
+pub struct ToolStep {
+    /// Response returned by the model
+    pub response: ChatCompletionResponse,
+
+    /// Tool calls executed in reply (empty for the final step)
+    pub invocations: Vec<ToolInvocation>,
+}
+
+/// Outcome of [`DeepSeekClient::run_with_tools`]
+///
+/// [`DeepSeekClient::run_with_tools`]: crate::DeepSeekClient::run_with_tools
+#[derive(Debug, Clone)]
+pub struct ToolRun {
+    /// Final response without tool calls
+    pub response: ChatCompletionResponse,
+
+    /// Full conversation, including assistant tool calls and tool results
+    pub messages: Vec<crate::models::request::Message>,
+
+    /// Every round trip, in order
+    pub steps: Vec<ToolStep>,
+}
+
+impl ToolRun {
+    /// Total number of tool calls executed
+    pub fn tool_call_count(&self) -> usize {
+        self.steps.iter().map(|s| s.invocations.len()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::response::FunctionCall;
+    use serde_json::json;
+
+    fn call(id: &str, name: &str, arguments: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    fn registry() -> ToolRegistry {
+        ToolRegistry::new()
+            .register(
+                FunctionDefinition::new("add").with_parameters(json!({
+                    "type": "object",
+                    "properties": {"a": {"type": "number"}, "b": {"type": "number"}},
+                    "required": ["a", "b"]
+                })),
+                |args| async move { Ok(args["a"].as_f64().unwrap_or(0.0) + args["b"].as_f64().unwrap_or(0.0)) },
+            )
+            .register(FunctionDefinition::new("greet"), |_| async { Ok("hello") })
+            .register(FunctionDefinition::new("fail"), |_| async {
+                Err::<(), _>(DeepSeekError::ToolError("backend down".to_string()))
+            })
+    }
+
+    #[tokio::test]
+    async fn test_execute_success() {
+        let registry = registry();
+        assert_eq!(registry.len(), 3);
+        assert_eq!(registry.tools()[0].function.name, "add");
+
+        let result = registry.execute(&call("1", "add", r#"{"a": 1, "b": 2}"#)).await;
+        assert!(!result.is_error);
+        assert_eq!(result.output, "3.0");
+
+        let result = registry.execute(&call("2", "greet", "")).await;
+        assert_eq!(result.output, "hello");
+    }
+
+    #[tokio::test]
+    async fn test_execute_errors_are_reported() {
+        let registry = registry();
+
+        let result = registry.execute(&call("1", "add", "{not json")).await;
+        assert!(result.is_error);
+        assert!(result.output.contains("not valid JSON"));
+
+        let result = registry.execute(&call("2", "add", r#"{"a": 1}"#)).await;
+        assert!(result.is_error);
+        assert!(result.output.contains("missing required arguments: b"));
+
+        let result = registry.execute(&call("3", "nope", "{}")).await;
+        assert!(result.output.contains("unknown function 'nope'"));
+
+        let result = registry.execute(&call("4", "fail", "{}")).await;
+        assert_eq!(result.output, r#"{"error":"backend down"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_execute_all_preserves_order() {
+        let registry = ToolRegistry::new().register(FunctionDefinition::new("sleep"), |args| async move {
+            let ms = args["ms"].as_u64().unwrap_or(0);
+            tokio::time::sleep(std::time::Duration::from_millis(ms)).await;
+            Ok(ms)
+        });
+
+        let calls = vec![
+            call("a", "sleep", r#"{"ms": 30}"#),
+            call("b", "missing", "{}"),
+            call("c", "sleep", r#"{"ms": 1}"#),
+        ];
+        let results = registry.execute_all(&calls).await;
+
+        let ids: Vec<_> = results.iter().map(|r| r.call.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        assert_eq!(results[0].output, "30");
+        assert!(results[1].is_error);
+        assert_eq!(results[2].output, "1");
+    }
+
+    #[tokio::test]
+    async fn test_execute_all_reports_panics() {
+        let registry = ToolRegistry::new()
+            .register(FunctionDefinition::new("slow"), |_| async {
+                tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+                Ok("done")
+            })
+            .register(FunctionDefinition::new("boom"), |_| async {
+                panic!("handler bug");
+                #[allow(unreachable_code)]
+                Ok(())
+            });
+
+        let calls = vec![call("a", "slow", "{}"), call("b", "boom", "{}")];
+        let results = registry.execute_all(&calls).await;
+
+        let ids: Vec<_> = results.iter().map(|r| r.call.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+        assert_eq!(results[0].output, "done");
+        assert!(results[1].is_error);
+        assert!(results[1].output.contains("handler panicked"));
+    }
+}
@@ -1,16 +1,23 @@
 //! Integration tests for DeepSeek Rust client
 
+use deepseek_rust::breaker::{CircuitBreaker, CircuitState};
+use deepseek_rust::cache::{CacheMode, InMemoryCache};
+use deepseek_rust::context::{KeepLastMessages, SummarizeOlderTurns};
+use deepseek_rust::limiter::{AdaptiveConcurrency, Priority, RateLimiter};
+use deepseek_rust::retry::{Jitter, RetryPolicy};
+use deepseek_rust::tokenizer::Tokenizer;
 use deepseek_rust::{
-    ChatCompletionRequest, DeepSeekClient, DeepSeekConfig, DeepSeekError, Message, Model,
-    Result, Temperature,
+    Budget, ChatCompletionRequest, Conversation, DeepSeekClient, DeepSeekConfig, DeepSeekError,
+    FileConversationStore, Message, Model, ModelPricing, ReasoningPolicy, Temperature,
 };
-use mockito::{mock, server_url, Matcher};
+use mockito::{Matcher, Server};
 use serde_json::json;
+use std::time::Duration;
 
 /// Helper function to create a test client with mock server
-fn create_test_client() -> DeepSeekClient {
+fn create_test_client(server: &Server) -> DeepSeekClient {
     let config = DeepSeekConfig::new("test-api-key")
-        .with_base_url(server_url())
+        .with_base_url(server.url())
         .with_max_retries(1);
     
     DeepSeekClient::new(config).expect("Failed to create test client")
@@ -40,7 +47,7 @@ fn mock_success_response() -> serde_json::Value {
 }
 
 /// Helper function to create a mock error response
-fn mock_error_response(status: u16, message: &str) -> serde_json::Value {
+fn mock_error_response(_status: u16, message: &str) -> serde_json::Value {
     json!({
         "error": {
             "message": message,
@@ -52,16 +59,18 @@ fn mock_error_response(status: u16, message: &str) -> serde_json::Value {
 
 #[tokio::test]
 async fn test_simple_chat_completion() {
-    let _mock = mock("POST", "/chat/completions")
+    let mut server = Server::new_async().await;
+    let _mock = server.mock("POST", "/chat/completions")
         .with_status(200)
         .with_header("content-type", "application/json")
         .with_body(mock_success_response().to_string())
         .match_header("authorization", "Bearer test-api-key")
         .match_header("content-type", "application/json")
         .expect(1)
-        .create();
+        .create_async()
+        .await;
 
-    let client = create_test_client();
+    let client = create_test_client(&server);
     let response = client
         .chat()
         .add_user_message("Hello")
@@ -79,7 +88,8 @@ async fn test_simple_chat_completion() {
 
 #[tokio::test]
 async fn test_chat_with_system_message() {
-    let _mock = mock("POST", "/chat/completions")
+    let mut server = Server::new_async().await;
+    let _mock = server.mock("POST", "/chat/completions")
         .with_status(200)
         .with_header("content-type", "application/json")
         .with_body(mock_success_response().to_string())
@@ -89,9 +99,10 @@ async fn test_chat_with_system_message() {
                 {"role": "user", "content": "Hello"}
             ]
         })))
-        .create();
+        .create_async()
+        .await;
 
-    let client = create_test_client();
+    let client = create_test_client(&server);
     let response = client
         .chat()
         .add_system_message("You are a helpful assistant")
@@ -105,7 +116,8 @@ async fn test_chat_with_system_message() {
 
 #[tokio::test]
 async fn test_chat_with_parameters() {
-    let _mock = mock("POST", "/chat/completions")
+    let mut server = Server::new_async().await;
+    let _mock = server.mock("POST", "/chat/completions")
         .with_status(200)
         .with_header("content-type", "application/json")
         .with_body(mock_success_response().to_string())
@@ -114,9 +126,10 @@ async fn test_chat_with_parameters() {
             "temperature": 0.5,
             "max_tokens": 100
         })))
-        .create();
+        .create_async()
+        .await;
 
-    let client = create_test_client();
+    let client = create_test_client(&server);
     let response = client
         .chat()
         .add_user_message("Write code")
@@ -133,6 +146,7 @@ async fn test_chat_with_parameters() {
 
 #[tokio::test]
 async fn test_reasoning_model_response() {
+    let mut server = Server::new_async().await;
     let reasoning_response = json!({
         "id": "chatcmpl-reasoning",
         "object": "chat.completion",
@@ -155,16 +169,17 @@ async fn test_reasoning_model_response() {
         }
     });
 
-    let _mock = mock("POST", "/chat/completions")
+    let _mock = server.mock("POST", "/chat/completions")
         .with_status(200)
         .with_header("content-type", "application/json")
         .with_body(reasoning_response.to_string())
         .match_body(Matcher::PartialJson(json!({
             "model": "deepseek-reasoner"
         })))
-        .create();
+        .create_async()
+        .await;
 
-    let client = create_test_client();
+    let client = create_test_client(&server);
     let response = client
         .chat()
         .add_user_message("What is 6 * 7?")
@@ -186,13 +201,15 @@ async fn test_reasoning_model_response() {
 
 #[tokio::test]
 async fn test_authentication_error() {
-    let _mock = mock("POST", "/chat/completions")
+    let mut server = Server::new_async().await;
+    let _mock = server.mock("POST", "/chat/completions")
         .with_status(401)
         .with_header("content-type", "application/json")
         .with_body(mock_error_response(401, "Invalid API key").to_string())
-        .create();
+        .create_async()
+        .await;
 
-    let client = create_test_client();
+    let client = create_test_client(&server);
     let result = client
         .chat()
         .add_user_message("Hello")
@@ -200,17 +217,22 @@ async fn test_authentication_error() {
         .await;
 
     assert!(result.is_err());
-    match result.unwrap_err() {
-        DeepSeekError::InvalidParameter(msg) => {
-            assert!(msg.contains("At least one message is required"));
+    let error = result.unwrap_err();
+    assert!(error.is_auth_error());
+    
+    match error {
+        DeepSeekError::ApiError { status, message } => {
+            assert_eq!(status, 401);
+            assert!(message.contains("Invalid API key"));
         }
-        _ => panic!("Expected InvalidParameter error"),
+        _ => panic!("Expected ApiError"),
     }
 }
 
 #[tokio::test]
 async fn test_invalid_temperature() {
-    let client = create_test_client();
+    let server = Server::new_async().await;
+    let client = create_test_client(&server);
     let result = client
         .chat()
         .add_user_message("Hello")
@@ -227,7 +249,8 @@ async fn test_invalid_temperature() {
 
 #[tokio::test]
 async fn test_multi_turn_conversation() {
-    let _mock = mock("POST", "/chat/completions")
+    let mut server = Server::new_async().await;
+    let _mock = server.mock("POST", "/chat/completions")
         .with_status(200)
         .with_header("content-type", "application/json")
         .with_body(mock_success_response().to_string())
@@ -238,9 +261,10 @@ async fn test_multi_turn_conversation() {
                 {"role": "user", "content": "What's my name?"}
             ]
         })))
-        .create();
+        .create_async()
+        .await;
 
-    let client = create_test_client();
+    let client = create_test_client(&server);
     let response = client
         .chat()
         .add_user_message("My name is Alice")
@@ -255,7 +279,8 @@ async fn test_multi_turn_conversation() {
 
 #[tokio::test]
 async fn test_request_with_all_parameters() {
-    let _mock = mock("POST", "/chat/completions")
+    let mut server = Server::new_async().await;
+    let _mock = server.mock("POST", "/chat/completions")
         .with_status(200)
         .with_header("content-type", "application/json")
         .with_body(mock_success_response().to_string())
@@ -270,7 +295,8 @@ async fn test_request_with_all_parameters() {
             "n": 2,
             "user": "test-user"
         })))
-        .create();
+        .create_async()
+        .await;
 
     let request = ChatCompletionRequest::new(vec![Message::user("Hello")])
         .with_model(Model::Chat)
@@ -283,7 +309,7 @@ async fn test_request_with_all_parameters() {
         .with_n(2)
         .with_user("test-user");
 
-    let client = create_test_client();
+    let client = create_test_client(&server);
     let response = client
         .chat_completion(request)
         .await
@@ -294,21 +320,24 @@ async fn test_request_with_all_parameters() {
 
 #[tokio::test]
 async fn test_retry_on_transient_error() {
+    let mut server = Server::new_async().await;
     // First request fails with 500, second succeeds
-    let _mock_fail = mock("POST", "/chat/completions")
+    let _mock_fail = server.mock("POST", "/chat/completions")
         .with_status(500)
         .with_body("Internal Server Error")
         .expect(1)
-        .create();
+        .create_async()
+        .await;
 
-    let _mock_success = mock("POST", "/chat/completions")
+    let _mock_success = server.mock("POST", "/chat/completions")
         .with_status(200)
         .with_header("content-type", "application/json")
         .with_body(mock_success_response().to_string())
         .expect(1)
-        .create();
+        .create_async()
+        .await;
 
-    let client = create_test_client();
+    let client = create_test_client(&server);
     let response = client
         .chat()
         .add_user_message("Hello")
@@ -319,15 +348,226 @@ async fn test_retry_on_transient_error() {
     assert!(response.choices[0].message.content.is_some());
 }
 
+/// Client retrying up to `max_retries` times with a fixed delay
+fn create_retrying_client(server: &Server, max_retries: u32, policy: RetryPolicy) -> DeepSeekClient {
+    let config = DeepSeekConfig::new("test-api-key")
+        .with_base_url(server.url())
+        .with_max_retries(max_retries)
+        .with_retry_policy(policy);
+    DeepSeekClient::new(config).unwrap()
+}
+
+#[tokio::test]
+async fn test_retry_reports_attempts() {
+    let mut server = Server::new_async().await;
+    let mock = server.mock("POST", "/chat/completions")
+        .with_status(502)
+        .with_body("Bad Gateway")
+        .expect(3)
+        .create_async()
+        .await;
+
+    let policy = RetryPolicy::new().with_base_delay(Duration::from_millis(10));
+    let client = create_retrying_client(&server, 2, policy);
+    let error = client.chat().add_user_message("Hello").send().await.unwrap_err();
+
+    assert!(matches!(error, DeepSeekError::RetriesExhausted { attempts: 3, .. }));
+    assert_eq!(error.attempts(), 3);
+    assert_eq!(error.status_code(), Some(502));
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_retry_honours_retry_after() {
+    let mut server = Server::new_async().await;
+    let throttled = server.mock("POST", "/chat/completions")
+        .with_status(429)
+        .with_header("retry-after", "0")
+        .with_body(mock_error_response(429, "Rate limit exceeded").to_string())
+        .expect(1)
+        .create_async()
+        .await;
+    let success = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(mock_success_response().to_string())
+        .expect(1)
+        .create_async()
+        .await;
+
+    // Without the header the retry would wait 20 seconds
+    let policy = RetryPolicy::new()
+        .with_base_delay(Duration::from_secs(20))
+        .with_jitter(Jitter::None);
+    let client = create_retrying_client(&server, 1, policy);
+    let started = std::time::Instant::now();
+    client.chat().add_user_message("Hello").send().await.unwrap();
+
+    assert!(started.elapsed() < Duration::from_secs(5));
+    throttled.assert_async().await;
+    success.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_retry_rules_and_deadline() {
+    let mut server = Server::new_async().await;
+    let mock = server.mock("POST", "/chat/completions")
+        .with_status(503)
+        .with_body("Service Unavailable")
+        .expect(2)
+        .create_async()
+        .await;
+
+    let never_503 = RetryPolicy::new().with_status_rule(503, false);
+    let client = create_retrying_client(&server, 3, never_503);
+    let error = client.chat().add_user_message("Hello").send().await.unwrap_err();
+    assert!(matches!(error, DeepSeekError::ApiError { status: 503, .. }));
+
+    // The first retry would start after the deadline
+    let short_deadline = RetryPolicy::new()
+        .with_base_delay(Duration::from_secs(1))
+        .with_jitter(Jitter::None)
+        .with_deadline(Duration::from_millis(100));
+    let client = create_retrying_client(&server, 3, short_deadline);
+    let error = client.chat().add_user_message("Hello").send().await.unwrap_err();
+    assert_eq!(error.attempts(), 1);
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_sub_second_timeout_is_reported() {
+    let mut server = Server::new_async().await;
+    let slow = |w: &mut dyn std::io::Write| {
+        std::thread::sleep(Duration::from_millis(500));
+        w.write_all(mock_success_response().to_string().as_bytes())
+    };
+    let _mock = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_chunked_body(slow)
+        .create_async()
+        .await;
+
+    let client = create_retrying_client(&server, 0, RetryPolicy::new());
+    let error = client
+        .chat()
+        .add_user_message("Hello")
+        .with_timeout(Duration::from_millis(100))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(matches!(error, DeepSeekError::TimeoutError(1)));
+}
+
+#[tokio::test]
+async fn test_no_retry_after_partial_body() {
+    let mut server = Server::new_async().await;
+    let truncated = |w: &mut dyn std::io::Write| {
+        w.write_all(b"{\"id\": \"chatcmpl-123\",")?;
+        std::thread::sleep(Duration::from_millis(50));
+        Err(std::io::Error::other("connection dropped"))
+    };
+    let chat = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_chunked_body(truncated)
+        .expect(1)
+        .create_async()
+        .await;
+    let models = server.mock("GET", "/models")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_chunked_body(truncated)
+        .expect(3)
+        .create_async()
+        .await;
+
+    let policy = RetryPolicy::new().with_base_delay(Duration::from_millis(10));
+    let client = create_retrying_client(&server, 2, policy);
+
+    // The POST may already have been processed, so it is not retried
+    let error = client.chat().add_user_message("Hello").send().await.unwrap_err();
+    assert!(matches!(error, DeepSeekError::HttpError(_)));
+
+    // GET is idempotent and retried
+    let error = client.list_models().await.unwrap_err();
+    assert_eq!(error.attempts(), 3);
+
+    chat.assert_async().await;
+    models.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_circuit_breaker_fails_fast() {
+    let mut server = Server::new_async().await;
+    let outage = server.mock("POST", "/chat/completions")
+        .with_status(503)
+        .with_body("Service Unavailable")
+        .expect(2)
+        .create_async()
+        .await;
+
+    let breaker = CircuitBreaker::new()
+        .with_min_requests(2)
+        .with_failure_ratio(0.5)
+        .with_open_duration(Duration::from_secs(60));
+    let config = DeepSeekConfig::new("test-api-key")
+        .with_base_url(server.url())
+        .with_max_retries(0)
+        .with_circuit_breaker(breaker.clone());
+    let client = DeepSeekClient::new(config).unwrap();
+
+    for _ in 0..2 {
+        let error = client.chat().add_user_message("Hello").send().await.unwrap_err();
+        assert_eq!(error.status_code(), Some(503));
+    }
+    assert_eq!(breaker.state(), CircuitState::Open);
+
+    // The API is not called while the circuit is open
+    let error = client.chat().add_user_message("Hello").send().await.unwrap_err();
+    assert!(matches!(error, DeepSeekError::CircuitOpen(retry_in) if retry_in > Duration::from_secs(50)));
+    outage.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_circuit_breaker_stops_retries() {
+    let mut server = Server::new_async().await;
+    let outage = server.mock("POST", "/chat/completions")
+        .with_status(503)
+        .with_body("Service Unavailable")
+        .expect(1)
+        .create_async()
+        .await;
+
+    let breaker = CircuitBreaker::new()
+        .with_min_requests(1)
+        .with_open_duration(Duration::from_secs(60));
+    let config = DeepSeekConfig::new("test-api-key")
+        .with_base_url(server.url())
+        .with_max_retries(3)
+        .with_retry_policy(RetryPolicy::new().with_base_delay(Duration::from_millis(10)))
+        .with_circuit_breaker(breaker);
+    let client = DeepSeekClient::new(config).unwrap();
+
+    // The first failure opens the circuit, so the retry is never sent
+    let error = client.chat().add_user_message("Hello").send().await.unwrap_err();
+    assert!(matches!(error, DeepSeekError::CircuitOpen(_)));
+    assert!(error.is_circuit_open());
+    assert_eq!(error.attempts(), 1);
+    outage.assert_async().await;
+}
+
 #[tokio::test]
 async fn test_connection_test() {
-    let _mock = mock("POST", "/chat/completions")
+    let mut server = Server::new_async().await;
+    let _mock = server.mock("POST", "/chat/completions")
         .with_status(200)
         .with_header("content-type", "application/json")
         .with_body(mock_success_response().to_string())
-        .create();
+        .create_async()
+        .await;
 
-    let client = create_test_client();
+    let client = create_test_client(&server);
     let result = client.test_connection().await;
     
     assert!(result.is_ok());
@@ -335,7 +575,8 @@ async fn test_connection_test() {
 
 #[tokio::test]
 async fn test_builder_pattern_chaining() {
-    let client = create_test_client();
+    let server = Server::new_async().await;
+    let client = create_test_client(&server);
     
     // Test that all builder methods can be chained
     let builder = client
@@ -405,16 +646,18 @@ async fn test_from_env() {
 // Performance test
 #[tokio::test]
 async fn test_concurrent_requests() {
+    let mut server = Server::new_async().await;
     use futures::future::join_all;
     
-    let _mock = mock("POST", "/chat/completions")
+    let _mock = server.mock("POST", "/chat/completions")
         .with_status(200)
         .with_header("content-type", "application/json")
         .with_body(mock_success_response().to_string())
         .expect_at_least(3)
-        .create();
+        .create_async()
+        .await;
 
-    let client = create_test_client();
+    let client = create_test_client(&server);
     
     let futures = vec![
         client.chat().add_user_message("Hello 1").send(),
@@ -427,28 +670,96 @@ async fn test_concurrent_requests() {
     for result in results {
         assert!(result.is_ok());
     }
-}());
-    let error = result.unwrap_err();
-    assert!(error.is_auth_error());
-    
-    match error {
-        DeepSeekError::ApiError { status, message } => {
-            assert_eq!(status, 401);
-            assert!(message.contains("Invalid API key"));
-        }
-        _ => panic!("Expected ApiError"),
+}
+
+#[tokio::test]
+async fn test_rate_limiter_queues_requests() {
+    let mut server = Server::new_async().await;
+    let mock = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_chunked_body(|w| {
+            std::thread::sleep(Duration::from_millis(20));
+            w.write_all(mock_success_response().to_string().as_bytes())
+        })
+        .expect(3)
+        .create_async()
+        .await;
+
+    let limiter = RateLimiter::new()
+        .with_requests_per_minute(600)
+        .with_tokens_per_minute(100_000)
+        .with_max_concurrency(1);
+    let config = DeepSeekConfig::new("test-api-key")
+        .with_base_url(server.url())
+        .with_rate_limiter(limiter.clone());
+    let client = DeepSeekClient::new(config).unwrap();
+
+    let results = futures::future::join_all([
+        client.chat().add_user_message("Batch").with_priority(Priority::Low).send(),
+        client.chat().add_user_message("Hello").send(),
+        client.chat().add_user_message("Urgent").with_priority(Priority::High).send(),
+    ])
+    .await;
+
+    assert!(results.iter().all(Result::is_ok));
+    assert_eq!(limiter.in_flight(), 0);
+    assert_eq!(limiter.queue_depth(), 0);
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_adaptive_concurrency_backs_off_on_rate_limit() {
+    let mut server = Server::new_async().await;
+    let throttled = server.mock("POST", "/chat/completions")
+        .with_status(429)
+        .with_body(mock_error_response(429, "Rate limit exceeded").to_string())
+        .expect(1)
+        .create_async()
+        .await;
+    let success = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(mock_success_response().to_string())
+        .expect(3)
+        .create_async()
+        .await;
+
+    let limiter = RateLimiter::new()
+        .with_adaptive_concurrency(AdaptiveConcurrency::new().with_initial_limit(4));
+    let config = DeepSeekConfig::new("test-api-key")
+        .with_base_url(server.url())
+        .with_max_retries(0)
+        .with_rate_limiter(limiter.clone());
+    let client = DeepSeekClient::new(config).unwrap();
+    assert_eq!(limiter.concurrency_limit(), Some(4));
+
+    let error = client.chat().add_user_message("Hello").send().await.unwrap_err();
+    assert!(error.is_rate_limit());
+    assert_eq!(limiter.concurrency_limit(), Some(2));
+
+    // Healthy responses grow the limit again, by one per `limit` responses
+    for _ in 0..3 {
+        client.chat().add_user_message("Hello").send().await.unwrap();
     }
+    assert_eq!(limiter.concurrency_limit(), Some(3));
+    assert_eq!(limiter.queue_depth(), 0);
+
+    throttled.assert_async().await;
+    success.assert_async().await;
 }
 
 #[tokio::test]
 async fn test_rate_limit_error() {
-    let _mock = mock("POST", "/chat/completions")
+    let mut server = Server::new_async().await;
+    let _mock = server.mock("POST", "/chat/completions")
         .with_status(429)
         .with_header("content-type", "application/json")
         .with_body(mock_error_response(429, "Rate limit exceeded").to_string())
-        .create();
+        .create_async()
+        .await;
 
-    let client = create_test_client();
+    let client = create_test_client(&server);
     let result = client
         .chat()
         .add_user_message("Hello")
@@ -462,7 +773,1082 @@ async fn test_rate_limit_error() {
 
 #[tokio::test]
 async fn test_empty_messages_validation() {
-    let client = create_test_client();
+    let server = Server::new_async().await;
+    let client = create_test_client(&server);
     let result = client.chat().send().await;
 
-    assert!(result.is_err
\ No newline at end of file
+    assert!(result.is_err());
+    match result.unwrap_err() {
+        DeepSeekError::InvalidParameter(msg) => {
+            assert!(msg.contains("At least one message is required"));
+        }
+        _ => panic!("Expected InvalidParameter error"),
+    }
+}
+
+#[tokio::test]
+async fn test_chat_with_extra_headers() {
+    let mut server = Server::new_async().await;
+    let _mock = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(mock_success_response().to_string())
+        .match_header("x-request-id", "req-42")
+        .match_body(Matcher::PartialJson(json!({
+            "top_p": 0.5,
+            "stop": ["END"]
+        })))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = create_test_client(&server);
+    let response = client
+        .chat()
+        .add_user_message("Hello")
+        .with_top_p(0.5)
+        .with_stop(["END"])
+        .with_timeout(std::time::Duration::from_secs(5))
+        .with_header("x-request-id", "req-42")
+        .expect("Header should be valid")
+        .send()
+        .await
+        .expect("Request should succeed");
+
+    assert!(response.choices[0].message.content.is_some());
+}
+
+#[cfg(feature = "streaming")]
+#[tokio::test]
+async fn test_streaming_chat_completion() {
+    use futures::StreamExt;
+
+    let chunk = |content: &str| {
+        json!({
+            "id": "chatcmpl-stream",
+            "object": "chat.completion.chunk",
+            "created": 1677652288,
+            "model": "deepseek-chat",
+            "choices": [{"index": 0, "delta": {"content": content}}]
+        })
+    };
+    let body = format!(
+        "data: {}\n\n: keep-alive\n\ndata: {}\n\ndata: [DONE]\n\n",
+        chunk("Hello"),
+        chunk(" world")
+    );
+
+    let mut server = Server::new_async().await;
+    let _mock = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_body(body)
+        .match_body(Matcher::PartialJson(json!({"stream": true})))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = create_test_client(&server);
+    let mut stream = client
+        .chat()
+        .add_user_message("Hello")
+        .stream()
+        .await
+        .expect("Stream should start");
+
+    let mut content = String::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.expect("Chunk should parse");
+        content.push_str(chunk.choices[0].delta.content.as_deref().unwrap_or_default());
+    }
+
+    assert_eq!(content, "Hello world");
+}
+
+#[tokio::test]
+async fn test_tool_call_round_trip() {
+    use deepseek_rust::{FunctionDefinition, Tool, ToolChoice};
+
+    let tool_call_response = json!({
+        "id": "chatcmpl-tool",
+        "object": "chat.completion",
+        "created": 1677652288,
+        "model": "deepseek-chat",
+        "choices": [{
+            "index": 0,
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}
+                }]
+            },
+            "finish_reason": "tool_calls"
+        }]
+    });
+
+    let mut server = Server::new_async().await;
+    let _mock = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(tool_call_response.to_string())
+        .match_body(Matcher::PartialJson(json!({
+            "tools": [{
+                "type": "function",
+                "function": {
+                    "name": "get_weather",
+                    "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}
+                }
+            }],
+            "tool_choice": "auto"
+        })))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let weather = Tool::function(
+        FunctionDefinition::new("get_weather").with_parameters(json!({
+            "type": "object",
+            "properties": {"city": {"type": "string"}}
+        })),
+    );
+
+    let client = create_test_client(&server);
+    let response = client
+        .chat()
+        .add_user_message("What's the weather in Paris?")
+        .add_tool(weather.clone())
+        .with_tool_choice(ToolChoice::Auto)
+        .send()
+        .await
+        .expect("Request should succeed");
+
+    let calls = response.choices[0].message.tool_calls.clone().unwrap();
+    assert_eq!(calls[0].function.name, "get_weather");
+
+    // The tool result can be sent back in a follow-up request
+    let follow_up = client
+        .chat()
+        .add_user_message("What's the weather in Paris?")
+        .add_message(Message::assistant_with_tool_calls("", calls))
+        .add_tool_message("call_1", "{\"temperature\": 21}")
+        .add_tool(weather)
+        .build();
+    assert!(follow_up.is_ok());
+}
+
+#[tokio::test]
+async fn test_run_with_tools() {
+    use deepseek_rust::{FunctionDefinition, ToolRegistry};
+
+    let tool_call_response = json!({
+        "id": "chatcmpl-tool",
+        "object": "chat.completion",
+        "created": 1677652288,
+        "model": "deepseek-chat",
+        "choices": [{
+            "index": 0,
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [
+                    {
+                        "id": "call_1",
+                        "type": "function",
+                        "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}
+                    },
+                    {
+                        "id": "call_2",
+                        "type": "function",
+                        "function": {"name": "get_weather", "arguments": "{}"}
+                    }
+                ]
+            },
+            "finish_reason": "tool_calls"
+        }]
+    });
+
+    let mut server = Server::new_async().await;
+    let _final_mock = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(mock_success_response().to_string())
+        .match_body(Matcher::PartialJson(json!({
+            "messages": [
+                {"role": "user", "content": "Weather in Paris?"},
+                {"role": "assistant", "content": ""},
+                {"role": "tool", "tool_call_id": "call_1", "content": "{\"temperature\":21}"},
+                {"role": "tool", "tool_call_id": "call_2", "content": "{\"error\":\"missing required arguments: city\"}"}
+            ]
+        })))
+        .expect(1)
+        .create_async()
+        .await;
+    let _tool_mock = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(tool_call_response.to_string())
+        .expect(1)
+        .create_async()
+        .await;
+
+    let registry = ToolRegistry::new().register(
+        FunctionDefinition::new("get_weather").with_parameters(json!({
+            "type": "object",
+            "properties": {"city": {"type": "string"}},
+            "required": ["city"]
+        })),
+        |_args| async move { Ok(json!({"temperature": 21})) },
+    );
+
+    let client = create_test_client(&server);
+    let run = client
+        .run_with_tools(ChatCompletionRequest::from_user_message("Weather in Paris?"), &registry)
+        .await
+        .expect("Tool loop should finish");
+
+    assert_eq!(run.steps.len(), 2);
+    assert_eq!(run.tool_call_count(), 2);
+    assert!(run.steps[0].invocations[1].is_error);
+    assert_eq!(run.response.get_content(), Some("Hello! How can I help you today?"));
+    assert_eq!(run.messages.len(), 5);
+}
+
+#[tokio::test]
+async fn test_run_with_tools_iteration_cap() {
+    use deepseek_rust::{FunctionDefinition, ToolRegistry};
+
+    let tool_call_response = json!({
+        "id": "chatcmpl-tool",
+        "object": "chat.completion",
+        "created": 1677652288,
+        "model": "deepseek-chat",
+        "choices": [{
+            "index": 0,
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": {"name": "ping", "arguments": "{}"}
+                }]
+            },
+            "finish_reason": "tool_calls"
+        }]
+    });
+
+    let mut server = Server::new_async().await;
+    let _mock = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(tool_call_response.to_string())
+        .expect(2)
+        .create_async()
+        .await;
+
+    let registry = ToolRegistry::new()
+        .register(FunctionDefinition::new("ping"), |_| async { Ok("pong") })
+        .with_max_iterations(2);
+
+    let client = create_test_client(&server);
+    let result = client
+        .run_with_tools(ChatCompletionRequest::from_user_message("Ping forever"), &registry)
+        .await;
+
+    assert!(matches!(result, Err(DeepSeekError::ToolError(_))));
+}
+
+#[tokio::test]
+async fn test_send_json_with_repair() {
+    #[derive(Debug, serde::Deserialize, PartialEq)]
+    struct Colors {
+        colors: Vec<String>,
+    }
+
+    let reply = |content: &str| {
+        json!({
+            "id": "chatcmpl-json",
+            "object": "chat.completion",
+            "created": 1677652288,
+            "model": "deepseek-chat",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": content},
+                "finish_reason": "stop"
+            }]
+        })
+    };
+
+    let mut server = Server::new_async().await;
+    let _repaired = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(reply("```json\n{\"colors\": [\"red\", \"blue\"]}\n```").to_string())
+        .match_body(Matcher::Regex("could not be parsed as the expected JSON".to_string()))
+        .expect(1)
+        .create_async()
+        .await;
+    let _invalid = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(reply("{\"colours\": []}").to_string())
+        .match_body(Matcher::PartialJson(json!({"response_format": {"type": "json_object"}})))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = create_test_client(&server);
+    let colors: Colors = client
+        .chat()
+        .add_system_message("Reply in JSON with a `colors` array")
+        .add_user_message("List two colors")
+        .with_json_repair_attempts(1)
+        .send_json()
+        .await
+        .expect("Repaired JSON should parse");
+
+    assert_eq!(colors.colors, vec!["red", "blue"]);
+
+    let missing_mention = client
+        .chat()
+        .add_user_message("List two colors")
+        .send_json::<Colors>()
+        .await;
+    assert!(matches!(missing_mention, Err(DeepSeekError::InvalidParameter(_))));
+}
+
+#[tokio::test]
+async fn test_send_json_repairs_empty_content() {
+    let reply = |content: serde_json::Value| {
+        json!({
+            "id": "chatcmpl-json",
+            "object": "chat.completion",
+            "created": 1677652288,
+            "model": "deepseek-chat",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": content},
+                "finish_reason": "stop"
+            }]
+        })
+    };
+
+    let mut server = Server::new_async().await;
+    let repaired = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(reply(json!("{\"ok\": true}")).to_string())
+        .match_body(Matcher::Regex("could not be parsed as the expected JSON".to_string()))
+        .expect(1)
+        .create_async()
+        .await;
+    let empty = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(reply(serde_json::Value::Null).to_string())
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = create_test_client(&server);
+    let value: serde_json::Value = client
+        .chat()
+        .add_user_message("Reply in JSON with an `ok` flag")
+        .with_json_repair_attempts(1)
+        .send_json()
+        .await
+        .expect("Empty content should be re-asked");
+
+    assert_eq!(value, json!({"ok": true}));
+    repaired.assert_async().await;
+    empty.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_logprobs_round_trip() {
+    let mut server = Server::new_async().await;
+    let _m = server.mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({"logprobs": true, "top_logprobs": 2})))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(json!({
+            "id": "chatcmpl-logprobs",
+            "object": "chat.completion",
+            "created": 1677652288,
+            "model": "deepseek-chat",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "positive"},
+                "finish_reason": "stop",
+                "logprobs": {
+                    "content": [{
+                        "token": "positive",
+                        "logprob": -0.05,
+                        "bytes": [112, 111, 115, 105, 116, 105, 118, 101],
+                        "top_logprobs": [
+                            {"token": "positive", "logprob": -0.05, "bytes": null},
+                            {"token": "negative", "logprob": -3.0, "bytes": null}
+                        ]
+                    }]
+                }
+            }]
+        }).to_string())
+        .create_async()
+        .await;
+
+    let client = create_test_client(&server);
+    let response = client
+        .chat()
+        .add_user_message("Classify the sentiment: I love it")
+        .with_top_logprobs(2)
+        .send()
+        .await
+        .expect("Request should succeed");
+
+    let logprobs = response.logprobs().expect("Logprobs should be present");
+    assert_eq!(logprobs.content[0].top_logprobs.len(), 2);
+    assert!(logprobs.sequence_probability() > 0.9);
+}
+
+#[tokio::test]
+async fn test_fim_completion() {
+    let mut server = Server::new_async().await;
+    let _mock = server.mock("POST", "/beta/completions")
+        .match_body(Matcher::PartialJson(json!({
+            "model": "deepseek-coder",
+            "prompt": "def fib(n):\n",
+            "suffix": "\n    return fib(n - 1) + fib(n - 2)",
+            "max_tokens": 64
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(json!({
+            "id": "cmpl-fim",
+            "object": "text_completion",
+            "created": 1677652288,
+            "model": "deepseek-coder",
+            "choices": [{
+                "index": 0,
+                "text": "    if n < 2:\n        return n",
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 18, "completion_tokens": 12, "total_tokens": 30}
+        }).to_string())
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = create_test_client(&server);
+    let response = client
+        .fim()
+        .with_prompt("def fib(n):\n")
+        .with_suffix("\n    return fib(n - 1) + fib(n - 2)")
+        .with_max_tokens(64)
+        .send()
+        .await
+        .expect("FIM request should succeed");
+
+    assert_eq!(response.get_text(), Some("    if n < 2:\n        return n"));
+    assert_eq!(response.total_tokens(), Some(30));
+}
+
+#[cfg(feature = "streaming")]
+#[tokio::test]
+async fn test_fim_completion_stream() {
+    use futures::StreamExt;
+
+    let chunk = |text: &str| {
+        json!({
+            "id": "cmpl-fim",
+            "object": "text_completion",
+            "created": 1677652288,
+            "model": "deepseek-coder",
+            "choices": [{"index": 0, "text": text}]
+        })
+    };
+    let body = format!("data: {}\n\ndata: {}\n\ndata: [DONE]\n\n", chunk("return "), chunk("a + b"));
+
+    let mut server = Server::new_async().await;
+    let _mock = server.mock("POST", "/beta/completions")
+        .match_body(Matcher::PartialJson(json!({"stream": true})))
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_body(body)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = create_test_client(&server);
+    let mut stream = client
+        .fim()
+        .with_prompt("fn add(a: i32, b: i32) -> i32 { ")
+        .with_suffix(" }")
+        .stream()
+        .await
+        .expect("Stream should start");
+
+    let mut text = String::new();
+    while let Some(chunk) = stream.next().await {
+        text.push_str(&chunk.expect("Chunk should parse").choices[0].text);
+    }
+    assert_eq!(text, "return a + b");
+}
+
+#[tokio::test]
+async fn test_prefix_completion_uses_beta_endpoint() {
+    let mut server = Server::new_async().await;
+    let _mock = server.mock("POST", "/beta/chat/completions")
+        .match_body(Matcher::PartialJson(json!({
+            "messages": [
+                {"role": "user", "content": "Print hello in Rust"},
+                {"role": "assistant", "content": "```rust\n", "prefix": true}
+            ]
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(json!({
+            "id": "chatcmpl-prefix",
+            "object": "chat.completion",
+            "created": 1677652288,
+            "model": "deepseek-chat",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "fn main() {\n    println!(\"hello\");\n}\n"},
+                "finish_reason": "stop"
+            }]
+        }).to_string())
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = create_test_client(&server);
+    let response = client
+        .chat()
+        .add_user_message("Print hello in Rust")
+        .continue_from("```rust\n")
+        .add_stop("```")
+        .send()
+        .await
+        .expect("Prefix completion should succeed");
+
+    assert!(response.get_content().unwrap().starts_with("fn main()"));
+}
+
+#[tokio::test]
+async fn test_reasoning_is_not_sent_back_in_history() {
+    let mut server = Server::new_async().await;
+    let rejected = server.mock("POST", "/chat/completions")
+        .match_body(Matcher::Regex("reasoning_content".to_string()))
+        .with_status(400)
+        .with_body(json!({"error": {"message": "reasoning_content is not allowed", "type": "invalid_request_error"}}).to_string())
+        .expect(0)
+        .create_async()
+        .await;
+    let accepted = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(json!({
+            "id": "chatcmpl-reasoner",
+            "object": "chat.completion",
+            "created": 1677652288,
+            "model": "deepseek-reasoner",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": "9.11 is smaller.",
+                    "reasoning_content": "Compare the decimals..."
+                },
+                "finish_reason": "stop"
+            }]
+        }).to_string())
+        .expect(2)
+        .create_async()
+        .await;
+
+    let client = create_test_client(&server);
+    let mut messages = vec![Message::user("Which is larger, 9.11 or 9.8?")];
+    let request = ChatCompletionRequest::new(messages.clone()).with_model(Model::Reasoner);
+    let response = client.chat_completion(request).await.expect("First turn should succeed");
+
+    let audited = response
+        .clone()
+        .into_message_with(ReasoningPolicy::Keep)
+        .unwrap();
+    assert_eq!(audited.reasoning_content.as_deref(), Some("Compare the decimals..."));
+    assert!(response.into_message().unwrap().reasoning_content.is_none());
+
+    messages.push(audited);
+    messages.push(Message::user("Are you sure?"));
+    let request = ChatCompletionRequest::new(messages).with_model(Model::Reasoner);
+    client.chat_completion(request).await.expect("Second turn should succeed");
+
+    rejected.assert_async().await;
+    accepted.assert_async().await;
+}
+
+fn conversation_reply(content: &str) -> String {
+    json!({
+        "id": "chatcmpl-conversation",
+        "object": "chat.completion",
+        "created": 1677652288,
+        "model": "deepseek-reasoner",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": content, "reasoning_content": "Thinking..."},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+    })
+    .to_string()
+}
+
+#[tokio::test]
+async fn test_conversation_keeps_history() {
+    let mut server = Server::new_async().await;
+    let second = server.mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({
+            "messages": [
+                {"role": "system", "content": "Be brief"},
+                {"role": "user", "content": "My name is Alice"},
+                {"role": "assistant", "content": "Hi Alice"},
+                {"role": "user", "content": "What's my name?"}
+            ]
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(conversation_reply("Alice"))
+        .expect(1)
+        .create_async()
+        .await;
+    let first = server.mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({
+            "model": "deepseek-reasoner",
+            "messages": [
+                {"role": "system", "content": "Be brief"},
+                {"role": "user", "content": "My name is Alice"}
+            ]
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(conversation_reply("Hi Alice"))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = create_test_client(&server);
+    let mut conversation = client
+        .conversation()
+        .with_model(Model::Reasoner)
+        .with_system_prompt("Be brief");
+
+    assert_eq!(conversation.ask("My name is Alice").await.unwrap(), "Hi Alice");
+    assert_eq!(conversation.ask("What's my name?").await.unwrap(), "Alice");
+
+    assert_eq!(conversation.turns(), 2);
+    assert!(conversation.messages().iter().all(|m| m.reasoning_content.is_none()));
+    assert_eq!(conversation.last_response().unwrap().get_reasoning(), Some("Thinking..."));
+    assert_eq!(conversation.usage().total_tokens, 30);
+
+    first.assert_async().await;
+    second.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_conversation_regenerate_and_edit() {
+    let mut server = Server::new_async().await;
+    let _edited = server.mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({
+            "messages": [{"role": "user", "content": "Tell me a joke about Rust"}]
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(conversation_reply("The borrow checker said no."))
+        .expect(1)
+        .create_async()
+        .await;
+    let _failing = server.mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({
+            "messages": [{"role": "user", "content": "Tell me a joke about C"}]
+        })))
+        .with_status(400)
+        .with_body(json!({"error": {"message": "Bad request", "type": "invalid_request_error"}}).to_string())
+        .create_async()
+        .await;
+    let _original = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(conversation_reply("Why did the programmer quit?"))
+        .expect(2)
+        .create_async()
+        .await;
+
+    let client = create_test_client(&server);
+    let mut conversation = client.conversation();
+    assert!(conversation.regenerate().await.is_err());
+
+    conversation.ask("Tell me a joke").await.unwrap();
+    conversation.regenerate().await.unwrap();
+    assert_eq!(conversation.messages().len(), 2);
+    assert_eq!(conversation.usage().total_tokens, 30);
+
+    assert!(conversation.edit_last_user_message("Tell me a joke about C").await.is_err());
+    assert_eq!(conversation.messages()[0], Message::user("Tell me a joke"));
+    assert_eq!(conversation.messages().len(), 2);
+
+    let reply = conversation
+        .edit_last_user_message("Tell me a joke about Rust")
+        .await
+        .unwrap();
+    assert_eq!(reply, "The borrow checker said no.");
+    assert_eq!(conversation.messages()[0], Message::user("Tell me a joke about Rust"));
+    assert_eq!(conversation.turns(), 1);
+}
+
+#[tokio::test]
+async fn test_chat_builder_context_strategy() {
+    let mut server = Server::new_async().await;
+    let mock = server.mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({
+            "messages": [
+                {"role": "system", "content": "Be brief"},
+                {"role": "user", "content": "Third"}
+            ]
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(mock_success_response().to_string())
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = create_test_client(&server);
+    client
+        .chat()
+        .add_system_message("Be brief")
+        .add_user_message("First")
+        .add_assistant_message("1")
+        .add_user_message("Second")
+        .add_assistant_message("2")
+        .add_user_message("Third")
+        .with_context_strategy(KeepLastMessages::new(1))
+        .send()
+        .await
+        .expect("Trimmed request should succeed");
+
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_conversation_summarizes_older_turns() {
+    let mut server = Server::new_async().await;
+    let summary = server.mock("POST", "/chat/completions")
+        .match_body(Matcher::Regex("Summarize the following conversation".to_string()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(conversation_reply("The user is called Alice."))
+        .expect(1)
+        .create_async()
+        .await;
+    let summarized = server.mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({
+            "messages": [
+                {"role": "system", "content": "Be brief"},
+                {"role": "system", "content": "Summary of the earlier conversation:\nThe user is called Alice."},
+                {"role": "user", "content": "What's my name?"}
+            ]
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(conversation_reply("Alice"))
+        .expect(1)
+        .create_async()
+        .await;
+    let first = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(conversation_reply("Hi Alice"))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = create_test_client(&server);
+    let mut conversation = client
+        .conversation()
+        .with_system_prompt("Be brief")
+        .with_context_strategy(SummarizeOlderTurns::new(1));
+
+    conversation.ask("My name is Alice").await.unwrap();
+    assert_eq!(conversation.ask("What's my name?").await.unwrap(), "Alice");
+    assert_eq!(conversation.messages().len(), 4);
+
+    first.assert_async().await;
+    summary.assert_async().await;
+    summarized.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_summarize_keeps_latest_turn() {
+    let mut server = Server::new_async().await;
+    let summary = server.mock("POST", "/chat/completions")
+        .match_body(Matcher::Regex("Summarize the following conversation".to_string()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(conversation_reply("The user is called Alice."))
+        .expect(1)
+        .create_async()
+        .await;
+    let summarized = server.mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({
+            "messages": [
+                {"role": "system", "content": "Summary of the earlier conversation:\nThe user is called Alice."},
+                {"role": "user", "content": "What's my name?"}
+            ]
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(conversation_reply("Alice"))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = create_test_client(&server);
+    let response = client
+        .chat()
+        .add_user_message("My name is Alice")
+        .add_assistant_message("Hi Alice")
+        .add_user_message("What's my name?")
+        .with_context_strategy(SummarizeOlderTurns::new(0))
+        .send()
+        .await
+        .expect("The newest question should be sent");
+
+    assert_eq!(response.get_content(), Some("Alice"));
+    summary.assert_async().await;
+    summarized.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_conversation_survives_restore() {
+    let mut server = Server::new_async().await;
+    let resumed = server.mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({
+            "messages": [
+                {"role": "user", "content": "Remember 42"},
+                {"role": "assistant", "content": "Noted"},
+                {"role": "user", "content": "Which number?"}
+            ]
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(conversation_reply("42"))
+        .expect(1)
+        .create_async()
+        .await;
+    let _first = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(conversation_reply("Noted"))
+        .create_async()
+        .await;
+
+    let dir = std::env::temp_dir().join(format!("deepseek-store-{}", std::process::id()));
+    let store = FileConversationStore::new(&dir).unwrap();
+
+    let client = create_test_client(&server);
+    let mut conversation = client.conversation().with_id("resume-test");
+    conversation.ask("Remember 42").await.unwrap();
+    store.save(&conversation.snapshot()).unwrap();
+    drop(conversation);
+
+    let snapshot = store.load("resume-test").unwrap();
+    let mut restored = Conversation::restore(client, snapshot);
+    assert_eq!(restored.usage().total_tokens, 15);
+    assert_eq!(restored.ask("Which number?").await.unwrap(), "42");
+    assert_eq!(restored.usage().total_tokens, 30);
+
+    resumed.assert_async().await;
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_tokenizer_rejects_oversized_prompt() {
+    let mut server = Server::new_async().await;
+    let mock = server.mock("POST", "/chat/completions")
+        .expect(0)
+        .create_async()
+        .await;
+
+    let config = DeepSeekConfig::new("test-api-key")
+        .with_base_url(server.url())
+        .with_tokenizer(Tokenizer::approximate());
+    let client = DeepSeekClient::new(config).unwrap();
+
+    let builder = client.chat().add_user_message("word ".repeat(200_000));
+    assert!(builder.count_tokens() > 128_000);
+
+    let result = builder.send().await;
+    assert!(matches!(result, Err(DeepSeekError::InvalidParameter(_))));
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_budget_refuses_requests_over_cap() {
+    let mut server = Server::new_async().await;
+    let mock = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(mock_success_response().to_string())
+        .expect(2)
+        .create_async()
+        .await;
+
+    // $0.10 per prompt token, so the mocked 10 prompt tokens cost $1
+    let pricing = ModelPricing {
+        input_cache_hit: 100_000.0,
+        input_cache_miss: 100_000.0,
+        output: 0.0,
+        off_peak: Vec::new(),
+    };
+    let budget = Budget::new().with_limit(5.0).with_user_limit(1.0);
+    let config = DeepSeekConfig::new("test-api-key")
+        .with_base_url(server.url())
+        .with_model_pricing(Model::Chat, pricing)
+        .with_budget(budget.clone());
+    let client = DeepSeekClient::new(config).unwrap();
+
+    let builder = client.chat().add_user_message("Hello").with_max_tokens(10);
+    let estimate = builder.estimate_cost().unwrap();
+    assert!((estimate - builder.count_tokens() as f64 * 0.1).abs() < 1e-9);
+
+    builder.clone().with_user("alice").send().await.unwrap();
+    assert!((budget.spent_by("alice") - 1.0).abs() < 1e-9);
+
+    let refused = builder.clone().with_user("alice").send().await;
+    assert!(matches!(refused, Err(DeepSeekError::BudgetExceeded(_))));
+
+    builder.with_user("bob").send().await.unwrap();
+    assert!((budget.spent() - 2.0).abs() < 1e-9);
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_response_cache() {
+    let mut server = Server::new_async().await;
+    let mock = server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(mock_success_response().to_string())
+        .expect(4)
+        .create_async()
+        .await;
+
+    let config = DeepSeekConfig::new("test-api-key")
+        .with_base_url(server.url())
+        .with_response_cache(InMemoryCache::new(10));
+    let client = DeepSeekClient::new(config).unwrap();
+    let ask = |question: &str| {
+        client.chat().add_user_message(question).with_temperature(0.0).unwrap()
+    };
+
+    // Deterministic requests are served from the cache after the first
+    let first = ask("Hello").send().await.unwrap();
+    assert!(!first.cached);
+    let second = ask("Hello").send().await.unwrap();
+    assert!(second.cached);
+    assert_eq!(second.get_content(), first.get_content());
+
+    // Sampled requests are not cached by default
+    let sampled = client.chat().add_user_message("Hello").with_temperature(1.0).unwrap();
+    assert!(!sampled.send().await.unwrap().cached);
+
+    assert!(!ask("Hello").with_cache_mode(CacheMode::Bypass).send().await.unwrap().cached);
+    assert!(!ask("Hello").with_cache_mode(CacheMode::Refresh).send().await.unwrap().cached);
+    assert!(ask("Hello").with_cache_mode(CacheMode::OnlyIfCached).send().await.unwrap().cached);
+
+    let missing = ask("Goodbye").with_cache_mode(CacheMode::OnlyIfCached).send().await;
+    assert!(matches!(missing, Err(DeepSeekError::CacheMiss)));
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_list_models() {
+    let mut server = Server::new_async().await;
+    let _mock = server.mock("GET", "/models")
+        .match_header("authorization", "Bearer test-api-key")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(json!({
+            "object": "list",
+            "data": [
+                {"id": "deepseek-chat", "object": "model", "owned_by": "deepseek"},
+                {"id": "deepseek-reasoner", "object": "model", "owned_by": "deepseek"},
+                {"id": "deepseek-v4-preview", "object": "model", "owned_by": "deepseek"}
+            ]
+        }).to_string())
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = create_test_client(&server);
+    let models = client.list_models().await.expect("Listing models should succeed");
+
+    assert_eq!(models.len(), 3);
+    assert_eq!(models[1].model(), Model::Reasoner);
+
+    let custom = models[2].model();
+    assert_eq!(custom, Model::Custom("deepseek-v4-preview".to_string()));
+
+    let request = ChatCompletionRequest::from_user_message("Hi").with_model(custom);
+    let body = serde_json::to_value(&request).unwrap();
+    assert_eq!(body["model"], "deepseek-v4-preview");
+}
+
+#[tokio::test]
+async fn test_balance() {
+    let mut server = Server::new_async().await;
+    let _mock = server.mock("GET", "/user/balance")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(json!({
+            "is_available": true,
+            "balance_infos": [{
+                "currency": "USD",
+                "total_balance": "3.50",
+                "granted_balance": "0.00",
+                "topped_up_balance": "3.50"
+            }]
+        }).to_string())
+        .expect_at_least(1)
+        .create_async()
+        .await;
+
+    let config = DeepSeekConfig::new("test-api-key")
+        .with_base_url(server.url())
+        .with_low_balance_threshold(5.0);
+    let client = DeepSeekClient::new(config).unwrap();
+
+    let balance = client.balance().await.expect("Balance request should succeed");
+    assert!(balance.is_available);
+    assert_eq!(balance.currency("USD").unwrap().total_balance, 3.5);
+
+    let alerts = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counter = alerts.clone();
+    let watcher = client
+        .watch_balance()
+        .with_interval(std::time::Duration::from_millis(20))
+        .on_low_balance(move |_| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        })
+        .spawn();
+
+    tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+    assert!(watcher.is_running());
+    watcher.stop();
+
+    // Reported once while the balance stays low
+    assert_eq!(alerts.load(std::sync::atomic::Ordering::SeqCst), 1);
+}